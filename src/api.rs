@@ -1,4 +1,4 @@
+pub mod cache;
 pub mod client;
 pub mod model;
-
-pub use client::Client;
+pub mod provider;
@@ -0,0 +1,102 @@
+use crate::log_error;
+use async_std::fs;
+use failure::{format_err, Error};
+use std::{path::PathBuf, time::Duration};
+
+pub const TEAMS_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+pub const SCHEDULE_TTL: Duration = Duration::from_secs(5 * 60);
+pub const CONTENT_TTL: Duration = Duration::from_secs(3 * 60 * 60);
+
+pub fn run() {
+    if let Err(e) = clear() {
+        log_error(e.as_fail());
+        std::process::exit(1);
+    }
+}
+
+/// Stats API responses saved under the user cache directory
+pub struct Cache {
+    dir: PathBuf,
+}
+
+impl Cache {
+    /// `None` if the platform has no user cache directory
+    pub fn new() -> Option<Cache> {
+        dir().map(|dir| Cache { dir })
+    }
+
+    /// Cached data for `key`, unless it's older than `ttl`
+    pub async fn get(&self, key: &str, ttl: Duration) -> Option<Vec<u8>> {
+        let path = self.dir.join(key);
+
+        let modified = fs::metadata(&path).await.ok()?.modified().ok()?;
+        if modified.elapsed().ok()? >= ttl {
+            return None;
+        }
+
+        fs::read(&path).await.ok()
+    }
+
+    /// Save `data` for `key`. Failures are logged, as the data can always be
+    /// fetched again.
+    pub async fn set(&self, key: &str, data: &[u8]) {
+        let path = self.dir.join(key);
+
+        let result = async {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).await?;
+            }
+            fs::write(&path, data).await
+        }
+        .await;
+
+        if let Err(e) = result {
+            log_error(&Error::from(e).context(format!("Failed to cache {}", path.display())));
+        }
+    }
+}
+
+fn dir() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join("lazystream"))
+}
+
+fn clear() -> Result<(), Error> {
+    let dir = dir().ok_or_else(|| format_err!("Couldn't find the user cache directory"))?;
+
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir)
+            .map_err(|e| format_err!("Failed to remove {}: {}", dir.display(), e))?;
+    }
+
+    println!("Cache cleared: {}", dir.display());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_std::task;
+
+    #[test]
+    fn get_respects_ttl() {
+        let dir = std::env::temp_dir().join(format!("lazystream-cache-{}", std::process::id()));
+        let cache = Cache { dir: dir.clone() };
+
+        task::block_on(async {
+            assert_eq!(cache.get("nhl/teams.json", TEAMS_TTL).await, None);
+
+            cache.set("nhl/teams.json", b"[]").await;
+            assert_eq!(
+                cache.get("nhl/teams.json", TEAMS_TTL).await.as_deref(),
+                Some(&b"[]"[..])
+            );
+            assert_eq!(
+                cache.get("nhl/teams.json", Duration::from_secs(0)).await,
+                None
+            );
+        });
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
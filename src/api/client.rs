@@ -1,83 +1,112 @@
-use super::model::*;
-use crate::opt::Sport;
+use super::{
+    cache::{self, Cache},
+    model::*,
+    provider::{self, offline::Offline, remote::Remote, LeagueProvider},
+};
+use crate::opt::{Opt, Sport};
 use failure::Error;
-use stats_api::{MlbClient, NhlClient};
+use serde::de::DeserializeOwned;
+use std::{future::Future, time::Duration};
 
 pub struct Client {
-    mlb: MlbClient,
-    nhl: NhlClient,
+    provider: Box<dyn LeagueProvider>,
     sport: Sport,
+    cache: Option<Cache>,
 }
 
 impl Client {
-    pub fn new(sport: Sport) -> Self {
-        let mlb = MlbClient::default();
-        let nhl = NhlClient::default();
+    pub fn new(sport: Sport, opts: &Opt) -> Self {
+        let mut provider = provider::provider(sport);
+        if let Some(api_mirror) = &opts.api_mirror {
+            provider = Box::new(Remote::new(provider, sport, api_mirror));
+        }
+        if opts.uses_local_files() {
+            provider = Box::new(Offline::new(provider, sport, opts));
+        }
 
-        Client { mlb, nhl, sport }
+        // Local files & mirrors aren't cached, so they're replayed exactly
+        let cache = if opts.no_cache || opts.uses_local_files() || opts.api_mirror.is_some() {
+            None
+        } else {
+            Cache::new()
+        };
+
+        Client {
+            provider,
+            sport,
+            cache,
+        }
+    }
+
+    pub fn sport(&self) -> Sport {
+        self.sport
+    }
+
+    pub fn provider(&self) -> &dyn LeagueProvider {
+        self.provider.as_ref()
     }
 
     pub async fn get_todays_schedule(&self) -> Result<Schedule, Error> {
-        let serialized = match &self.sport {
-            Sport::Mlb => {
-                let schedule = self.mlb.get_todays_schedule().await?;
-                serde_json::to_vec(&schedule)?
-            }
-            Sport::Nhl => {
-                let schedule = self.nhl.get_todays_schedule().await?;
-                serde_json::to_vec(&schedule)?
-            }
-        };
+        let fetch = self.provider.schedule(None);
 
-        let schedule = serde_json::from_slice(&serialized)?;
-        Ok(schedule)
+        // Dated, so yesterday's schedule isn't served after midnight
+        let today = chrono::Local::now().naive_local().date();
+        let key = format!("schedule-today-{}.json", today.format("%Y-%m-%d"));
+        self.cached(&key, cache::SCHEDULE_TTL, fetch).await
     }
 
     pub async fn get_schedule_for(&self, date: chrono::NaiveDate) -> Result<Schedule, Error> {
-        let serialized = match &self.sport {
-            Sport::Mlb => {
-                let schedule = self.mlb.get_schedule_for(date).await?;
-                serde_json::to_vec(&schedule)?
-            }
-            Sport::Nhl => {
-                let schedule = self.nhl.get_schedule_for(date).await?;
-                serde_json::to_vec(&schedule)?
-            }
-        };
+        let fetch = self.provider.schedule(Some(date));
+
+        let key = format!("schedule-{}.json", date.format("%Y-%m-%d"));
+        self.cached(&key, cache::SCHEDULE_TTL, fetch).await
+    }
+
+    /// Schedule for `date` fetched again, bypassing the cache and updating it
+    pub async fn refresh_schedule_for(&self, date: chrono::NaiveDate) -> Result<Schedule, Error> {
+        let fetch = self.provider.schedule(Some(date));
 
-        let schedule = serde_json::from_slice(&serialized)?;
-        Ok(schedule)
+        let key = format!("schedule-{}.json", date.format("%Y-%m-%d"));
+        self.cached(&key, Duration::from_secs(0), fetch).await
     }
 
     pub async fn get_game_content(&self, game_pk: u64) -> Result<GameContentResponse, Error> {
-        let serialized = match &self.sport {
-            Sport::Mlb => {
-                let game_content = self.mlb.get_game_content(game_pk).await?;
-                serde_json::to_vec(&game_content)?
-            }
-            Sport::Nhl => {
-                let game_content = self.nhl.get_game_content(game_pk).await?;
-                serde_json::to_vec(&game_content)?
-            }
-        };
+        let fetch = self.provider.game_content(game_pk);
 
-        let game_content = serde_json::from_slice(&serialized)?;
-        Ok(game_content)
+        let key = format!("content-{}.json", game_pk);
+        self.cached(&key, cache::CONTENT_TTL, fetch).await
     }
 
     pub async fn get_teams(&self) -> Result<Vec<Team>, Error> {
-        let serialized = match &self.sport {
-            Sport::Mlb => {
-                let teams = self.mlb.get_all_teams().await?;
-                serde_json::to_vec(&teams)?
-            }
-            Sport::Nhl => {
-                let teams = self.nhl.get_teams().await?;
-                serde_json::to_vec(&teams)?
+        let fetch = self.provider.teams();
+
+        self.cached("teams.json", cache::TEAMS_TTL, fetch).await
+    }
+
+    /// Deserialize the cached response for `key`, or the response from `fetch`
+    /// if it isn't cached or is older than `ttl`
+    async fn cached<T, F>(&self, key: &str, ttl: Duration, fetch: F) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+        F: Future<Output = Result<Vec<u8>, Error>>,
+    {
+        let key = format!("{}/{}", self.sport.to_string().to_lowercase(), key);
+
+        if let Some(cache) = &self.cache {
+            if let Some(serialized) = cache.get(&key, ttl).await {
+                if let Ok(value) = serde_json::from_slice(&serialized) {
+                    return Ok(value);
+                }
             }
-        };
+        }
+
+        let serialized = fetch.await?;
+        let value = serde_json::from_slice(&serialized)?;
+
+        if let Some(cache) = &self.cache {
+            cache.set(&key, &serialized).await;
+        }
 
-        let teams = serde_json::from_slice(&serialized)?;
-        Ok(teams)
+        Ok(value)
     }
 }
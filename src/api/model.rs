@@ -1,3 +1,6 @@
+// Fields mirror the API responses, not all of them are read
+#![allow(dead_code)]
+
 use chrono::{DateTime, NaiveDate, Utc};
 use serde::Deserialize;
 
@@ -40,12 +43,113 @@ pub struct ScheduleGame {
     pub game_pk: u64,
     #[serde(default)]
     pub link: String,
+    #[serde(alias = "gameDate")]
     pub date: DateTime<Utc>,
     #[serde(default)]
     pub game_type: String,
     #[serde(default)]
     pub season: String,
+    #[serde(default)]
+    pub status: GameStatus,
     pub teams: ScheduleGameTeams,
+    #[serde(default, deserialize_with = "fail_as_none")]
+    pub linescore: Option<ScheduleGameLinescore>,
+}
+
+/// Where a game is at, from the `status` block of the schedule
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(from = "ScheduleGameStatus")]
+pub enum GameStatus {
+    #[default]
+    Preview,
+    Live,
+    Final,
+    Postponed,
+    Cancelled,
+}
+
+impl GameStatus {
+    /// Postponed & cancelled games won't be played today, so never go live
+    pub fn is_off(self) -> bool {
+        matches!(self, GameStatus::Postponed | GameStatus::Cancelled)
+    }
+}
+
+impl From<GameStatus> for &str {
+    fn from(status: GameStatus) -> Self {
+        match status {
+            GameStatus::Preview => "Preview",
+            GameStatus::Live => "Live",
+            GameStatus::Final => "Final",
+            GameStatus::Postponed => "Postponed",
+            GameStatus::Cancelled => "Cancelled",
+        }
+    }
+}
+
+impl std::fmt::Display for GameStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s: &str = (*self).into();
+        write!(f, "{}", s)
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+struct ScheduleGameStatus {
+    #[serde(default)]
+    abstract_game_state: String,
+    #[serde(default)]
+    detailed_state: String,
+}
+
+impl From<ScheduleGameStatus> for GameStatus {
+    fn from(status: ScheduleGameStatus) -> Self {
+        // Postponed MLB games are "Final", the detailed state tells them apart.
+        // Suspended games are finished another day.
+        let detailed = status.detailed_state.to_lowercase();
+        if detailed.starts_with("postponed") || detailed.starts_with("suspended") {
+            return GameStatus::Postponed;
+        }
+        if detailed.starts_with("cancelled") || detailed.starts_with("canceled") {
+            return GameStatus::Cancelled;
+        }
+
+        match status.abstract_game_state.as_str() {
+            "Live" => GameStatus::Live,
+            "Final" => GameStatus::Final,
+            _ => GameStatus::Preview,
+        }
+    }
+}
+
+/// Period (NHL) or inning (MLB) the game is in
+#[derive(Debug, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduleGameLinescore {
+    pub current_period_ordinal: Option<String>,
+    pub current_period_time_remaining: Option<String>,
+    pub current_inning_ordinal: Option<String>,
+    pub inning_state: Option<String>,
+}
+
+impl ScheduleGameLinescore {
+    /// e.g. `2nd 12:34`, `End 2nd` or `Top 5th`
+    pub fn progress(&self) -> Option<String> {
+        if let Some(inning) = &self.current_inning_ordinal {
+            return match &self.inning_state {
+                Some(state) => Some(format!("{} {}", state, inning)),
+                None => Some(inning.clone()),
+            };
+        }
+
+        let period = self.current_period_ordinal.as_ref()?;
+        match self.current_period_time_remaining.as_deref() {
+            Some("END") => Some(format!("End {}", period)),
+            Some("Final") | None => Some(period.clone()),
+            Some(remaining) => Some(format!("{} {}", period, remaining)),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -59,6 +163,7 @@ pub struct ScheduleGameTeams {
 #[serde(rename_all = "camelCase")]
 pub struct ScheduleGameTeam {
     pub score: Option<u8>,
+    #[serde(alias = "team")]
     pub detail: ScheduleGameTeamDetail,
 }
 
@@ -195,3 +300,58 @@ where
         Err(_) => Ok(None),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(abstract_game_state: &str, detailed_state: &str) -> GameStatus {
+        serde_json::from_value(serde_json::json!({
+            "abstractGameState": abstract_game_state,
+            "detailedState": detailed_state,
+        }))
+        .unwrap()
+    }
+
+    fn progress(linescore: serde_json::Value) -> Option<String> {
+        serde_json::from_value::<ScheduleGameLinescore>(linescore)
+            .unwrap()
+            .progress()
+    }
+
+    #[test]
+    fn linescore_progress() {
+        let nhl = |remaining: &str| {
+            progress(serde_json::json!({
+                "currentPeriod": 2,
+                "currentPeriodOrdinal": "2nd",
+                "currentPeriodTimeRemaining": remaining,
+            }))
+        };
+        assert_eq!(nhl("12:34").as_deref(), Some("2nd 12:34"));
+        assert_eq!(nhl("END").as_deref(), Some("End 2nd"));
+        assert_eq!(nhl("Final").as_deref(), Some("2nd"));
+
+        let mlb = progress(serde_json::json!({
+            "currentInning": 5,
+            "currentInningOrdinal": "5th",
+            "inningState": "Top",
+        }));
+        assert_eq!(mlb.as_deref(), Some("Top 5th"));
+
+        assert_eq!(progress(serde_json::json!({})), None);
+    }
+
+    #[test]
+    fn game_status() {
+        assert_eq!(status("Preview", "Scheduled"), GameStatus::Preview);
+        assert_eq!(status("Preview", "Pre-Game"), GameStatus::Preview);
+        assert_eq!(status("Live", "In Progress"), GameStatus::Live);
+        assert_eq!(status("Final", "Final"), GameStatus::Final);
+        assert_eq!(status("Final", "Game Over"), GameStatus::Final);
+        assert_eq!(status("Final", "Postponed"), GameStatus::Postponed);
+        assert_eq!(status("Preview", "Postponed"), GameStatus::Postponed);
+        assert_eq!(status("Final", "Suspended: Rain"), GameStatus::Postponed);
+        assert_eq!(status("Final", "Cancelled"), GameStatus::Cancelled);
+    }
+}
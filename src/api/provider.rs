@@ -0,0 +1,157 @@
+use super::model::GameContentEpgItem;
+use crate::{opt::Sport, stream::SHARED_CLIENT};
+use chrono::NaiveDate;
+use failure::{bail, Error, ResultExt};
+use futures::{future::LocalBoxFuture, AsyncReadExt};
+use isahc::{http, AsyncBody, Request};
+
+pub mod mlb;
+pub mod nhl;
+pub mod offline;
+pub mod remote;
+
+/// A league streams can be watched for. Responses are returned serialized as
+/// JSON, to be deserialized into the `api::model` types.
+pub trait LeagueProvider: Send + Sync {
+    /// Schedule for `date`, or today if `None`
+    fn schedule(&self, date: Option<NaiveDate>) -> LocalBoxFuture<'_, Result<Vec<u8>, Error>>;
+
+    fn teams(&self) -> LocalBoxFuture<'_, Result<Vec<u8>, Error>>;
+
+    fn game_content(&self, game_pk: u64) -> LocalBoxFuture<'_, Result<Vec<u8>, Error>>;
+
+    /// Title of the game content EPG entry that lists the streams, e.g. `NHLTV`
+    fn epg_title(&self) -> &'static str;
+
+    /// Id the host knows the stream of an EPG item by
+    fn stream_id(&self, item: &GameContentEpgItem) -> Option<String>;
+
+    /// `league=` value passed to the host
+    fn host_league(&self) -> &'static str;
+
+    /// Icon used for XMLTV channels
+    fn icon(&self) -> &'static str;
+}
+
+/// Provider registered for `sport`
+pub fn provider(sport: Sport) -> Box<dyn LeagueProvider> {
+    match sport {
+        Sport::Mlb => Box::new(mlb::Mlb::default()),
+        Sport::Nhl => Box::new(nhl::Nhl::default()),
+    }
+}
+
+async fn fetch(url: String) -> Result<Vec<u8>, Error> {
+    let uri = url.parse::<http::Uri>().context("Failed to build URI")?;
+    let request = Request::builder()
+        .method("GET")
+        .uri(uri)
+        .body(AsyncBody::empty())
+        .unwrap();
+
+    let resp = SHARED_CLIENT.send_async(request).await?;
+    if !resp.status().is_success() {
+        bail!("Request to {} failed with {}", url, resp.status());
+    }
+
+    let mut body = vec![];
+    resp.into_body()
+        .read_to_end(&mut body)
+        .await
+        .context("Failed to read response body")?;
+
+    Ok(body)
+}
+
+/// `url` of a stats API schedule endpoint for `date`
+fn schedule_url(url: &str, date: Option<NaiveDate>) -> String {
+    match date {
+        Some(date) => format!("{}&date={}", url, date.format("%Y-%m-%d")),
+        None => url.to_owned(),
+    }
+}
+
+/// Day of the schedule at `url`, fetched directly so it includes what the
+/// request hydrates, e.g. the linescore
+async fn fetch_schedule(url: &str, date: Option<NaiveDate>) -> Result<Vec<u8>, Error> {
+    let response = fetch(schedule_url(url, date)).await?;
+    let date = date.unwrap_or_else(|| chrono::Local::now().naive_local().date());
+
+    first_day(&response, date)
+}
+
+/// First day of a schedule response, or an empty day for `date` if nothing
+/// is scheduled
+fn first_day(response: &[u8], date: NaiveDate) -> Result<Vec<u8>, Error> {
+    let mut response: serde_json::Value = serde_json::from_slice(response)?;
+
+    let day = match response.get_mut("dates").and_then(|dates| dates.get_mut(0)) {
+        Some(day) => day.take(),
+        None => serde_json::json!({ "date": date, "games": [] }),
+    };
+
+    Ok(serde_json::to_vec(&day)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::model::Schedule;
+
+    #[test]
+    fn stream_ids_per_league() {
+        let item: GameContentEpgItem = serde_json::from_value(serde_json::json!({
+            "mediaFeedType": "HOME",
+            "id": 12345,
+            "mediaPlaybackId": "67890",
+        }))
+        .unwrap();
+
+        let mlb = provider(Sport::Mlb);
+        assert_eq!(mlb.stream_id(&item).as_deref(), Some("12345"));
+        assert_eq!((mlb.epg_title(), mlb.host_league()), ("MLBTV", "MLB"));
+
+        let nhl = provider(Sport::Nhl);
+        assert_eq!(nhl.stream_id(&item).as_deref(), Some("67890"));
+        assert_eq!((nhl.epg_title(), nhl.host_league()), ("NHLTV", "nhl"));
+    }
+
+    #[test]
+    fn schedules_request_linescore() {
+        let date = NaiveDate::from_ymd_opt(2021, 4, 1);
+
+        assert_eq!(
+            schedule_url(nhl::SCHEDULE_URL, date),
+            "https://statsapi.web.nhl.com/api/v1/schedule?hydrate=linescore&date=2021-04-01"
+        );
+        assert_eq!(
+            schedule_url(mlb::SCHEDULE_URL, date),
+            "https://statsapi.mlb.com/api/v1/schedule?sportId=1&hydrate=linescore&date=2021-04-01"
+        );
+    }
+
+    #[test]
+    fn first_day_of_schedule_response() {
+        let response = include_bytes!("../../tests/fixtures/statsapi/nhl_schedule.json");
+        let date = NaiveDate::from_ymd_opt(2021, 4, 1).unwrap();
+
+        let schedule: Schedule =
+            serde_json::from_slice(&first_day(response, date).unwrap()).unwrap();
+        assert_eq!(schedule.date, date);
+
+        let game = &schedule.games[0];
+        assert_eq!(game.game_pk, 2020020700);
+        assert_eq!(game.teams.home.detail.name, "Boston Bruins");
+        assert_eq!(
+            (game.teams.away.score, game.teams.home.score),
+            (Some(2), Some(1))
+        );
+        let progress = game.linescore.as_ref().and_then(|l| l.progress());
+        assert_eq!(progress.as_deref(), Some("2nd 12:34"));
+
+        let empty = br#"{ "totalGames": 0, "dates": [] }"#;
+        let schedule: Schedule = serde_json::from_slice(&first_day(empty, date).unwrap()).unwrap();
+        assert_eq!(schedule.date, date);
+        assert!(schedule.games.is_empty());
+    }
+}
@@ -0,0 +1,55 @@
+use super::LeagueProvider;
+use crate::api::model::GameContentEpgItem;
+use chrono::NaiveDate;
+use failure::Error;
+use futures::future::{FutureExt, LocalBoxFuture};
+use stats_api::MlbClient;
+
+/// Schedule with the score & inning of each game
+pub const SCHEDULE_URL: &str =
+    "https://statsapi.mlb.com/api/v1/schedule?sportId=1&hydrate=linescore";
+
+const ICON: &str = "https://upload.wikimedia.org/wikipedia/en/thumb/a/a6/Major_League_Baseball_logo.svg/1200px-Major_League_Baseball_logo.svg.png";
+
+#[derive(Default)]
+pub struct Mlb {
+    client: MlbClient,
+}
+
+impl LeagueProvider for Mlb {
+    fn schedule(&self, date: Option<NaiveDate>) -> LocalBoxFuture<'_, Result<Vec<u8>, Error>> {
+        super::fetch_schedule(SCHEDULE_URL, date).boxed_local()
+    }
+
+    fn teams(&self) -> LocalBoxFuture<'_, Result<Vec<u8>, Error>> {
+        async move {
+            let teams = self.client.get_all_teams().await?;
+            Ok(serde_json::to_vec(&teams)?)
+        }
+        .boxed_local()
+    }
+
+    fn game_content(&self, game_pk: u64) -> LocalBoxFuture<'_, Result<Vec<u8>, Error>> {
+        async move {
+            let game_content = self.client.get_game_content(game_pk).await?;
+            Ok(serde_json::to_vec(&game_content)?)
+        }
+        .boxed_local()
+    }
+
+    fn epg_title(&self) -> &'static str {
+        "MLBTV"
+    }
+
+    fn stream_id(&self, item: &GameContentEpgItem) -> Option<String> {
+        item.id.map(|id| id.to_string())
+    }
+
+    fn host_league(&self) -> &'static str {
+        "MLB"
+    }
+
+    fn icon(&self) -> &'static str {
+        ICON
+    }
+}
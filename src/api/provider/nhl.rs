@@ -0,0 +1,54 @@
+use super::LeagueProvider;
+use crate::api::model::GameContentEpgItem;
+use chrono::NaiveDate;
+use failure::Error;
+use futures::future::{FutureExt, LocalBoxFuture};
+use stats_api::NhlClient;
+
+/// Schedule with the score & period of each game
+pub const SCHEDULE_URL: &str = "https://statsapi.web.nhl.com/api/v1/schedule?hydrate=linescore";
+
+const ICON: &str = "https://upload.wikimedia.org/wikipedia/en/thumb/3/3a/05_NHL_Shield.svg/1200px-05_NHL_Shield.svg.png";
+
+#[derive(Default)]
+pub struct Nhl {
+    client: NhlClient,
+}
+
+impl LeagueProvider for Nhl {
+    fn schedule(&self, date: Option<NaiveDate>) -> LocalBoxFuture<'_, Result<Vec<u8>, Error>> {
+        super::fetch_schedule(SCHEDULE_URL, date).boxed_local()
+    }
+
+    fn teams(&self) -> LocalBoxFuture<'_, Result<Vec<u8>, Error>> {
+        async move {
+            let teams = self.client.get_teams().await?;
+            Ok(serde_json::to_vec(&teams)?)
+        }
+        .boxed_local()
+    }
+
+    fn game_content(&self, game_pk: u64) -> LocalBoxFuture<'_, Result<Vec<u8>, Error>> {
+        async move {
+            let game_content = self.client.get_game_content(game_pk).await?;
+            Ok(serde_json::to_vec(&game_content)?)
+        }
+        .boxed_local()
+    }
+
+    fn epg_title(&self) -> &'static str {
+        "NHLTV"
+    }
+
+    fn stream_id(&self, item: &GameContentEpgItem) -> Option<String> {
+        item.media_playback_id.clone()
+    }
+
+    fn host_league(&self) -> &'static str {
+        "nhl"
+    }
+
+    fn icon(&self) -> &'static str {
+        ICON
+    }
+}
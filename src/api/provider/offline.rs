@@ -0,0 +1,148 @@
+use super::LeagueProvider;
+use crate::{
+    api::model::GameContentEpgItem,
+    opt::{Opt, Sport},
+};
+use async_std::fs;
+use chrono::NaiveDate;
+use failure::{format_err, Error};
+use futures::future::{FutureExt, LocalBoxFuture};
+use std::path::{Path, PathBuf};
+
+/// Serves responses from local JSON files where they're given, and from
+/// `inner` otherwise. Files are in the format `lazystream dump` saves.
+pub struct Offline {
+    inner: Box<dyn LeagueProvider>,
+    schedule_file: Option<PathBuf>,
+    teams_file: Option<PathBuf>,
+    content_dir: Option<PathBuf>,
+}
+
+impl Offline {
+    pub fn new(inner: Box<dyn LeagueProvider>, sport: Sport, opts: &Opt) -> Self {
+        let path = |path: &Option<PathBuf>| path.as_deref().map(|path| for_sport(path, sport));
+
+        Offline {
+            inner,
+            schedule_file: path(&opts.schedule_file),
+            teams_file: path(&opts.teams_file),
+            content_dir: path(&opts.content_dir),
+        }
+    }
+}
+
+/// `path` with any `{sport}` placeholder replaced, e.g. `nhl`
+fn for_sport(path: &Path, sport: Sport) -> PathBuf {
+    let sport = sport.to_string().to_lowercase();
+    PathBuf::from(path.to_string_lossy().replace("{sport}", &sport))
+}
+
+async fn read(path: &Path) -> Result<Vec<u8>, Error> {
+    fs::read(path)
+        .await
+        .map_err(|e| format_err!("Failed to read {}: {}", path.display(), e))
+}
+
+impl LeagueProvider for Offline {
+    fn schedule(&self, date: Option<NaiveDate>) -> LocalBoxFuture<'_, Result<Vec<u8>, Error>> {
+        match &self.schedule_file {
+            Some(path) => read(path).boxed_local(),
+            None => self.inner.schedule(date),
+        }
+    }
+
+    fn teams(&self) -> LocalBoxFuture<'_, Result<Vec<u8>, Error>> {
+        match &self.teams_file {
+            Some(path) => read(path).boxed_local(),
+            None => self.inner.teams(),
+        }
+    }
+
+    fn game_content(&self, game_pk: u64) -> LocalBoxFuture<'_, Result<Vec<u8>, Error>> {
+        match &self.content_dir {
+            Some(dir) => {
+                async move { read(&dir.join(format!("{}.json", game_pk))).await }.boxed_local()
+            }
+            None => self.inner.game_content(game_pk),
+        }
+    }
+
+    fn epg_title(&self) -> &'static str {
+        self.inner.epg_title()
+    }
+
+    fn stream_id(&self, item: &GameContentEpgItem) -> Option<String> {
+        self.inner.stream_id(item)
+    }
+
+    fn host_league(&self) -> &'static str {
+        self.inner.host_league()
+    }
+
+    fn icon(&self) -> &'static str {
+        self.inner.icon()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{opt::FeedType, stream::LazyStream};
+    use async_std::task;
+    use structopt::StructOpt;
+
+    fn fixture_opts(sport: &str) -> crate::opt::Opt {
+        let fixtures = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/api/{sport}");
+        crate::opt::Opt::from_iter(&[
+            "lazystream",
+            "--sport",
+            sport,
+            "--schedule-file",
+            &format!("{}/schedule.json", fixtures),
+            "--teams-file",
+            &format!("{}/teams.json", fixtures),
+            "--content-dir",
+            &format!("{}/content", fixtures),
+            "host",
+        ])
+    }
+
+    #[test]
+    fn replays_dumped_day() {
+        let opts = fixture_opts("nhl");
+
+        task::block_on(async {
+            let lazy_stream = LazyStream::new(&opts).await.unwrap();
+            let team = lazy_stream.find_team("vgk").unwrap();
+            let mut game = lazy_stream.games_with_team(&team).remove(0);
+
+            assert_eq!(lazy_stream.date().to_string(), "2021-04-01");
+            assert_eq!(game.home_team.team_name, "Bruins");
+            assert_eq!(game.game_number, None);
+
+            let streams = game.streams().await.unwrap();
+            assert_eq!(
+                streams.keys().copied().collect::<Vec<_>>(),
+                vec![FeedType::Home, FeedType::Away]
+            );
+        });
+    }
+
+    #[test]
+    fn numbers_doubleheaders() {
+        let opts = fixture_opts("mlb");
+
+        task::block_on(async {
+            let lazy_stream = LazyStream::new(&opts).await.unwrap();
+            let team = lazy_stream.find_team("NYY").unwrap();
+            let games = lazy_stream.games_with_team(&team);
+
+            assert_eq!(
+                games
+                    .iter()
+                    .map(|game| (game.game_pk, game.game_number))
+                    .collect::<Vec<_>>(),
+                vec![(634001, Some(1)), (634002, Some(2))]
+            );
+        });
+    }
+}
@@ -0,0 +1,60 @@
+use super::{fetch, LeagueProvider};
+use crate::{api::model::GameContentEpgItem, opt::Sport};
+use chrono::NaiveDate;
+use failure::Error;
+use futures::future::{FutureExt, LocalBoxFuture};
+
+/// Fetches responses from a stats API mirror in the layout `lazystream dump`
+/// saves, e.g. `{base_url}/nhl/schedule.json?date=2021-04-01`. A dumped
+/// directory served over HTTP works as one.
+pub struct Remote {
+    inner: Box<dyn LeagueProvider>,
+    base_url: String,
+}
+
+impl Remote {
+    pub fn new(inner: Box<dyn LeagueProvider>, sport: Sport, base_url: &str) -> Self {
+        Remote {
+            inner,
+            base_url: format!(
+                "{}/{}",
+                base_url.trim_end_matches('/'),
+                sport.to_string().to_lowercase()
+            ),
+        }
+    }
+}
+
+impl LeagueProvider for Remote {
+    fn schedule(&self, date: Option<NaiveDate>) -> LocalBoxFuture<'_, Result<Vec<u8>, Error>> {
+        let query = date
+            .map(|date| format!("?date={}", date.format("%Y-%m-%d")))
+            .unwrap_or_default();
+
+        fetch(format!("{}/schedule.json{}", self.base_url, query)).boxed_local()
+    }
+
+    fn teams(&self) -> LocalBoxFuture<'_, Result<Vec<u8>, Error>> {
+        fetch(format!("{}/teams.json", self.base_url)).boxed_local()
+    }
+
+    fn game_content(&self, game_pk: u64) -> LocalBoxFuture<'_, Result<Vec<u8>, Error>> {
+        fetch(format!("{}/content/{}.json", self.base_url, game_pk)).boxed_local()
+    }
+
+    fn epg_title(&self) -> &'static str {
+        self.inner.epg_title()
+    }
+
+    fn stream_id(&self, item: &GameContentEpgItem) -> Option<String> {
+        self.inner.stream_id(item)
+    }
+
+    fn host_league(&self) -> &'static str {
+        self.inner.host_league()
+    }
+
+    fn icon(&self) -> &'static str {
+        self.inner.icon()
+    }
+}
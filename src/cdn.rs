@@ -0,0 +1,201 @@
+use crate::{
+    hls::{MasterPlaylist, MediaPlaylist},
+    host,
+    opt::{Cdn, Quality},
+    stream::{self, Stream, SHARED_CLIENT},
+};
+use failure::{bail, format_err, Error, ResultExt};
+use futures::{future, AsyncReadExt};
+use isahc::{
+    config::{Configurable, SslOption},
+    http, AsyncBody, Request,
+};
+use once_cell::sync::Lazy;
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// How long the faster CDN is used before both are timed again
+const PICK_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// CDN picked by the last timing, and when
+static PICKED: Lazy<Mutex<Option<(Cdn, Instant)>>> = Lazy::new(|| Mutex::new(None));
+
+/// CDN picked by timing them, unless that was too long ago
+pub fn picked() -> Option<Cdn> {
+    PICKED
+        .lock()
+        .unwrap()
+        .filter(|(_, picked_at)| picked_at.elapsed() < PICK_TTL)
+        .map(|(cdn, _)| cdn)
+}
+
+/// Faster CDN for `--cdn auto`, or `None` if neither has `stream`. The pick
+/// is reused for other streams for a while.
+pub async fn fastest(stream: &Stream) -> Option<Cdn> {
+    if let Some(cdn) = picked() {
+        return Some(cdn);
+    }
+
+    let cdn = pick(stream).await?;
+    *PICKED.lock().unwrap() = Some((cdn, Instant::now()));
+    Some(cdn)
+}
+
+/// Time each CDN resolving the master link of `stream`, then downloading its
+/// master playlist, the media playlist of the best variant and one segment
+async fn pick(stream: &Stream) -> Option<Cdn> {
+    let timings = future::join_all(
+        Cdn::ALL
+            .iter()
+            .map(|cdn| async move { (*cdn, time(stream, *cdn).await) }),
+    )
+    .await;
+
+    let (cdn, elapsed) = timings
+        .iter()
+        .filter_map(|(cdn, timing)| timing.as_ref().ok().map(|elapsed| (*cdn, *elapsed)))
+        .min_by_key(|(_, elapsed)| *elapsed)?;
+
+    let timings: Vec<_> = timings
+        .iter()
+        .map(|(cdn, timing)| match timing {
+            Ok(elapsed) => format!("{} took {} ms", cdn, elapsed.as_millis()),
+            Err(e) => format!("{} failed, {}", cdn, e),
+        })
+        .collect();
+    println!(
+        "Using CDN {} ({} ms): {}",
+        cdn,
+        elapsed.as_millis(),
+        timings.join(", ")
+    );
+
+    Some(cdn)
+}
+
+async fn time(stream: &Stream, cdn: Cdn) -> Result<Duration, Error> {
+    let start = Instant::now();
+
+    let (_, master_link) =
+        host::master_link(stream.hosts(), |host| stream.link_on(host, cdn)).await?;
+    let master_playlist =
+        MasterPlaylist::parse(&master_link, &stream::get_m3u8(&master_link).await?)?;
+
+    let variant = master_playlist
+        .variant(Quality::Best)
+        .ok_or_else(|| format_err!("Master playlist doesn't contain any variant streams"))?;
+    let media_playlist =
+        MediaPlaylist::parse(&variant.uri, &stream::get_m3u8(&variant.uri).await?)?;
+
+    let segment = match media_playlist.segments.last() {
+        Some(segment) => segment,
+        None => bail!("Media playlist doesn't contain any segments"),
+    };
+    download(&segment.uri).await?;
+
+    Ok(start.elapsed())
+}
+
+async fn download(url: &str) -> Result<Vec<u8>, Error> {
+    let uri = url.parse::<http::Uri>().context("Failed to build URI")?;
+    let request = Request::builder()
+        .method("GET")
+        .uri(uri)
+        .ssl_options(SslOption::DANGER_ACCEPT_INVALID_CERTS)
+        .body(AsyncBody::empty())
+        .unwrap();
+
+    let resp = SHARED_CLIENT.send_async(request).await?;
+    if !resp.status().is_success() {
+        bail!("Request to {} failed with {}", url, resp.status());
+    }
+
+    let mut body = resp.into_body();
+    let mut data = vec![];
+    body.read_to_end(&mut data)
+        .await
+        .context("Failed to read response body")?;
+
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::opt::{FeedType, Hosts};
+    use async_std::task;
+    use chrono::Utc;
+    use std::{net::SocketAddr, thread};
+    use tiny_http::{Response, Server};
+
+    /// Stub host with a stream on both CDNs. Segments on `slow` take a while
+    /// to download, and `missing` has no media playlist.
+    fn stub_host(slow: &'static str, missing: &'static str) -> SocketAddr {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let addr = server.server_addr();
+
+        // Each request on its own thread, so the slow CDN doesn't hold up the other
+        thread::spawn(move || {
+            for request in server.incoming_requests() {
+                thread::spawn(move || {
+                    let url = request.url().to_owned();
+                    let cdn = if url.contains("akc") { "akc" } else { "l3c" };
+
+                    let body = if url.starts_with("/getM3U8.php") {
+                        format!("http://{}/{}/master.m3u8", addr, cdn)
+                    } else if url.ends_with("/master.m3u8") {
+                        "#EXTM3U\n#EXT-X-STREAM-INF:BANDWIDTH=800000\nmedia.m3u8\n".to_owned()
+                    } else if url.ends_with("/media.m3u8") && cdn != missing {
+                        "#EXTM3U\n#EXT-X-TARGETDURATION:5\n#EXTINF:5.0,\nsegment.ts\n".to_owned()
+                    } else if url.ends_with("/segment.ts") {
+                        if cdn == slow {
+                            thread::sleep(Duration::from_millis(500));
+                        }
+                        "segment".to_owned()
+                    } else {
+                        let _ = request.respond(Response::from_string("").with_status_code(404));
+                        return;
+                    };
+
+                    let _ = request.respond(Response::from_string(body));
+                });
+            }
+        });
+
+        addr
+    }
+
+    /// The stub host, which hands out plain http links
+    fn stub_hosts(addr: SocketAddr) -> Hosts {
+        let mut hosts: Hosts = format!("http://{}", addr).parse().unwrap();
+        hosts.link_scheme = "http".to_owned();
+        hosts
+    }
+
+    fn stub_stream(addr: SocketAddr) -> Stream {
+        Stream::new(
+            "1".to_owned(),
+            stub_hosts(addr),
+            "nhl",
+            FeedType::Home,
+            Utc::now().naive_utc().date(),
+        )
+    }
+
+    #[test]
+    fn picks_faster_cdn() {
+        let stream = stub_stream(stub_host("akc", ""));
+        assert_eq!(task::block_on(pick(&stream)), Some(Cdn::L3c));
+
+        let stream = stub_stream(stub_host("l3c", ""));
+        assert_eq!(task::block_on(pick(&stream)), Some(Cdn::Akc));
+    }
+
+    #[test]
+    fn skips_failing_cdn() {
+        let stream = stub_stream(stub_host("akc", "l3c"));
+        assert_eq!(task::block_on(pick(&stream)), Some(Cdn::Akc));
+    }
+}
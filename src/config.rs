@@ -0,0 +1,1264 @@
+use crate::{
+    log_error,
+    opt::{
+        self, CastCommand, Command, ConfigCommand, FeedType, GenerateCommand, Opt, PlayCommand,
+        RecordCommand,
+    },
+};
+use failure::{bail, format_err, Error, ResultExt};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    ffi::OsString,
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+    process,
+    str::FromStr,
+};
+use structopt::{clap::ArgMatches, StructOpt};
+
+/// Defaults for command line options, read from `config.toml` in the user
+/// config directory. Keys are named after the long flags, with options of
+/// subcommands under a section of the same name, e.g. `[record]`. Named
+/// profiles under `[profile.NAME]` hold the same keys, and are merged over the
+/// rest of the file with `--profile NAME`. Options picking the games, e.g.
+/// `--date` or the teams, only come from the command line.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Config {
+    sport: Option<String>,
+    days: Option<u32>,
+    cdn: Option<String>,
+    quality: Option<String>,
+    disable_retry: Option<bool>,
+    give_up_after: Option<u32>,
+    spoilers: Option<bool>,
+    no_cache: Option<bool>,
+    schedule_file: Option<PathBuf>,
+    teams_file: Option<PathBuf>,
+    content_dir: Option<PathBuf>,
+    api_mirror: Option<String>,
+    host: Option<String>,
+    link_scheme: Option<String>,
+    select: SelectConfig,
+    play: PlayConfig,
+    record: RecordConfig,
+    cast: CastConfig,
+    generate: GenerateConfig,
+    serve: ServeConfig,
+    watch: WatchConfig,
+    #[serde(skip_serializing)]
+    profile: BTreeMap<String, Config>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+struct SelectConfig {
+    resolve: Option<bool>,
+    list_qualities: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+struct PlayConfig {
+    restart: Option<bool>,
+    feed_type: Option<String>,
+    proxy: Option<String>,
+    offset: Option<String>,
+    passthrough: Option<bool>,
+    custom_player: Option<PathBuf>,
+    priority: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+struct RecordConfig {
+    output: Option<PathBuf>,
+    restart: Option<bool>,
+    feed_type: Option<String>,
+    proxy: Option<String>,
+    offset: Option<String>,
+    audio_source: Option<String>,
+    backend: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+struct CastConfig {
+    chromecast_host: Option<String>,
+    restart: Option<bool>,
+    feed_type: Option<String>,
+    proxy: Option<String>,
+    offset: Option<String>,
+    audio_source: Option<String>,
+    priority: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+struct GenerateConfig {
+    start_channel: Option<u32>,
+    channel_prefix: Option<String>,
+    exclude_feeds: Option<Vec<String>>,
+    start_prepend: Option<u16>,
+    trim: Option<bool>,
+    server: Option<String>,
+    exclude_postponed: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+struct ServeConfig {
+    bind: Option<String>,
+    advertise_url: Option<String>,
+    proxy_playlist: Option<bool>,
+    ttl: Option<u64>,
+    hdhomerun: Option<bool>,
+    start_channel: Option<u32>,
+    channel_prefix: Option<String>,
+    exclude_feeds: Option<Vec<String>>,
+    start_prepend: Option<u16>,
+    trim: Option<bool>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+struct WatchConfig {
+    format: Option<String>,
+}
+
+pub fn run(opts: Opt) {
+    if let Err(e) = process(&opts) {
+        log_error(e.as_fail());
+        process::exit(1);
+    }
+}
+
+fn process(opts: &Opt) -> Result<(), Error> {
+    match &opts.command {
+        Command::Config {
+            command: ConfigCommand::Show { command },
+        } => {
+            let opts = &if command.is_empty() {
+                opts.clone()
+            } else {
+                command_opts(opts, command)?
+            };
+            let path = path(opts);
+            let config = load(opts)?.unwrap_or_default();
+
+            if let (true, Some(profile)) = (path.is_file(), &opts.profile) {
+                println!(
+                    "# Loaded from {}, with profile {}\n",
+                    path.display(),
+                    profile
+                );
+            } else if path.is_file() {
+                println!("# Loaded from {}\n", path.display());
+            } else {
+                println!("# No config file at {}\n", path.display());
+            }
+            print!("{}", toml::to_string(&config.effective(opts))?);
+        }
+        _ => unreachable!(),
+    }
+
+    Ok(())
+}
+
+/// Options of the command line `command` of `config show`, with the
+/// `--config` & `--profile` given to `config show` unless it has its own
+fn command_opts(opts: &Opt, command: &[String]) -> Result<Opt, Error> {
+    let mut args: Vec<OsString> = vec!["lazystream".into()];
+    args.extend(command.iter().map(OsString::from));
+
+    let has = |name: &str| command.iter().any(|arg| arg == name);
+    if let (Some(config), false) = (&opts.config, has("--config")) {
+        args.extend(vec!["--config".into(), config.clone().into_os_string()]);
+    }
+    if let (Some(profile), false) = (&opts.profile, has("--profile")) {
+        args.extend(vec!["--profile".into(), profile.into()]);
+    }
+
+    let matches = Opt::clap().get_matches_from_safe(args)?;
+    let mut opts = Opt::from_clap(&matches);
+    apply(&mut opts, &matches)?;
+
+    Ok(opts)
+}
+
+/// `--config`, or `lazystream/config.toml` in the user config directory
+pub fn path(opts: &Opt) -> PathBuf {
+    opts.config.clone().unwrap_or_else(|| {
+        dirs::config_dir()
+            .unwrap_or_default()
+            .join("lazystream")
+            .join("config.toml")
+    })
+}
+
+/// Config file, if there is one, with the `--profile` merged over it. A file
+/// given with `--config` must exist, as must a file with the profile.
+pub fn load(opts: &Opt) -> Result<Option<Config>, Error> {
+    let path = path(opts);
+    if opts.config.is_none() && !path.is_file() {
+        if let Some(profile) = &opts.profile {
+            bail!(
+                "Profile {} does not exist, there is no config file at {}",
+                profile,
+                path.display()
+            );
+        }
+        return Ok(None);
+    }
+
+    let config = Config::from_file(&path)?;
+    match &opts.profile {
+        Some(profile) => config.with_profile(profile).map(Some),
+        None => Ok(Some(config)),
+    }
+}
+
+/// Fill in every option not given on the command line from the config file
+pub fn apply(opts: &mut Opt, matches: &ArgMatches) -> Result<(), Error> {
+    if let Some(config) = load(opts)? {
+        config.apply(opts, &Given::new(matches))?;
+    }
+
+    Ok(())
+}
+
+impl Config {
+    fn from_file(path: &Path) -> Result<Config, Error> {
+        let config = fs::read_to_string(path)
+            .context(format!("Failed to read config file {}", path.display()))?;
+
+        Ok(toml::from_str(&config).context(format!("Invalid config file {}", path.display()))?)
+    }
+
+    /// Values of profile `name` in place of the ones of the base config
+    fn with_profile(mut self, name: &str) -> Result<Config, Error> {
+        let profile = match self.profile.remove(name) {
+            Some(profile) => profile,
+            None if self.profile.is_empty() => {
+                bail!("Profile {} does not exist, the config file has none", name)
+            }
+            None => {
+                let names: Vec<_> = self.profile.keys().cloned().collect();
+                bail!(
+                    "Profile {} does not exist, use one of: {}",
+                    name,
+                    names.join(", ")
+                );
+            }
+        };
+        if !profile.profile.is_empty() {
+            bail!("Profile {} can't contain other profiles", name);
+        }
+
+        let mut config = serde_json::to_value(&self)?;
+        merge(&mut config, serde_json::to_value(&profile)?);
+        Ok(serde_json::from_value(config)?)
+    }
+
+    fn apply(&self, opts: &mut Opt, given: &Given) -> Result<(), Error> {
+        set(
+            &mut opts.sports,
+            parse("sport", &self.sport)?,
+            given.any("sports"),
+        );
+        // '--days' can't be combined with '--date-range'
+        set_opt(
+            &mut opts.days,
+            self.days,
+            given.any("days") || given.any("date-range"),
+        );
+        set(&mut opts.cdn, parse("cdn", &self.cdn)?, given.any("cdn"));
+        set_opt(
+            &mut opts.quality,
+            parse("quality", &self.quality)?,
+            given.any("quality"),
+        );
+        set(
+            &mut opts.disable_retry,
+            self.disable_retry,
+            given.any("disable-retry") || opts.retry,
+        );
+        set(
+            &mut opts.give_up_after,
+            self.give_up_after,
+            given.any("give-up-after"),
+        );
+        set(
+            &mut opts.spoilers,
+            self.spoilers,
+            given.any("spoilers") || opts.no_spoilers,
+        );
+        set(
+            &mut opts.no_cache,
+            self.no_cache,
+            given.any("no-cache") || opts.cache,
+        );
+        set_opt(
+            &mut opts.schedule_file,
+            self.schedule_file.clone(),
+            given.any("schedule-file"),
+        );
+        set_opt(
+            &mut opts.teams_file,
+            self.teams_file.clone(),
+            given.any("teams-file"),
+        );
+        set_opt(
+            &mut opts.content_dir,
+            self.content_dir.clone(),
+            given.any("content-dir"),
+        );
+        set_opt(
+            &mut opts.api_mirror,
+            self.api_mirror.clone(),
+            given.any("api-mirror"),
+        );
+        set(
+            &mut opts.host,
+            parse("host", &self.host)?,
+            given.any("host"),
+        );
+        if let Some(scheme) = &self.link_scheme {
+            if scheme != "https" && scheme != "http" {
+                bail!(
+                    "Invalid link-scheme '{}' in config, use https or http",
+                    scheme
+                );
+            }
+        }
+        set(
+            &mut opts.link_scheme,
+            self.link_scheme.clone(),
+            given.any("link-scheme"),
+        );
+
+        match &mut opts.command {
+            Command::Select {
+                resolve,
+                no_resolve,
+                list_qualities,
+                no_list_qualities,
+            } => {
+                set(
+                    resolve,
+                    self.select.resolve,
+                    given.any("resolve") || *no_resolve,
+                );
+                set(
+                    list_qualities,
+                    self.select.list_qualities,
+                    given.any("list-qualities") || *no_list_qualities,
+                );
+                Ok(())
+            }
+            Command::Play { command } => self.play.apply(command, given),
+            Command::Record { command } => self.record.apply(command, given),
+            Command::Cast { command } => self.cast.apply(command, given),
+            Command::Generate { command } => self.generate.apply(command, given),
+            Command::Serve {
+                bind,
+                advertise_url,
+                proxy_playlist,
+                no_proxy_playlist,
+                ttl,
+                hdhomerun,
+                no_hdhomerun,
+                start_channel,
+                channel_prefix,
+                exclude_feeds,
+                start_prepend,
+                trim,
+                no_trim,
+                ..
+            } => {
+                let serve = &self.serve;
+                set(bind, parse("bind", &serve.bind)?, given.any("bind"));
+                set_opt(
+                    advertise_url,
+                    serve.advertise_url.clone(),
+                    given.any("advertise-url"),
+                );
+                set(
+                    proxy_playlist,
+                    serve.proxy_playlist,
+                    given.any("proxy-playlist") || *no_proxy_playlist,
+                );
+                set(ttl, serve.ttl, given.any("ttl"));
+                set(
+                    hdhomerun,
+                    serve.hdhomerun,
+                    given.any("hdhomerun") || *no_hdhomerun,
+                );
+                set(
+                    start_channel,
+                    serve.start_channel,
+                    given.any("start-channel"),
+                );
+                set(
+                    channel_prefix,
+                    serve.channel_prefix.clone(),
+                    given.any("channel-prefix"),
+                );
+                set(
+                    exclude_feeds,
+                    parse_feeds(&serve.exclude_feeds)?,
+                    given.any("exclude-feeds"),
+                );
+                set(
+                    start_prepend,
+                    serve.start_prepend,
+                    given.any("start-prepend"),
+                );
+                set(trim, serve.trim, given.any("trim") || *no_trim);
+                Ok(())
+            }
+            Command::Watch { format } => {
+                set(
+                    format,
+                    parse("format", &self.watch.format)?,
+                    given.any("format"),
+                );
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Options in effect after merging the command line, for `config show`.
+    /// Options of subcommands come from the file, except for the subcommand
+    /// of `opts`.
+    fn effective(&self, opts: &Opt) -> Config {
+        let sports: Vec<_> = opts
+            .sports
+            .iter()
+            .map(|sport| sport.to_string().to_lowercase())
+            .collect();
+
+        let mut config = Config {
+            sport: Some(sports.join(",")),
+            days: opts.days,
+            cdn: Some(opts.cdn.to_string()),
+            quality: opts.quality.map(|quality| quality.to_string()),
+            disable_retry: Some(opts.disable_retry),
+            give_up_after: Some(opts.give_up_after),
+            spoilers: Some(opts.spoilers),
+            no_cache: Some(opts.no_cache),
+            schedule_file: opts.schedule_file.clone(),
+            teams_file: opts.teams_file.clone(),
+            content_dir: opts.content_dir.clone(),
+            api_mirror: opts.api_mirror.clone(),
+            host: Some(opts.host.to_string()),
+            link_scheme: Some(opts.link_scheme.clone()),
+            ..self.clone()
+        };
+
+        match &opts.command {
+            Command::Select {
+                resolve,
+                list_qualities,
+                ..
+            } => {
+                config.select = SelectConfig {
+                    resolve: Some(*resolve),
+                    list_qualities: Some(*list_qualities),
+                }
+            }
+            Command::Play { command } => config.play = self.play.effective(command),
+            Command::Record { command } => config.record = self.record.effective(command),
+            Command::Cast { command } => config.cast = self.cast.effective(command),
+            Command::Generate { command } => config.generate = self.generate.effective(command),
+            Command::Serve {
+                bind,
+                advertise_url,
+                proxy_playlist,
+                ttl,
+                hdhomerun,
+                start_channel,
+                channel_prefix,
+                exclude_feeds,
+                start_prepend,
+                trim,
+                ..
+            } => {
+                config.serve = ServeConfig {
+                    bind: Some(bind.to_string()),
+                    advertise_url: advertise_url.clone(),
+                    proxy_playlist: Some(*proxy_playlist),
+                    ttl: Some(*ttl),
+                    hdhomerun: Some(*hdhomerun),
+                    start_channel: Some(*start_channel),
+                    channel_prefix: Some(channel_prefix.clone()),
+                    exclude_feeds: Some(feed_names(exclude_feeds)),
+                    start_prepend: Some(*start_prepend),
+                    trim: Some(*trim),
+                }
+            }
+            Command::Watch { format } => config.watch.format = Some(format.to_string()),
+            _ => {}
+        }
+
+        config
+    }
+}
+
+impl PlayConfig {
+    fn apply(&self, command: &mut PlayCommand, given: &Given) -> Result<(), Error> {
+        match command {
+            PlayCommand::Select {
+                restart,
+                no_restart,
+                proxy,
+                offset,
+                passthrough,
+                no_passthrough,
+                custom_player,
+                ..
+            } => {
+                set(restart, self.restart, given.any("restart") || *no_restart);
+                set_opt(proxy, parse("proxy", &self.proxy)?, given.any("proxy"));
+                set_opt(offset, parse_offset(&self.offset)?, given.any("offset"));
+                set(
+                    passthrough,
+                    self.passthrough,
+                    given.any("passthrough") || *no_passthrough,
+                );
+                set_opt(custom_player, self.custom_player.clone(), given.any("PATH"));
+            }
+            PlayCommand::Team {
+                restart,
+                no_restart,
+                feed_type,
+                proxy,
+                offset,
+                passthrough,
+                no_passthrough,
+                custom_player,
+                priority,
+                no_priority,
+                ..
+            } => {
+                set(restart, self.restart, given.any("restart") || *no_restart);
+                set_opt(
+                    feed_type,
+                    parse("feed-type", &self.feed_type)?,
+                    given.any("feed-type"),
+                );
+                set_opt(proxy, parse("proxy", &self.proxy)?, given.any("proxy"));
+                set_opt(offset, parse_offset(&self.offset)?, given.any("offset"));
+                set(
+                    passthrough,
+                    self.passthrough,
+                    given.any("passthrough") || *no_passthrough,
+                );
+                set_opt(custom_player, self.custom_player.clone(), given.any("PATH"));
+                set(
+                    priority,
+                    self.priority,
+                    given.any("priority") || *no_priority,
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn effective(&self, command: &PlayCommand) -> PlayConfig {
+        match command {
+            PlayCommand::Select {
+                restart,
+                proxy,
+                offset,
+                passthrough,
+                custom_player,
+                ..
+            } => PlayConfig {
+                restart: Some(*restart),
+                proxy: proxy.as_ref().map(ToString::to_string),
+                offset: offset.clone(),
+                passthrough: Some(*passthrough),
+                custom_player: custom_player.clone(),
+                ..self.clone()
+            },
+            PlayCommand::Team {
+                restart,
+                feed_type,
+                proxy,
+                offset,
+                passthrough,
+                custom_player,
+                priority,
+                ..
+            } => PlayConfig {
+                restart: Some(*restart),
+                feed_type: feed_type.map(|feed_type| feed_type.to_string()),
+                proxy: proxy.as_ref().map(ToString::to_string),
+                offset: offset.clone(),
+                passthrough: Some(*passthrough),
+                custom_player: custom_player.clone(),
+                priority: Some(*priority),
+            },
+        }
+    }
+}
+
+impl RecordConfig {
+    fn apply(&self, command: &mut RecordCommand, given: &Given) -> Result<(), Error> {
+        match command {
+            RecordCommand::Select {
+                output,
+                restart,
+                no_restart,
+                proxy,
+                offset,
+                audio_source,
+                backend,
+                ..
+            } => {
+                set_opt(output, self.output.clone(), given.any("OUTPUT_DIR"));
+                set(restart, self.restart, given.any("restart") || *no_restart);
+                set_opt(proxy, parse("proxy", &self.proxy)?, given.any("proxy"));
+                set_opt(offset, parse_offset(&self.offset)?, given.any("offset"));
+                set_opt(
+                    audio_source,
+                    self.audio_source.clone(),
+                    given.any("audio-source"),
+                );
+                set(
+                    backend,
+                    parse("backend", &self.backend)?,
+                    given.any("backend"),
+                );
+            }
+            RecordCommand::Team {
+                output,
+                restart,
+                no_restart,
+                feed_type,
+                proxy,
+                offset,
+                audio_source,
+                backend,
+                ..
+            } => {
+                set_opt(output, self.output.clone(), given.any("OUTPUT_DIR"));
+                set(restart, self.restart, given.any("restart") || *no_restart);
+                set_opt(
+                    feed_type,
+                    parse("feed-type", &self.feed_type)?,
+                    given.any("feed-type"),
+                );
+                set_opt(proxy, parse("proxy", &self.proxy)?, given.any("proxy"));
+                set_opt(offset, parse_offset(&self.offset)?, given.any("offset"));
+                set_opt(
+                    audio_source,
+                    self.audio_source.clone(),
+                    given.any("audio-source"),
+                );
+                set(
+                    backend,
+                    parse("backend", &self.backend)?,
+                    given.any("backend"),
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn effective(&self, command: &RecordCommand) -> RecordConfig {
+        match command {
+            RecordCommand::Select {
+                output,
+                restart,
+                proxy,
+                offset,
+                audio_source,
+                backend,
+                ..
+            } => RecordConfig {
+                output: output.clone(),
+                restart: Some(*restart),
+                proxy: proxy.as_ref().map(ToString::to_string),
+                offset: offset.clone(),
+                audio_source: audio_source.clone(),
+                backend: Some(backend.to_string()),
+                ..self.clone()
+            },
+            RecordCommand::Team {
+                output,
+                restart,
+                feed_type,
+                proxy,
+                offset,
+                audio_source,
+                backend,
+                ..
+            } => RecordConfig {
+                output: output.clone(),
+                restart: Some(*restart),
+                feed_type: feed_type.map(|feed_type| feed_type.to_string()),
+                proxy: proxy.as_ref().map(ToString::to_string),
+                offset: offset.clone(),
+                audio_source: audio_source.clone(),
+                backend: Some(backend.to_string()),
+            },
+        }
+    }
+}
+
+impl CastConfig {
+    fn apply(&self, command: &mut CastCommand, given: &Given) -> Result<(), Error> {
+        match command {
+            CastCommand::Select {
+                restart,
+                no_restart,
+                proxy,
+                offset,
+                audio_source,
+                ..
+            } => {
+                set(restart, self.restart, given.any("restart") || *no_restart);
+                set_opt(proxy, parse("proxy", &self.proxy)?, given.any("proxy"));
+                set_opt(offset, parse_offset(&self.offset)?, given.any("offset"));
+                set_opt(
+                    audio_source,
+                    self.audio_source.clone(),
+                    given.any("audio-source"),
+                );
+            }
+            CastCommand::Team {
+                cast_host,
+                restart,
+                no_restart,
+                feed_type,
+                proxy,
+                offset,
+                audio_source,
+                priority,
+                no_priority,
+                ..
+            } => {
+                set_opt(
+                    cast_host,
+                    self.chromecast_host.clone(),
+                    given.any("CHROMECAST_HOST"),
+                );
+                set(restart, self.restart, given.any("restart") || *no_restart);
+                set_opt(
+                    feed_type,
+                    parse("feed-type", &self.feed_type)?,
+                    given.any("feed-type"),
+                );
+                set_opt(proxy, parse("proxy", &self.proxy)?, given.any("proxy"));
+                set_opt(offset, parse_offset(&self.offset)?, given.any("offset"));
+                set_opt(
+                    audio_source,
+                    self.audio_source.clone(),
+                    given.any("audio-source"),
+                );
+                set(
+                    priority,
+                    self.priority,
+                    given.any("priority") || *no_priority,
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn effective(&self, command: &CastCommand) -> CastConfig {
+        match command {
+            CastCommand::Select {
+                restart,
+                proxy,
+                offset,
+                audio_source,
+                ..
+            } => CastConfig {
+                restart: Some(*restart),
+                proxy: proxy.as_ref().map(ToString::to_string),
+                offset: offset.clone(),
+                audio_source: audio_source.clone(),
+                ..self.clone()
+            },
+            CastCommand::Team {
+                cast_host,
+                restart,
+                feed_type,
+                proxy,
+                offset,
+                audio_source,
+                priority,
+                ..
+            } => CastConfig {
+                chromecast_host: cast_host.clone(),
+                restart: Some(*restart),
+                feed_type: feed_type.map(|feed_type| feed_type.to_string()),
+                proxy: proxy.as_ref().map(ToString::to_string),
+                offset: offset.clone(),
+                audio_source: audio_source.clone(),
+                priority: Some(*priority),
+            },
+        }
+    }
+}
+
+impl GenerateConfig {
+    fn apply(&self, command: &mut GenerateCommand, given: &Given) -> Result<(), Error> {
+        match command {
+            GenerateCommand::Playlist {
+                exclude_feeds,
+                server,
+                exclude_postponed,
+                no_exclude_postponed,
+                ..
+            } => {
+                set(
+                    exclude_feeds,
+                    parse_feeds(&self.exclude_feeds)?,
+                    given.any("exclude-feeds"),
+                );
+                set_opt(server, self.server.clone(), given.any("server"));
+                set(
+                    exclude_postponed,
+                    self.exclude_postponed,
+                    given.any("exclude-postponed") || *no_exclude_postponed,
+                );
+            }
+            GenerateCommand::Xmltv {
+                start_channel,
+                channel_prefix,
+                exclude_feeds,
+                start_prepend,
+                trim,
+                no_trim,
+                server,
+                exclude_postponed,
+                no_exclude_postponed,
+                ..
+            } => {
+                set(
+                    start_channel,
+                    self.start_channel,
+                    given.any("start-channel"),
+                );
+                set(
+                    channel_prefix,
+                    self.channel_prefix.clone(),
+                    given.any("channel-prefix"),
+                );
+                set(
+                    exclude_feeds,
+                    parse_feeds(&self.exclude_feeds)?,
+                    given.any("exclude-feeds"),
+                );
+                set(
+                    start_prepend,
+                    self.start_prepend,
+                    given.any("start-prepend"),
+                );
+                set(trim, self.trim, given.any("trim") || *no_trim);
+                set_opt(server, self.server.clone(), given.any("server"));
+                set(
+                    exclude_postponed,
+                    self.exclude_postponed,
+                    given.any("exclude-postponed") || *no_exclude_postponed,
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    fn effective(&self, command: &GenerateCommand) -> GenerateConfig {
+        match command {
+            GenerateCommand::Playlist {
+                exclude_feeds,
+                server,
+                exclude_postponed,
+                ..
+            } => GenerateConfig {
+                exclude_feeds: Some(feed_names(exclude_feeds)),
+                server: server.clone(),
+                exclude_postponed: Some(*exclude_postponed),
+                ..self.clone()
+            },
+            GenerateCommand::Xmltv {
+                start_channel,
+                channel_prefix,
+                exclude_feeds,
+                start_prepend,
+                trim,
+                server,
+                exclude_postponed,
+                ..
+            } => GenerateConfig {
+                start_channel: Some(*start_channel),
+                channel_prefix: Some(channel_prefix.clone()),
+                exclude_feeds: Some(feed_names(exclude_feeds)),
+                start_prepend: Some(*start_prepend),
+                trim: Some(*trim),
+                server: server.clone(),
+                exclude_postponed: Some(*exclude_postponed),
+            },
+        }
+    }
+}
+
+/// Arguments given on the command line, at any level of subcommand
+struct Given<'a>(Vec<&'a ArgMatches<'a>>);
+
+impl<'a> Given<'a> {
+    fn new(matches: &'a ArgMatches<'a>) -> Self {
+        let mut levels = vec![matches];
+        let mut matches = matches;
+        while let (_, Some(subcommand)) = matches.subcommand() {
+            levels.push(subcommand);
+            matches = subcommand;
+        }
+
+        Given(levels)
+    }
+
+    fn any(&self, name: &str) -> bool {
+        self.0
+            .iter()
+            .any(|matches| matches.occurrences_of(name) > 0)
+    }
+}
+
+/// Set every value of `overlay` that isn't null in `base`, recursing into tables
+fn merge(base: &mut serde_json::Value, overlay: serde_json::Value) {
+    match (base, overlay) {
+        (serde_json::Value::Object(base), serde_json::Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                merge(base.entry(key).or_insert(serde_json::Value::Null), value);
+            }
+        }
+        (_, serde_json::Value::Null) => {}
+        (base, overlay) => *base = overlay,
+    }
+}
+
+fn set<T>(field: &mut T, value: Option<T>, given: bool) {
+    if let (Some(value), false) = (value, given) {
+        *field = value;
+    }
+}
+
+fn set_opt<T>(field: &mut Option<T>, value: Option<T>, given: bool) {
+    if value.is_some() && !given {
+        *field = value;
+    }
+}
+
+/// Parse a config value like its command line option
+fn parse<T>(key: &str, value: &Option<String>) -> Result<Option<T>, Error>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    value
+        .as_deref()
+        .map(|value| parse_value(key, value))
+        .transpose()
+}
+
+fn parse_value<T>(key: &str, value: &str) -> Result<T, Error>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    T::from_str(value).map_err(|e| format_err!("Invalid {} '{}' in config: {}", key, value, e))
+}
+
+fn parse_offset(offset: &Option<String>) -> Result<Option<String>, Error> {
+    offset
+        .as_deref()
+        .map(|value| {
+            opt::parse_offset(value)
+                .map_err(|e| format_err!("Invalid offset '{}' in config: {}", value, e))
+        })
+        .transpose()
+}
+
+fn parse_feeds(feeds: &Option<Vec<String>>) -> Result<Option<Vec<FeedType>>, Error> {
+    feeds
+        .as_ref()
+        .map(|feeds| {
+            feeds
+                .iter()
+                .map(|feed| parse_value("exclude-feeds", feed))
+                .collect()
+        })
+        .transpose()
+}
+
+fn feed_names(feeds: &[FeedType]) -> Vec<String> {
+    feeds.iter().map(ToString::to_string).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::opt::{Cdn, RecordBackend};
+
+    const CONFIG: &str = r#"
+sport = "mlb"
+cdn = "l3c"
+quality = "720p"
+spoilers = true
+
+[select]
+resolve = true
+
+[record]
+output = "/recordings"
+backend = "native"
+proxy = "http://127.0.0.1:3128"
+offset = "10:00"
+
+[serve]
+proxy-playlist = true
+
+[generate]
+exclude-feeds = ["FRENCH", "COMPOSITE"]
+
+[profile.livingroom]
+sport = "nhl"
+
+[profile.livingroom.cast]
+chromecast-host = "192.168.0.100"
+
+[profile.basement]
+quality = "best"
+
+[profile.basement.record]
+proxy = "http://10.0.0.1:8080"
+"#;
+
+    fn opts(args: &[&str]) -> Opt {
+        let mut config: Config = toml::from_str(CONFIG).unwrap();
+        let matches = Opt::clap().get_matches_from(args);
+        let mut opts = Opt::from_clap(&matches);
+        if let Some(profile) = &opts.profile {
+            config = config.with_profile(profile).unwrap();
+        }
+        config.apply(&mut opts, &Given::new(&matches)).unwrap();
+        opts
+    }
+
+    #[test]
+    fn fills_in_options_not_given() {
+        let opts = opts(&["lazystream", "record", "team", "VGK"]);
+
+        assert_eq!(opts.sports.to_string(), "MLB");
+        assert_eq!(opts.cdn, Cdn::L3c);
+        assert_eq!(opts.quality.unwrap().to_string(), "720p");
+        assert!(opts.spoilers);
+        match opts.command {
+            Command::Record {
+                command:
+                    RecordCommand::Team {
+                        output,
+                        backend,
+                        proxy,
+                        offset,
+                        ..
+                    },
+            } => {
+                assert_eq!(output, Some(PathBuf::from("/recordings")));
+                assert_eq!(backend, RecordBackend::Native);
+                assert_eq!(proxy.unwrap().to_string(), "http://127.0.0.1:3128/");
+                assert_eq!(offset.as_deref(), Some("10:00"));
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn flags_win() {
+        let opts = opts(&[
+            "lazystream",
+            "--cdn",
+            "akc",
+            "record",
+            "team",
+            "VGK",
+            "/tmp",
+            "--backend",
+            "streamlink",
+            "--sport",
+            "nhl",
+        ]);
+
+        assert_eq!(opts.sports.to_string(), "NHL");
+        assert_eq!(opts.cdn, Cdn::Akc);
+        match opts.command {
+            Command::Record {
+                command:
+                    RecordCommand::Team {
+                        output, backend, ..
+                    },
+            } => {
+                assert_eq!(output, Some(PathBuf::from("/tmp")));
+                assert_eq!(backend, RecordBackend::Streamlink);
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn switches() {
+        let select = opts(&["lazystream", "select"]);
+        assert_eq!(
+            select.command,
+            Command::Select {
+                resolve: true,
+                no_resolve: false,
+                list_qualities: false,
+                no_list_qualities: false,
+            }
+        );
+
+        let serve = opts(&["lazystream", "serve"]);
+        match serve.command {
+            Command::Serve { proxy_playlist, .. } => assert!(proxy_playlist),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn switches_turned_off() {
+        let switched = opts(&["lazystream", "--no-spoilers", "select", "--no-resolve"]);
+        assert!(!switched.spoilers);
+        match switched.command {
+            Command::Select { resolve, .. } => assert!(!resolve),
+            _ => unreachable!(),
+        }
+
+        // The last of a switch & its '--no-' wins
+        let select = opts(&["lazystream", "select", "--no-resolve", "--resolve"]);
+        match select.command {
+            Command::Select { resolve, .. } => assert!(resolve),
+            _ => unreachable!(),
+        }
+
+        let serve = opts(&["lazystream", "serve", "--no-proxy-playlist"]);
+        match serve.command {
+            Command::Serve { proxy_playlist, .. } => assert!(!proxy_playlist),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn effective_options_of_command() {
+        let config: Config = toml::from_str(CONFIG).unwrap();
+        let opts = opts(&[
+            "lazystream",
+            "record",
+            "team",
+            "VGK",
+            "--backend",
+            "streamlink",
+        ]);
+        let effective = config.effective(&opts);
+
+        assert_eq!(effective.quality.as_deref(), Some("720p"));
+        assert_eq!(effective.record.backend.as_deref(), Some("streamlink"));
+        assert_eq!(effective.record.output, Some(PathBuf::from("/recordings")));
+        assert_eq!(effective.record.restart, Some(false));
+        // Only the file for other commands
+        assert_eq!(effective.serve.proxy_playlist, Some(true));
+        assert_eq!(effective.serve.trim, None);
+    }
+
+    #[test]
+    fn lists() {
+        let opts = opts(&["lazystream", "generate", "xmltv", "guide"]);
+
+        match opts.command {
+            Command::Generate {
+                command: GenerateCommand::Xmltv { exclude_feeds, .. },
+            } => assert_eq!(exclude_feeds, vec![FeedType::French, FeedType::Composite]),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn invalid_values() {
+        let config: Config = toml::from_str("cdn = \"abc\"").unwrap();
+        let matches = Opt::clap().get_matches_from(["lazystream", "host"]);
+        let mut opts = Opt::from_clap(&matches);
+
+        let e = config.apply(&mut opts, &Given::new(&matches)).unwrap_err();
+        assert!(e.to_string().starts_with("Invalid cdn 'abc' in config"));
+
+        let config: Config = toml::from_str("[play]\noffset = \"10\"").unwrap();
+        let matches = Opt::clap().get_matches_from(["lazystream", "play", "select"]);
+        let mut opts = Opt::from_clap(&matches);
+
+        let e = config.apply(&mut opts, &Given::new(&matches)).unwrap_err();
+        assert!(e.to_string().starts_with("Invalid offset '10' in config"));
+
+        assert!(toml::from_str::<Config>("cnd = \"akc\"").is_err());
+    }
+
+    #[test]
+    fn profiles() {
+        let livingroom = opts(&[
+            "lazystream",
+            "--profile",
+            "livingroom",
+            "cast",
+            "team",
+            "VGK",
+        ]);
+
+        assert_eq!(livingroom.sports.to_string(), "NHL");
+        assert_eq!(livingroom.cdn, Cdn::L3c);
+        assert_eq!(livingroom.quality.unwrap().to_string(), "720p");
+        match livingroom.command {
+            Command::Cast {
+                command: CastCommand::Team { cast_host, .. },
+            } => assert_eq!(cast_host.unwrap(), "192.168.0.100"),
+            _ => unreachable!(),
+        }
+
+        let basement = opts(&[
+            "lazystream",
+            "record",
+            "team",
+            "VGK",
+            "--profile",
+            "basement",
+        ]);
+
+        assert_eq!(basement.sports.to_string(), "MLB");
+        assert_eq!(basement.quality.unwrap().to_string(), "best");
+        match basement.command {
+            Command::Record {
+                command: RecordCommand::Team { output, proxy, .. },
+            } => {
+                assert_eq!(output, Some(PathBuf::from("/recordings")));
+                assert_eq!(proxy.unwrap().to_string(), "http://10.0.0.1:8080/");
+            }
+            _ => unreachable!(),
+        }
+
+        let config: Config = toml::from_str(CONFIG).unwrap();
+        let e = config.with_profile("attic").unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "Profile attic does not exist, use one of: basement, livingroom"
+        );
+    }
+}
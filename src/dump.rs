@@ -0,0 +1,77 @@
+use crate::{
+    api::{model::Schedule, provider},
+    log_error,
+    opt::{Command, Opt},
+};
+use async_std::{fs, process, task};
+use failure::{bail, Error, ResultExt};
+use std::path::Path;
+
+pub fn run(opts: Opt) {
+    task::block_on(async {
+        if let Err(e) = process(&opts).await {
+            log_error(e.as_fail());
+            process::exit(1);
+        };
+    });
+}
+
+async fn process(opts: &Opt) -> Result<(), Error> {
+    let dir = if let Command::Dump { dir } = &opts.command {
+        dir
+    } else {
+        unreachable!()
+    };
+
+    if opts.schedule_dates().len() > 1 {
+        bail!("Only a single day can be dumped, use '--date' instead");
+    }
+
+    for sport in opts.sports.iter() {
+        let provider = provider::provider(sport);
+        let dir = dir.join(sport.to_string().to_lowercase());
+
+        println!(
+            "Saving {} responses to {}...",
+            sport.to_string().to_uppercase(),
+            dir.display()
+        );
+
+        let schedule = provider.schedule(opts.date).await?;
+        save(&dir.join("schedule.json"), &schedule).await?;
+
+        let teams = provider.teams().await?;
+        save(&dir.join("teams.json"), &teams).await?;
+
+        let schedule: Schedule = serde_json::from_slice(&schedule)?;
+        for game in schedule.games {
+            let content = provider.game_content(game.game_pk).await?;
+            save(
+                &dir.join("content").join(format!("{}.json", game.game_pk)),
+                &content,
+            )
+            .await?;
+        }
+    }
+
+    println!(
+        "\nReplay with: --schedule-file {0}/{{sport}}/schedule.json --teams-file {0}/{{sport}}/teams.json --content-dir {0}/{{sport}}/content",
+        dir.display()
+    );
+
+    Ok(())
+}
+
+async fn save(path: &Path, data: &[u8]) -> Result<(), Error> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .await
+            .context(format!("Failed to create {}", parent.display()))?;
+    }
+
+    fs::write(path, data)
+        .await
+        .context(format!("Failed to save {}", path.display()))?;
+
+    Ok(())
+}
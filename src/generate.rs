@@ -1,16 +1,14 @@
 use crate::{
+    api::provider,
     log_error,
     opt::{Cdn, Command, FeedType, GenerateCommand, Opt, Quality, Sport},
+    serve::stream_url,
     stream::{Game, LazyStream},
     VERSION,
 };
 use async_std::{fs, process, task};
-use chrono::{Duration, Local};
+use chrono::{Duration, Local, NaiveDate};
 use failure::Error;
-use std::path::PathBuf;
-
-const NHL_ICON: &str = "https://upload.wikimedia.org/wikipedia/en/thumb/3/3a/05_NHL_Shield.svg/1200px-05_NHL_Shield.svg.png";
-const MLB_ICON: &str = "https://upload.wikimedia.org/wikipedia/en/thumb/a/a6/Major_League_Baseball_logo.svg/1200px-Major_League_Baseball_logo.svg.png";
 
 pub fn run(opts: Opt) {
     task::block_on(async {
@@ -31,21 +29,18 @@ async fn process(opts: Opt) -> Result<(), Error> {
         }
     }
 
-    let mut lazy_stream = LazyStream::new(&opts).await?;
+    let server = match &opts.command {
+        Command::Generate { command } => command.server().map(str::to_owned),
+        _ => None,
+    };
 
-    if let Some(quality) = opts.quality {
-        lazy_stream
-            .resolve_with_quality_link(opts.cdn, quality)
-            .await;
-    } else {
-        lazy_stream.resolve_with_master_link(opts.cdn).await;
-    }
+    let mut games = games(&opts, server.as_deref()).await?;
 
-    let games = lazy_stream
-        .games()
-        .into_iter()
-        .filter(|game| game.streams.is_some())
-        .collect::<Vec<_>>();
+    if let Command::Generate { command } = &opts.command {
+        if command.exclude_postponed() {
+            games.retain(|game| !game.status.is_off());
+        }
+    }
 
     if let Command::Generate { command } = opts.command {
         match command {
@@ -56,51 +51,65 @@ async fn process(opts: Opt) -> Result<(), Error> {
                 exclude_feeds,
                 start_prepend,
                 trim,
+                ..
             } => {
-                let path = file.with_extension("m3u");
-                create_playlist(
-                    path.clone(),
+                warn_dropped_streams(&games, opts.sports.as_slice(), &exclude_feeds);
+
+                let m3u = create_playlist(
                     games.clone(),
+                    opts.sports.as_slice(),
                     opts.cdn,
                     opts.quality,
+                    server.as_deref(),
                     true,
                     start_channel,
                     Some(&channel_prefix),
                     &exclude_feeds,
                     trim,
                 )
-                .await?;
+                .await;
 
-                let path = path.with_extension("xml");
-                create_xmltv(
-                    path,
+                let path = file.with_extension("m3u");
+                fs::write(&path, m3u).await?;
+                println!("Playlist saved to: {:?}", path);
+
+                let xmltv = create_xmltv(
                     games,
                     start_channel,
-                    opts.sport,
+                    opts.sports.as_slice(),
                     &channel_prefix,
                     &exclude_feeds,
                     start_prepend,
                     trim,
                 )
-                .await?;
+                .await;
+
+                let path = path.with_extension("xml");
+                fs::write(&path, xmltv).await?;
+                println!("Xmltv file saved to: {:?}", path);
             }
             GenerateCommand::Playlist {
                 file,
                 exclude_feeds,
+                ..
             } => {
-                let path = file.with_extension("m3u");
-                create_playlist(
-                    path,
+                let m3u = create_playlist(
                     games,
+                    opts.sports.as_slice(),
                     opts.cdn,
                     opts.quality,
+                    server.as_deref(),
                     false,
                     1000,
                     None,
                     &exclude_feeds,
                     false,
                 )
-                .await?;
+                .await;
+
+                let path = file.with_extension("m3u");
+                fs::write(&path, m3u).await?;
+                println!("Playlist saved to: {:?}", path);
             }
         }
     }
@@ -108,105 +117,251 @@ async fn process(opts: Opt) -> Result<(), Error> {
     Ok(())
 }
 
+/// Games with streams for the selected dates. Links are resolved for the games
+/// of the first day, unless entries will point at a `lazystream serve` server.
+pub async fn games(opts: &Opt, server: Option<&str>) -> Result<Vec<Game>, Error> {
+    let mut lazy_stream = LazyStream::new(opts).await?;
+
+    if server.is_some() {
+        lazy_stream.resolve_streams().await;
+    } else if let Some(quality) = opts.quality {
+        lazy_stream
+            .resolve_with_quality_link(opts.cdn, quality)
+            .await;
+    } else {
+        lazy_stream.resolve_with_master_link(opts.cdn).await;
+    }
+
+    Ok(lazy_stream
+        .games()
+        .into_iter()
+        .filter(|game| game.streams.is_some())
+        .collect())
+}
+
+/// Channels are allocated in blocks of this many per sport, so numbers don't
+/// collide and stay put as the games of other sports change
+pub const CHANNELS_PER_SPORT: u32 = 100;
+
+/// A stream on an XMLTV channel
+#[derive(Debug, Clone, PartialEq)]
+pub struct Channel {
+    pub number: u32,
+    pub name: String,
+    pub sport: Sport,
+    pub game_pk: u64,
+    pub selected_date: NaiveDate,
+    pub feed_type: FeedType,
+}
+
+/// Offset of the first channel of `sport` from the starting channel
+fn channel_offset(sports: &[Sport], sport: Sport) -> u32 {
+    sports.iter().position(|s| *s == sport).unwrap_or_default() as u32 * CHANNELS_PER_SPORT
+}
+
+/// Dates games were loaded for, in order. Every day uses the same channels.
+fn days(games: &[Game]) -> Vec<NaiveDate> {
+    let mut days: Vec<_> = games.iter().map(|game| game.selected_date).collect();
+    days.sort();
+    days.dedup();
+    days
+}
+
+/// Streams of `sport` games on `day`
+fn day_streams<'a>(
+    games: &'a [Game],
+    sport: Sport,
+    day: NaiveDate,
+    exclude_feeds: &'a [FeedType],
+) -> impl Iterator<Item = (&'a Game, FeedType)> {
+    games
+        .iter()
+        .filter(move |game| game.sport() == sport && game.selected_date == day)
+        .flat_map(move |game| {
+            game.streams
+                .iter()
+                .flat_map(|streams| streams.keys())
+                .filter(move |feed_type| !exclude_feeds.contains(feed_type))
+                .map(move |feed_type| (game, *feed_type))
+        })
+}
+
+/// Tell which days have more streams of a sport than it has channels. Those
+/// past its block of channels are left out, instead of taking the channels of
+/// the next sport.
+pub fn warn_dropped_streams(games: &[Game], sports: &[Sport], exclude_feeds: &[FeedType]) {
+    for day in days(games) {
+        for sport in sports.iter().copied() {
+            let count = day_streams(games, sport, day, exclude_feeds).count();
+            if count > CHANNELS_PER_SPORT as usize {
+                println!(
+                    "Leaving out {} of {} {} streams on {}, a sport has {} channels",
+                    count - CHANNELS_PER_SPORT as usize,
+                    count,
+                    sport.to_string().to_uppercase(),
+                    day,
+                    CHANNELS_PER_SPORT
+                );
+            }
+        }
+    }
+}
+
+/// Channels of every stream of the first day, numbered in the same order as
+/// the XMLTV playlist & guide. Like the playlist, channels play the games of
+/// the first day, so each number is used once.
+pub fn channels(
+    games: &[Game],
+    sports: &[Sport],
+    start_channel: u32,
+    channel_prefix: &str,
+    exclude_feeds: &[FeedType],
+) -> Vec<Channel> {
+    let mut channels = vec![];
+
+    let first_day = match days(games).first() {
+        Some(day) => *day,
+        None => return channels,
+    };
+
+    for sport in sports.iter().copied() {
+        let offset = channel_offset(sports, sport);
+
+        for (idx, (game, feed_type)) in day_streams(games, sport, first_day, exclude_feeds)
+            .take(CHANNELS_PER_SPORT as usize)
+            .enumerate()
+        {
+            let id = offset + idx as u32;
+
+            channels.push(Channel {
+                number: start_channel + id,
+                name: format!("{} {}", channel_prefix, id + 1),
+                sport,
+                game_pk: game.game_pk,
+                selected_date: game.selected_date,
+                feed_type,
+            });
+        }
+    }
+
+    channels
+}
+
 #[allow(clippy::too_many_arguments)]
-async fn create_playlist(
-    path: PathBuf,
+pub async fn create_playlist(
     mut games: Vec<Game>,
+    sports: &[Sport],
     cdn: Cdn,
     quality: Option<Quality>,
+    server: Option<&str>,
     is_xmltv: bool,
     start_channel: u32,
     channel_prefix: Option<&str>,
     exclude_feeds: &[FeedType],
     trim: bool,
-) -> Result<(), Error> {
+) -> String {
+    // Channels play the games of the first day, later days are only in the guide
+    if is_xmltv {
+        if let Some(first_day) = days(&games).first().copied() {
+            games.retain(|game| game.selected_date == first_day);
+        }
+    }
+
     let mut m3u = String::new();
     m3u.push_str("#EXTM3U\n");
 
-    let mut id: u32 = 0;
-    for game in games.iter_mut() {
-        for (_, stream) in game
-            .streams
-            .as_mut()
-            .unwrap()
-            .iter_mut()
-            .filter(|(feed_type, _)| !exclude_feeds.contains(feed_type))
-        {
-            let master_link = stream.master_link(cdn).await;
-
-            let link = if let Some(quality) = quality {
-                let quality_link = stream.quality_link(cdn, quality).await;
-
-                quality_link.or(master_link)
-            } else {
-                master_link
-            };
-
-            let title = if is_xmltv {
-                format!("{} {}", channel_prefix.unwrap(), id + 1)
-            } else {
-                format!(
-                    "{} {} @ {} {}",
-                    game.game_date
-                        .with_timezone(&Local)
-                        .time()
-                        .format("%-I:%M %p")
-                        .to_string(),
-                    game.away_team.team_name,
-                    game.home_team.team_name,
-                    stream.feed_type,
-                )
-            };
-            let record = format!(
-                "#EXTINF:-1 CUID=\"{}\" tvg-id=\"{}\" tvg-name=\"{} {}\",{}\n{}\n",
-                start_channel + id,
-                start_channel + id,
-                channel_prefix.unwrap_or("Lazyman"),
-                id + 1,
-                title,
-                link.unwrap_or_else(|_| ".".to_string())
-            );
-            m3u.push_str(&record);
-            id += 1;
+    for sport in sports.iter().copied() {
+        let mut id = channel_offset(sports, sport);
+        let end = id + CHANNELS_PER_SPORT;
+
+        for game in games.iter_mut().filter(|game| game.sport() == sport) {
+            for (_, stream) in game
+                .streams
+                .as_mut()
+                .unwrap()
+                .iter_mut()
+                .filter(|(feed_type, _)| !exclude_feeds.contains(feed_type))
+            {
+                if is_xmltv && id >= end {
+                    continue;
+                }
+
+                let link = if let Some(server) = server {
+                    Ok(stream_url(
+                        server,
+                        sport,
+                        game.game_pk,
+                        game.selected_date,
+                        stream.feed_type,
+                    ))
+                } else if let Some(quality) = quality {
+                    let master_link = stream.master_link(cdn).await;
+                    let quality_link = stream.quality_link(cdn, quality).await;
+
+                    quality_link.or(master_link)
+                } else {
+                    stream.master_link(cdn).await
+                };
+
+                let title = if is_xmltv {
+                    format!("{} {}", channel_prefix.unwrap(), id + 1)
+                } else {
+                    format!(
+                        "{} {} @ {} {}",
+                        game.game_date
+                            .with_timezone(&Local)
+                            .time()
+                            .format("%-I:%M %p"),
+                        game.away_team.team_name,
+                        game.home_team.team_name,
+                        stream.feed_type,
+                    )
+                };
+                let record = format!(
+                    "#EXTINF:-1 CUID=\"{}\" tvg-id=\"{}\" tvg-name=\"{} {}\",{}\n{}\n",
+                    start_channel + id,
+                    start_channel + id,
+                    channel_prefix.unwrap_or("Lazyman"),
+                    id + 1,
+                    title,
+                    link.unwrap_or_else(|_| ".".to_string())
+                );
+                m3u.push_str(&record);
+                id += 1;
+            }
         }
-    }
 
-    // Create additional blank records for all 100 channels
-    if is_xmltv && !trim {
-        let _id = id;
-        for _ in _id..100 {
-            let title = format!("{} {}", channel_prefix.unwrap(), id + 1);
-            let record = format!(
-                "#EXTINF:-1 CUID=\"{}\" tvg-id=\"{}\" tvg-name=\"{} {}\",{}\n.\n",
-                start_channel + id,
-                start_channel + id,
-                channel_prefix.unwrap_or("Lazyman"),
-                id + 1,
-                title,
-            );
-            m3u.push_str(&record);
-            id += 1;
+        // Create additional blank records for the rest of the channels of the sport
+        if is_xmltv && !trim {
+            while id < end {
+                let title = format!("{} {}", channel_prefix.unwrap(), id + 1);
+                let record = format!(
+                    "#EXTINF:-1 CUID=\"{}\" tvg-id=\"{}\" tvg-name=\"{} {}\",{}\n.\n",
+                    start_channel + id,
+                    start_channel + id,
+                    channel_prefix.unwrap_or("Lazyman"),
+                    id + 1,
+                    title,
+                );
+                m3u.push_str(&record);
+                id += 1;
+            }
         }
     }
 
-    fs::write(&path, m3u).await?;
-
-    println!("Playlist saved to: {:?}", path);
-
-    Ok(())
+    m3u
 }
 
 #[allow(clippy::too_many_arguments)]
-async fn create_xmltv(
-    path: PathBuf,
+pub async fn create_xmltv(
     mut games: Vec<Game>,
     start_channel: u32,
-    sport: Sport,
+    sports: &[Sport],
     channel_prefix: &str,
     exclude_feeds: &[FeedType],
     start_prepend: u16,
     trim: bool,
-) -> Result<(), Error> {
+) -> String {
     let mut xmltv = String::new();
     xmltv.push_str(&format!(
         "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
@@ -216,108 +371,116 @@ async fn create_xmltv(
         VERSION
     ));
 
-    let icon = match sport {
-        Sport::Nhl => NHL_ICON,
-        Sport::Mlb => MLB_ICON,
-    };
+    for sport in sports.iter().copied() {
+        let icon = provider::provider(sport).icon();
+        let offset = channel_offset(sports, sport);
 
-    let num_streams = games
-        .iter()
-        .map(|game| {
-            game.streams
-                .as_ref()
-                .unwrap()
-                .keys()
-                .filter(|feed_type| !exclude_feeds.contains(feed_type))
-        })
-        .flatten()
-        .count();
-
-    let num_channels = trim.then(|| num_streams as u32).unwrap_or(100);
-
-    let mut id: u32 = 0;
-    while id < num_channels {
-        let record = format!(
-            "\n    <channel id=\"{}\">\
-             \n      <display-name>{} {}</display-name>\
-             \n      <icon src=\"{}\"></icon>\
-             \n    </channel>",
-            start_channel + id,
-            channel_prefix,
-            id + 1,
-            icon
-        );
-        xmltv.push_str(&record);
-        id += 1;
-    }
+        // Enough channels for the day with the most streams
+        let num_streams = days(&games)
+            .into_iter()
+            .map(|day| day_streams(&games, sport, day, exclude_feeds).count())
+            .max()
+            .unwrap_or_default();
 
-    let mut id: u32 = 0;
-    for game in games.iter_mut() {
-        let icons = if let Some(game_cuts) = game.game_cuts().await {
-            let cuts = vec![&game_cuts.cut_320_180, &game_cuts.cut_2048_1152];
-            let mut icons = String::new();
-            for cut in cuts {
-                let icon = format!(
-                    "\n      <icon src=\"{}\" width=\"{}\" height=\"{}\"></icon>",
-                    cut.src, cut.width, cut.height,
-                );
-                icons.push_str(&icon);
-            }
-            icons
+        let num_channels = if trim {
+            (num_streams as u32).min(CHANNELS_PER_SPORT)
         } else {
-            String::from("\n      <icon src=\"\"></icon>")
+            CHANNELS_PER_SPORT
         };
 
-        let mut description = game.description().await.unwrap_or_else(|| String::from(""));
-        if description.is_empty() {
-            description = format!(
-                "Watch the {} take on the {}.",
-                game.away_team.team_name, game.home_team.team_name
-            );
-        }
-
-        for (_, stream) in game
-            .streams
-            .as_mut()
-            .unwrap()
-            .iter_mut()
-            .filter(|(feed_type, _)| !exclude_feeds.contains(feed_type))
-        {
-            let game_time = game.game_date.with_timezone(&Local);
-            let start = game_time - Duration::minutes(start_prepend as i64);
-            let stop = game_time + Duration::hours(4);
-
-            let title = format!(
-                "{} @ {} ({})",
-                game.away_team.team_name, game.home_team.team_name, stream.feed_type
-            );
-
+        let mut id = offset;
+        while id < offset + num_channels {
             let record = format!(
-                "\n    <programme channel=\"{}\" start=\"{} {}\" stop=\"{} {}\">\
-                     \n      <title lang=\"en\">{}</title>\
-                     \n      <desc lang=\"en\">{}</desc>\
-                     \n      <category lang=\"en\">Sports</category>\
-                     {}\
-                     \n    </programme>",
+                "\n    <channel id=\"{}\">\
+                 \n      <display-name>{} {}</display-name>\
+                 \n      <icon src=\"{}\"></icon>\
+                 \n    </channel>",
                 start_channel + id,
-                start.format("%Y%m%d%H%M%S"),
-                start.format("%z"),
-                stop.format("%Y%m%d%H%M%S"),
-                stop.format("%z"),
-                title,
-                description,
-                icons,
+                channel_prefix,
+                id + 1,
+                icon
             );
             xmltv.push_str(&record);
             id += 1;
         }
     }
 
-    xmltv.push_str("\n  </tv>");
-
-    fs::write(&path, xmltv).await?;
+    for day in days(&games) {
+        for sport in sports.iter().copied() {
+            let mut id = channel_offset(sports, sport);
+            let end = id + CHANNELS_PER_SPORT;
+
+            for game in games
+                .iter_mut()
+                .filter(|game| game.sport() == sport && game.selected_date == day)
+            {
+                let icons = if let Some(game_cuts) = game.game_cuts().await {
+                    let cuts = vec![&game_cuts.cut_320_180, &game_cuts.cut_2048_1152];
+                    let mut icons = String::new();
+                    for cut in cuts {
+                        let icon = format!(
+                            "\n      <icon src=\"{}\" width=\"{}\" height=\"{}\"></icon>",
+                            cut.src, cut.width, cut.height,
+                        );
+                        icons.push_str(&icon);
+                    }
+                    icons
+                } else {
+                    String::from("\n      <icon src=\"\"></icon>")
+                };
+
+                let mut description = game.description().await.unwrap_or_else(|| String::from(""));
+                if description.is_empty() {
+                    description = format!(
+                        "Watch the {} take on the {}.",
+                        game.away_team.team_name, game.home_team.team_name
+                    );
+                }
+
+                for (_, stream) in game
+                    .streams
+                    .as_mut()
+                    .unwrap()
+                    .iter_mut()
+                    .filter(|(feed_type, _)| !exclude_feeds.contains(feed_type))
+                {
+                    if id >= end {
+                        continue;
+                    }
+
+                    let game_time = game.game_date.with_timezone(&Local);
+                    let start = game_time - Duration::minutes(start_prepend as i64);
+                    let stop = game_time + Duration::hours(4);
+
+                    let title = format!(
+                        "{} @ {} ({})",
+                        game.away_team.team_name, game.home_team.team_name, stream.feed_type
+                    );
+
+                    let record = format!(
+                        "\n    <programme channel=\"{}\" start=\"{} {}\" stop=\"{} {}\">\
+                             \n      <title lang=\"en\">{}</title>\
+                             \n      <desc lang=\"en\">{}</desc>\
+                             \n      <category lang=\"en\">Sports</category>\
+                             {}\
+                             \n    </programme>",
+                        start_channel + id,
+                        start.format("%Y%m%d%H%M%S"),
+                        start.format("%z"),
+                        stop.format("%Y%m%d%H%M%S"),
+                        stop.format("%z"),
+                        title,
+                        description,
+                        icons,
+                    );
+                    xmltv.push_str(&record);
+                    id += 1;
+                }
+            }
+        }
+    }
 
-    println!("Xmltv file saved to: {:?}", path);
+    xmltv.push_str("\n  </tv>");
 
-    Ok(())
+    xmltv
 }
@@ -0,0 +1,771 @@
+use crate::opt::Quality;
+use failure::{bail, format_err, Error};
+use std::{collections::HashMap, str::FromStr};
+
+/// HLS master playlist, listing every variant stream and audio rendition of a
+/// feed
+#[derive(Debug, Clone, PartialEq)]
+pub struct MasterPlaylist {
+    pub variants: Vec<Variant>,
+    pub audio: Vec<AudioRendition>,
+}
+
+impl MasterPlaylist {
+    /// Parse the master playlist downloaded from `url`. All variant URIs are
+    /// resolved against `url`.
+    pub fn parse(url: &str, text: &str) -> Result<MasterPlaylist, Error> {
+        let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
+
+        if lines.next() != Some("#EXTM3U") {
+            bail!("Playlist is missing #EXTM3U header");
+        }
+
+        let mut variants = vec![];
+        let mut audio = vec![];
+        let mut stream_inf = None;
+
+        for line in lines {
+            if let Some(attributes) = line.strip_prefix("#EXT-X-STREAM-INF:") {
+                stream_inf = Some(parse_attributes(attributes));
+            } else if let Some(attributes) = line.strip_prefix("#EXT-X-MEDIA:") {
+                let attributes = parse_attributes(attributes);
+
+                if attributes.get("TYPE").map(String::as_str) == Some("AUDIO") {
+                    audio.push(AudioRendition::new(url, &attributes)?);
+                }
+            } else if line.starts_with('#') {
+                continue;
+            } else if let Some(attributes) = stream_inf.take() {
+                let variant = Variant::new(&attributes, resolve_uri(url, line))?;
+                variants.push(variant);
+            }
+        }
+
+        if variants.is_empty() {
+            bail!("Master playlist doesn't contain any variant streams");
+        }
+
+        Ok(MasterPlaylist { variants, audio })
+    }
+
+    /// Variant that best matches `quality`. The highest variant that doesn't
+    /// exceed the requested height & frame rate, or bandwidth, is chosen. If
+    /// every variant exceeds it, the lowest variant is chosen instead.
+    pub fn variant(&self, quality: Quality) -> Option<&Variant> {
+        let by_bandwidth = |a: &&Variant, b: &&Variant| a.bandwidth.cmp(&b.bandwidth);
+        let by_resolution = |a: &&Variant, b: &&Variant| {
+            (a.height(), a.rounded_frame_rate(), a.bandwidth).cmp(&(
+                b.height(),
+                b.rounded_frame_rate(),
+                b.bandwidth,
+            ))
+        };
+
+        match quality {
+            Quality::Best => self.variants.iter().max_by(by_bandwidth),
+            Quality::Worst => self.variants.iter().min_by(by_bandwidth),
+            Quality::Resolution { height, frame_rate } => {
+                let frame_rate = frame_rate.unwrap_or(30);
+                let with_resolution = self
+                    .variants
+                    .iter()
+                    .filter(|variant| variant.resolution.is_some());
+
+                with_resolution
+                    .clone()
+                    .filter(|variant| {
+                        variant.height() <= Some(height)
+                            && variant.rounded_frame_rate() <= frame_rate
+                    })
+                    .max_by(by_resolution)
+                    .or_else(|| with_resolution.min_by(by_resolution))
+            }
+            Quality::MaxBandwidth(kbps) => self
+                .variants
+                .iter()
+                .filter(|variant| variant.bandwidth <= u64::from(kbps) * 1000)
+                .max_by(by_bandwidth)
+                .or_else(|| self.variants.iter().min_by(by_bandwidth)),
+        }
+    }
+}
+
+/// HLS media playlist, listing the segments of a single variant stream
+#[derive(Debug, Clone, PartialEq)]
+pub struct MediaPlaylist {
+    pub target_duration: u64,
+    pub media_sequence: u64,
+    pub segments: Vec<Segment>,
+    /// No more segments will be added, e.g. the game is over
+    pub end_list: bool,
+}
+
+impl MediaPlaylist {
+    /// Parse the media playlist downloaded from `url`. All segment & key URIs
+    /// are resolved against `url`.
+    pub fn parse(url: &str, text: &str) -> Result<MediaPlaylist, Error> {
+        let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
+
+        if lines.next() != Some("#EXTM3U") {
+            bail!("Playlist is missing #EXTM3U header");
+        }
+
+        let mut target_duration = 0;
+        let mut media_sequence = 0;
+        let mut segments = vec![];
+        let mut end_list = false;
+
+        let mut key = None;
+        let mut duration = None;
+        let mut discontinuity = false;
+
+        for line in lines {
+            if let Some(value) = line.strip_prefix("#EXT-X-TARGETDURATION:") {
+                target_duration = value.parse()?;
+            } else if let Some(value) = line.strip_prefix("#EXT-X-MEDIA-SEQUENCE:") {
+                media_sequence = value.parse()?;
+            } else if let Some(attributes) = line.strip_prefix("#EXT-X-KEY:") {
+                key = Key::new(url, &parse_attributes(attributes))?;
+            } else if let Some(value) = line.strip_prefix("#EXTINF:") {
+                let value = value.split(',').next().unwrap_or_default();
+                duration = Some(value.parse()?);
+            } else if line == "#EXT-X-DISCONTINUITY" {
+                discontinuity = true;
+            } else if line == "#EXT-X-ENDLIST" {
+                end_list = true;
+            } else if line.starts_with("#EXT-X-STREAM-INF:") {
+                bail!("Expected a media playlist, got a master playlist");
+            } else if line.starts_with("#EXT-X-MAP:") {
+                bail!("Fragmented MP4 streams are not supported");
+            } else if line.starts_with('#') {
+                continue;
+            } else {
+                segments.push(Segment {
+                    sequence: media_sequence + segments.len() as u64,
+                    duration: duration.take().unwrap_or_default(),
+                    uri: resolve_uri(url, line),
+                    key: key.clone(),
+                    discontinuity,
+                });
+                discontinuity = false;
+            }
+        }
+
+        Ok(MediaPlaylist {
+            target_duration,
+            media_sequence,
+            segments,
+            end_list,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Segment {
+    pub sequence: u64,
+    /// Duration in seconds
+    pub duration: f64,
+    pub uri: String,
+    pub key: Option<Key>,
+    /// Encoding parameters change from the previous segment
+    pub discontinuity: bool,
+}
+
+/// AES-128 key from an `EXT-X-KEY` tag
+#[derive(Debug, Clone, PartialEq)]
+pub struct Key {
+    pub uri: String,
+    pub iv: Option<[u8; 16]>,
+}
+
+impl Key {
+    /// Returns `None` for `METHOD=NONE`, meaning following segments aren't
+    /// encrypted
+    fn new(url: &str, attributes: &HashMap<String, String>) -> Result<Option<Key>, Error> {
+        match attributes.get("METHOD").map(String::as_str) {
+            Some("NONE") => Ok(None),
+            Some("AES-128") => {
+                let uri = attributes
+                    .get("URI")
+                    .ok_or_else(|| format_err!("AES-128 key is missing URI"))?;
+                let iv = attributes.get("IV").map(|iv| parse_iv(iv)).transpose()?;
+
+                Ok(Some(Key {
+                    uri: resolve_uri(url, uri),
+                    iv,
+                }))
+            }
+            Some(method) => bail!("Unsupported encryption method {}", method),
+            None => bail!("Key is missing METHOD"),
+        }
+    }
+
+    /// IV to decrypt the segment with. When the playlist doesn't specify one,
+    /// the segment's sequence number is used.
+    pub fn iv(&self, sequence: u64) -> [u8; 16] {
+        self.iv
+            .unwrap_or_else(|| u128::from(sequence).to_be_bytes())
+    }
+}
+
+fn parse_iv(s: &str) -> Result<[u8; 16], Error> {
+    let hex = s
+        .strip_prefix("0x")
+        .or_else(|| s.strip_prefix("0X"))
+        .unwrap_or(s);
+
+    match u128::from_str_radix(hex, 16) {
+        Ok(iv) => Ok(iv.to_be_bytes()),
+        Err(_) => bail!("Invalid IV {}", s),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Variant {
+    pub bandwidth: u64,
+    pub resolution: Option<Resolution>,
+    pub frame_rate: Option<f64>,
+    pub codecs: Option<String>,
+    pub audio: Option<String>,
+    pub uri: String,
+}
+
+impl Variant {
+    fn new(attributes: &HashMap<String, String>, uri: String) -> Result<Variant, Error> {
+        let bandwidth = attributes
+            .get("BANDWIDTH")
+            .ok_or_else(|| format_err!("Variant stream {} is missing BANDWIDTH", uri))?
+            .parse()?;
+        let resolution = attributes
+            .get("RESOLUTION")
+            .map(|resolution| resolution.parse())
+            .transpose()?;
+        let frame_rate = attributes
+            .get("FRAME-RATE")
+            .map(|frame_rate| frame_rate.parse())
+            .transpose()?;
+
+        Ok(Variant {
+            bandwidth,
+            resolution,
+            frame_rate,
+            codecs: attributes.get("CODECS").cloned(),
+            audio: attributes.get("AUDIO").cloned(),
+            uri,
+        })
+    }
+
+    pub fn height(&self) -> Option<u32> {
+        self.resolution.map(|resolution| resolution.height)
+    }
+
+    /// Anything above 30 fps, e.g. 59.94 or 60
+    pub fn is_high_frame_rate(&self) -> bool {
+        matches!(self.frame_rate, Some(frame_rate) if frame_rate > 30.0)
+    }
+
+    /// Frame rate rounded to a whole number, e.g. 59.94 is 60. Defaults to 30
+    /// when the playlist doesn't specify one.
+    pub fn rounded_frame_rate(&self) -> u32 {
+        self.frame_rate
+            .map_or(30, |frame_rate| frame_rate.round() as u32)
+    }
+
+    /// Name of this variant as accepted by `--quality`, e.g. `720p60`
+    pub fn name(&self) -> String {
+        match self.height() {
+            Some(height) if self.is_high_frame_rate() => {
+                format!("{}p{}", height, self.rounded_frame_rate())
+            }
+            Some(height) => format!("{}p", height),
+            None => format!("max-bandwidth={}", self.bandwidth / 1000),
+        }
+    }
+}
+
+/// Audio track from an `EXT-X-MEDIA` tag with `TYPE=AUDIO`
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioRendition {
+    pub group_id: String,
+    pub name: String,
+    pub language: Option<String>,
+    pub default: bool,
+    pub autoselect: bool,
+    /// Separate playlist for this track. Absent when the audio is muxed in
+    /// with the variant streams.
+    pub uri: Option<String>,
+}
+
+impl AudioRendition {
+    fn new(url: &str, attributes: &HashMap<String, String>) -> Result<AudioRendition, Error> {
+        let group_id = attributes
+            .get("GROUP-ID")
+            .ok_or_else(|| format_err!("Audio rendition is missing GROUP-ID"))?;
+        let name = attributes
+            .get("NAME")
+            .ok_or_else(|| format_err!("Audio rendition is missing NAME"))?;
+        let is_yes = |name| attributes.get(name).map(String::as_str) == Some("YES");
+
+        Ok(AudioRendition {
+            group_id: group_id.clone(),
+            name: name.clone(),
+            language: attributes.get("LANGUAGE").cloned(),
+            default: is_yes("DEFAULT"),
+            autoselect: is_yes("AUTOSELECT"),
+            uri: attributes.get("URI").map(|uri| resolve_uri(url, uri)),
+        })
+    }
+
+    /// Whether `source` names this rendition by its name or language, same as
+    /// Streamlink's `--hls-audio-select`
+    pub fn matches(&self, source: &str) -> bool {
+        source == "*"
+            || self.name.eq_ignore_ascii_case(source)
+            || self
+                .language
+                .iter()
+                .any(|language| language.eq_ignore_ascii_case(source))
+    }
+}
+
+impl std::fmt::Display for AudioRendition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)?;
+        if let Some(language) = &self.language {
+            write!(f, " ({})", language)?;
+        }
+        if self.default {
+            write!(f, " [default]")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Resolution {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl FromStr for Resolution {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Resolution, Error> {
+        let mut parts = s.splitn(2, 'x');
+
+        match (parts.next(), parts.next()) {
+            (Some(width), Some(height)) => Ok(Resolution {
+                width: width.parse()?,
+                height: height.parse()?,
+            }),
+            _ => bail!("Invalid RESOLUTION {}", s),
+        }
+    }
+}
+
+/// Parse an attribute list, e.g. `BANDWIDTH=800000,CODECS="avc1.4d401f,mp4a.40.2"`.
+/// Quotes are stripped from quoted string values.
+fn parse_attributes(s: &str) -> HashMap<String, String> {
+    let mut attributes = HashMap::new();
+    let mut rest = s;
+
+    while let Some(idx) = rest.find('=') {
+        let name = rest[..idx].trim();
+        rest = &rest[idx + 1..];
+
+        let value = if let Some(quoted) = rest.strip_prefix('"') {
+            let end = quoted.find('"').unwrap_or(quoted.len());
+            rest = quoted.get(end + 1..).unwrap_or("");
+            &quoted[..end]
+        } else {
+            let end = rest.find(',').unwrap_or(rest.len());
+            let value = &rest[..end];
+            rest = &rest[end..];
+            value
+        };
+        rest = rest.strip_prefix(',').unwrap_or(rest);
+
+        attributes.insert(name.to_owned(), value.to_owned());
+    }
+
+    attributes
+}
+
+/// Resolve `uri` from a playlist against the `base` url the playlist was
+/// downloaded from. Absolute uris are returned untouched and any query string
+/// on `base` is dropped, while the query string of `uri` is kept.
+pub fn resolve_uri(base: &str, uri: &str) -> String {
+    let base = base.trim();
+    let uri = uri.trim();
+
+    if has_scheme(uri) {
+        return uri.to_owned();
+    }
+
+    let (scheme, rest) = match base.find("://") {
+        Some(idx) => (&base[..idx], &base[idx + 3..]),
+        None => ("http", base),
+    };
+
+    if let Some(uri) = uri.strip_prefix("//") {
+        return format!("{}://{}", scheme, uri);
+    }
+
+    let authority_end = rest.find(&['/', '?', '#'][..]).unwrap_or(rest.len());
+    let authority = &rest[..authority_end];
+
+    let path = if uri.starts_with('/') {
+        uri.to_owned()
+    } else {
+        let base_path = &rest[authority_end..];
+        let base_path = &base_path[..base_path.find(&['?', '#'][..]).unwrap_or(base_path.len())];
+        let base_dir = &base_path[..base_path.rfind('/').map_or(0, |idx| idx + 1)];
+
+        if base_dir.is_empty() {
+            format!("/{}", uri)
+        } else {
+            format!("{}{}", base_dir, uri)
+        }
+    };
+
+    format!("{}://{}{}", scheme, authority, remove_dot_segments(&path))
+}
+
+/// Whether `uri` starts with a scheme like `https:`, ahead of any path, query
+/// or fragment, which makes it absolute
+fn has_scheme(uri: &str) -> bool {
+    match uri.find(&[':', '/', '?', '#'][..]) {
+        Some(idx) if uri[idx..].starts_with(':') => {
+            let scheme = &uri[..idx];
+            scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+        }
+        _ => false,
+    }
+}
+
+/// Rewrite every uri in the playlist `text` downloaded from `url` to be
+/// absolute, so the playlist can be served from a different host.
+pub fn absolutize(url: &str, text: &str) -> String {
+    let mut playlist = String::with_capacity(text.len());
+
+    for line in text.lines() {
+        let line = line.trim();
+
+        if line.is_empty() {
+            continue;
+        } else if !line.starts_with('#') {
+            playlist.push_str(&resolve_uri(url, line));
+        } else if let Some(idx) = line.find("URI=\"") {
+            let start = idx + 5;
+            let end = line[start..]
+                .find('"')
+                .map_or(line.len(), |end| start + end);
+
+            playlist.push_str(&line[..start]);
+            playlist.push_str(&resolve_uri(url, &line[start..end]));
+            playlist.push_str(&line[end..]);
+        } else {
+            playlist.push_str(line);
+        }
+        playlist.push('\n');
+    }
+
+    playlist
+}
+
+/// Collapse `.` and `..` segments of the path, leaving the query string alone
+fn remove_dot_segments(path: &str) -> String {
+    let (path, query) = match path.find('?') {
+        Some(idx) => path.split_at(idx),
+        None => (path, ""),
+    };
+
+    let mut segments: Vec<&str> = vec![];
+    for segment in path.split('/').skip(1) {
+        match segment {
+            "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+
+    format!("/{}{}", segments.join("/"), query)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NHL_MASTER: &str = include_str!("../tests/fixtures/hls/nhl_master.m3u8");
+    const MLB_MASTER: &str = include_str!("../tests/fixtures/hls/mlb_master.m3u8");
+    const ABSOLUTE_MASTER: &str = include_str!("../tests/fixtures/hls/absolute_master.m3u8");
+    const ENCRYPTED_MEDIA: &str = include_str!("../tests/fixtures/hls/encrypted_media.m3u8");
+
+    const NHL_LINK: &str = "https://hlslive-akc.med2.med.nhl.com/hls/live/2018011/nhl/nlds/master_wired60.m3u8?token=abc";
+
+    #[test]
+    fn parse_nhl_master() {
+        let playlist = MasterPlaylist::parse(NHL_LINK, NHL_MASTER).unwrap();
+
+        assert_eq!(playlist.variants.len(), 7);
+
+        let best = &playlist.variants[0];
+        assert_eq!(best.bandwidth, 6_600_000);
+        assert_eq!(
+            best.resolution,
+            Some(Resolution {
+                width: 1280,
+                height: 720
+            })
+        );
+        assert_eq!(best.frame_rate, Some(59.94));
+        assert!(best.is_high_frame_rate());
+        assert_eq!(best.codecs.as_deref(), Some("avc1.64002a,mp4a.40.2"));
+        assert_eq!(best.audio.as_deref(), Some("aac"));
+        assert_eq!(
+            best.uri,
+            "https://hlslive-akc.med2.med.nhl.com/hls/live/2018011/nhl/nlds/6600K/6600_complete-trimmed.m3u8"
+        );
+
+        assert_eq!(playlist.audio.len(), 2);
+        assert_eq!(
+            playlist.audio[1],
+            AudioRendition {
+                group_id: "aac".to_owned(),
+                name: "Francais".to_owned(),
+                language: Some("fr".to_owned()),
+                default: false,
+                autoselect: true,
+                uri: Some(
+                    "https://hlslive-akc.med2.med.nhl.com/hls/live/2018011/nhl/nlds/audio/fr/index.m3u8"
+                        .to_owned()
+                ),
+            }
+        );
+        assert_eq!(playlist.audio[0].uri, None);
+        assert!(playlist.audio[0].default);
+
+        let worst = &playlist.variants[6];
+        assert_eq!(worst.bandwidth, 192_000);
+        assert_eq!(worst.height(), Some(216));
+        assert!(!worst.is_high_frame_rate());
+    }
+
+    #[test]
+    fn parse_mlb_master_with_query_strings() {
+        let link = "https://mlb-ws-mf.media.mlb.com/mlbam/2021/04/01/MLB_GAME_VIDEO_NYYTOR_HOME_20210401/master_desktop_complete.m3u8";
+        let playlist = MasterPlaylist::parse(link, MLB_MASTER).unwrap();
+
+        assert_eq!(playlist.variants.len(), 4);
+        assert_eq!(
+            playlist.variants[0].uri,
+            "https://mlb-ws-mf.media.mlb.com/mlbam/2021/04/01/MLB_GAME_VIDEO_NYYTOR_HOME_20210401/5600K/5600_complete.m3u8?hdnea=exp=1617300000~hmac=ff00"
+        );
+        assert_eq!(
+            playlist.variants[3].uri,
+            "https://mlb-ws-mf.media.mlb.com/mlbam/2021/04/01/MLB_GAME_VIDEO_NYYTOR_HOME_20210401/1200K/1200_complete.m3u8"
+        );
+        assert_eq!(playlist.variants[3].frame_rate, None);
+        assert!(playlist.audio.is_empty());
+    }
+
+    #[test]
+    fn parse_absolute_and_root_relative_uris() {
+        let link = "http://cdn.example.com/live/feed/master.m3u8";
+        let playlist = MasterPlaylist::parse(link, ABSOLUTE_MASTER).unwrap();
+
+        let uris = playlist
+            .variants
+            .iter()
+            .map(|variant| variant.uri.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            uris,
+            vec![
+                "https://other.example.com/hls/3500K/index.m3u8",
+                "http://cdn.example.com/hls/1800K/index.m3u8?a=1/2",
+                "http://cdn.example.com/live/800K/index.m3u8",
+            ]
+        );
+    }
+
+    #[test]
+    fn variant_for_quality() {
+        let playlist = MasterPlaylist::parse(NHL_LINK, NHL_MASTER).unwrap();
+        let bandwidth = |quality: &str| {
+            playlist
+                .variant(quality.parse().unwrap())
+                .map(|variant| variant.bandwidth)
+        };
+
+        assert_eq!(bandwidth("best"), Some(6_600_000));
+        assert_eq!(bandwidth("worst"), Some(192_000));
+        assert_eq!(bandwidth("720p60"), Some(6_600_000));
+        assert_eq!(bandwidth("720p"), Some(4_800_000));
+        assert_eq!(bandwidth("1080p60"), Some(6_600_000));
+        assert_eq!(bandwidth("1080p"), Some(4_800_000));
+        assert_eq!(bandwidth("540p"), Some(3_500_000));
+        assert_eq!(bandwidth("400p"), Some(1_200_000));
+        assert_eq!(bandwidth("144p"), Some(192_000));
+        assert_eq!(bandwidth("max-bandwidth=3000"), Some(2_500_000));
+        assert_eq!(bandwidth("max-bandwidth=100"), Some(192_000));
+    }
+
+    #[test]
+    fn audio_rendition_matches() {
+        let playlist = MasterPlaylist::parse(NHL_LINK, NHL_MASTER).unwrap();
+        let french = &playlist.audio[1];
+
+        assert!(french.matches("fr"));
+        assert!(french.matches("FR"));
+        assert!(french.matches("francais"));
+        assert!(french.matches("*"));
+        assert!(!french.matches("en"));
+        assert_eq!(french.to_string(), "Francais (fr)");
+        assert_eq!(playlist.audio[0].to_string(), "English (en) [default]");
+    }
+
+    #[test]
+    fn variant_names() {
+        let playlist = MasterPlaylist::parse(NHL_LINK, NHL_MASTER).unwrap();
+        let names = playlist
+            .variants
+            .iter()
+            .map(Variant::name)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            names,
+            vec!["720p60", "720p", "540p", "504p", "360p", "288p", "216p"]
+        );
+    }
+
+    #[test]
+    fn parse_rejects_invalid_playlists() {
+        assert!(MasterPlaylist::parse(NHL_LINK, "<html></html>").is_err());
+        assert!(MasterPlaylist::parse(NHL_LINK, "#EXTM3U\n#EXT-X-VERSION:3\n").is_err());
+        assert!(MasterPlaylist::parse(
+            NHL_LINK,
+            "#EXTM3U\n#EXT-X-STREAM-INF:RESOLUTION=1280x720\n720.m3u8\n"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn parse_encrypted_media() {
+        let link = "https://host.com/hls/6600K/6600_complete-trimmed.m3u8?token=abc";
+        let playlist = MediaPlaylist::parse(link, ENCRYPTED_MEDIA).unwrap();
+
+        assert_eq!(playlist.target_duration, 6);
+        assert_eq!(playlist.media_sequence, 1200);
+        assert!(playlist.end_list);
+        assert_eq!(playlist.segments.len(), 5);
+
+        let first = &playlist.segments[0];
+        assert_eq!(first.sequence, 1200);
+        assert_eq!(first.duration, 5.005);
+        assert_eq!(first.uri, "https://host.com/hls/6600K/00/6600K_1200.ts");
+        assert_eq!(
+            first.key,
+            Some(Key {
+                uri: "https://keys.host.com/key/1".to_owned(),
+                iv: None,
+            })
+        );
+        assert_eq!(
+            first.key.as_ref().unwrap().iv(1200),
+            [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x04, 0xb0]
+        );
+
+        let rotated = &playlist.segments[2];
+        assert!(rotated.discontinuity);
+        assert_eq!(
+            rotated.key.as_ref().unwrap().iv,
+            Some([0x12, 0x34, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xff])
+        );
+
+        assert!(!playlist.segments[3].discontinuity);
+        assert_eq!(playlist.segments[4].key, None);
+    }
+
+    #[test]
+    fn parse_media_rejects_master() {
+        assert!(MediaPlaylist::parse(NHL_LINK, NHL_MASTER).is_err());
+        assert!(MediaPlaylist::parse(
+            NHL_LINK,
+            "#EXTM3U\n#EXT-X-KEY:METHOD=SAMPLE-AES,URI=\"key\"\n#EXTINF:5,\n1.ts\n"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn attributes_with_quoted_commas() {
+        let attributes =
+            parse_attributes(r#"BANDWIDTH=800000,CODECS="avc1.4d401f,mp4a.40.2",AUDIO="aac""#);
+
+        assert_eq!(attributes["BANDWIDTH"], "800000");
+        assert_eq!(attributes["CODECS"], "avc1.4d401f,mp4a.40.2");
+        assert_eq!(attributes["AUDIO"], "aac");
+    }
+
+    #[test]
+    fn resolve_relative_uris() {
+        let base = "https://host.com/a/b/master.m3u8?token=x/y";
+
+        assert_eq!(
+            resolve_uri(base, "720.m3u8"),
+            "https://host.com/a/b/720.m3u8"
+        );
+        assert_eq!(
+            resolve_uri(base, "../c/720.m3u8?t=1"),
+            "https://host.com/a/c/720.m3u8?t=1"
+        );
+        assert_eq!(
+            resolve_uri(base, "/root/720.m3u8"),
+            "https://host.com/root/720.m3u8"
+        );
+        assert_eq!(
+            resolve_uri(base, "//cdn.com/720.m3u8"),
+            "https://cdn.com/720.m3u8"
+        );
+        assert_eq!(
+            resolve_uri("https://host.com?x=1", "720.m3u8"),
+            "https://host.com/720.m3u8"
+        );
+        // Only a scheme makes a uri absolute, not a link in its query
+        assert_eq!(
+            resolve_uri(base, "720.m3u8?back=https://host.com/"),
+            "https://host.com/a/b/720.m3u8?back=https://host.com/"
+        );
+        assert_eq!(
+            resolve_uri(base, "HTTP://cdn.com/720.m3u8"),
+            "HTTP://cdn.com/720.m3u8"
+        );
+    }
+
+    #[test]
+    fn absolutize_playlist() {
+        let playlist = absolutize(NHL_LINK, NHL_MASTER);
+        let master = MasterPlaylist::parse("http://127.0.0.1/other.m3u8", &playlist).unwrap();
+
+        assert_eq!(
+            master.variants,
+            MasterPlaylist::parse(NHL_LINK, NHL_MASTER)
+                .unwrap()
+                .variants
+        );
+        assert!(playlist
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .all(|line| line.starts_with("https://hlslive-akc.med2.med.nhl.com/")));
+
+        let media = absolutize("https://host.com/a/720.m3u8", ENCRYPTED_MEDIA);
+        assert!(media.contains("URI=\"https://host.com/key/2\",IV=0x1234"));
+        assert!(media.contains("\nhttps://host.com/a/00/6600K_1204.ts\n"));
+    }
+}
@@ -0,0 +1,521 @@
+use crate::{
+    api::model::GameStatus,
+    log_error,
+    opt::{Cdn, Command, FeedType, Hosts, Opt, Sport},
+    stream::{LazyStream, Stream, SHARED_CLIENT},
+};
+use async_std::{process, task};
+use failure::{bail, format_err, Error, Fail, ResultExt};
+use futures::AsyncReadExt;
+use isahc::{config::Configurable, http, AsyncBody, Request};
+use once_cell::sync::Lazy;
+use std::{
+    collections::HashMap,
+    fmt,
+    net::ToSocketAddrs,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// How long a probe waits for a host to answer
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long the outcome of a probe is trusted before probing again
+const PROBE_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// Whether each host answered its last probe, and when it was probed
+static PROBES: Lazy<Mutex<HashMap<String, (bool, Instant)>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// None of the stream hosts could be reached, as opposed to a stream that
+/// isn't available yet
+#[derive(Debug)]
+pub struct Unreachable(String);
+
+impl Fail for Unreachable {}
+
+impl fmt::Display for Unreachable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Could not reach stream host {}", self.0)
+    }
+}
+
+/// Every stream host that was reached answered with an HTTP error, which
+/// won't go away by waiting for the stream
+#[derive(Debug)]
+pub struct HttpErrors(String);
+
+impl Fail for HttpErrors {}
+
+impl fmt::Display for HttpErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Stream host answered with an error, {}", self.0)
+    }
+}
+
+/// What a host answered a request for the link of a stream with
+#[derive(Debug, Clone, PartialEq)]
+pub enum Answer {
+    /// The request failed before getting a response
+    Unreachable(String),
+    /// A response with an error status
+    HttpError(http::StatusCode),
+    /// A response without a link, e.g. for a stream that isn't live yet
+    NotAvailable,
+    /// The master link of the stream
+    Link(String),
+}
+
+impl fmt::Display for Answer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Answer::Unreachable(e) => write!(f, "Unreachable, {}", e),
+            Answer::HttpError(status) => write!(f, "HTTP error {}", status),
+            Answer::NotAvailable => write!(f, "Not available"),
+            Answer::Link(_) => write!(f, "OK"),
+        }
+    }
+}
+
+pub fn run(opts: Opt) {
+    task::block_on(async {
+        if let Err(e) = process_host(&opts).await {
+            log_error(e.as_fail());
+            process::exit(1);
+        };
+    });
+}
+
+async fn process_host(opts: &Opt) -> Result<(), Error> {
+    match &opts.command {
+        Command::Host { command: None } => {
+            for host in opts.host.iter() {
+                println!("{}", host);
+            }
+            Ok(())
+        }
+        Command::Host { command: Some(_) } => check(opts).await,
+        _ => unreachable!(),
+    }
+}
+
+/// Time `host` takes to answer a request for its front page, with any status
+pub async fn probe(host: &str) -> Result<(http::StatusCode, Duration), Error> {
+    let request = Request::get(host)
+        .timeout(PROBE_TIMEOUT)
+        .body(AsyncBody::empty())
+        .context("Failed to build URI")?;
+
+    let start = Instant::now();
+    let resp = SHARED_CLIENT.send_async(request).await?;
+
+    Ok((resp.status(), start.elapsed()))
+}
+
+/// Whether `host` answered its last probe, probing it once that's stale
+async fn is_up(host: &str) -> bool {
+    if let Some((up, probed_at)) = PROBES.lock().unwrap().get(host) {
+        if probed_at.elapsed() < PROBE_TTL {
+            return *up;
+        }
+    }
+
+    let up = probe(host).await.is_ok();
+    record(host, up);
+    up
+}
+
+fn record(host: &str, up: bool) {
+    PROBES
+        .lock()
+        .unwrap()
+        .insert(host.to_owned(), (up, Instant::now()));
+}
+
+/// Master link of a stream from the first of `hosts` that has it, along with
+/// that host. `host_link` builds the link to request from a host. Hosts that
+/// are down, answer without a link or with an HTTP error are skipped.
+pub async fn master_link(
+    hosts: &Hosts,
+    host_link: impl Fn(&str) -> String,
+) -> Result<(String, String), Error> {
+    let mut unreachable = vec![];
+    let mut http_errors = vec![];
+    let mut not_available = false;
+
+    for host in hosts.iter() {
+        if !is_up(host).await {
+            unreachable.push(host);
+            continue;
+        }
+
+        match request_link(&host_link(host), &hosts.link_scheme).await {
+            Answer::Link(master_link) => return Ok((host.to_owned(), master_link)),
+            Answer::NotAvailable => not_available = true,
+            Answer::HttpError(status) => http_errors.push(format!("{} {}", host, status)),
+            Answer::Unreachable(_) => {
+                record(host, false);
+                unreachable.push(host);
+            }
+        }
+    }
+
+    if not_available {
+        bail!("Stream not available yet");
+    }
+    if !http_errors.is_empty() {
+        return Err(HttpErrors(http_errors.join(", ")).into());
+    }
+
+    Err(Unreachable(unreachable.join(", ")).into())
+}
+
+/// Whether `e` is from every stream host being down
+pub fn is_unreachable(e: &Error) -> bool {
+    e.downcast_ref::<Unreachable>().is_some()
+}
+
+/// Whether `e` is from every stream host that was reached answering with an
+/// HTTP error
+pub fn is_http_error(e: &Error) -> bool {
+    e.downcast_ref::<HttpErrors>().is_some()
+}
+
+/// Request the link of a stream at `url`. Only an answer with a `link_scheme`
+/// link is a link.
+pub async fn request_link(url: &str, link_scheme: &str) -> Answer {
+    match get_link(url, link_scheme).await {
+        Ok(answer) => answer,
+        Err(e) => Answer::Unreachable(e.to_string()),
+    }
+}
+
+async fn get_link(url: &str, link_scheme: &str) -> Result<Answer, Error> {
+    let uri = url.parse::<http::Uri>().context("Failed to build URI")?;
+    let request = Request::builder()
+        .method("GET")
+        .uri(uri)
+        .body(AsyncBody::empty())
+        .unwrap();
+
+    let resp = SHARED_CLIENT.send_async(request).await?;
+    if !resp.status().is_success() {
+        return Ok(Answer::HttpError(resp.status()));
+    }
+
+    let mut body = resp.into_body();
+    let mut body_text = String::new();
+    body.read_to_string(&mut body_text)
+        .await
+        .context("Failed to read response body text")?;
+
+    if !is_link(&body_text, link_scheme) {
+        return Ok(Answer::NotAvailable);
+    }
+
+    Ok(Answer::Link(body_text))
+}
+
+/// Hosts answer with a link to the master playlist, anything else is a message
+/// or an error page
+fn is_link(body: &str, link_scheme: &str) -> bool {
+    body.starts_with(&format!("{}://", link_scheme))
+}
+
+/// Stream of today's games of a sport to check hosts with
+struct Sample {
+    sport: Sport,
+    label: String,
+    live: bool,
+    stream: Stream,
+}
+
+/// Outcome of one check of `host check`
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Outcome {
+    Passed,
+    Failed,
+    /// No stream could be used to tell whether the host works
+    Unverified,
+}
+
+/// Check every host of `--host` with the streams of each sport, failing if
+/// any check fails. Checks that can't be verified are only reported.
+async fn check(opts: &Opt) -> Result<(), Error> {
+    let mut outcomes = vec![];
+    let mut tally = |outcome: Outcome| outcomes.push(outcome);
+
+    let mut samples = vec![];
+    for sport in opts.sports.iter() {
+        match sample(opts, sport).await {
+            Ok(Some(sample)) => samples.push(sample),
+            Ok(None) => {
+                println!(
+                    "{}: No games today to check streams with",
+                    sport_name(sport)
+                );
+                tally(Outcome::Unverified);
+            }
+            Err(e) => {
+                println!("{}: Could not load games, {}", sport_name(sport), e);
+                tally(Outcome::Failed);
+            }
+        }
+    }
+
+    for host in opts.host.iter() {
+        println!("\n{}", host);
+
+        match resolve(host).await {
+            Ok((addrs, elapsed)) => {
+                println!("  DNS       {} in {} ms", addrs, elapsed.as_millis());
+                tally(Outcome::Passed);
+            }
+            Err(e) => {
+                println!("  DNS       Failed, {}", e);
+                tally(Outcome::Failed);
+                continue;
+            }
+        }
+
+        match probe(host).await {
+            Ok((status, elapsed)) => {
+                println!("  HTTP      {} in {} ms", status, elapsed.as_millis());
+                tally(Outcome::Passed);
+            }
+            Err(e) => {
+                println!("  HTTP      Unreachable, {}", e);
+                tally(Outcome::Failed);
+                continue;
+            }
+        }
+
+        for sample in samples.iter() {
+            for cdn in Cdn::ALL.iter() {
+                let answer =
+                    request_link(&sample.stream.link_on(host, *cdn), &opts.host.link_scheme).await;
+                // Streams of games that haven't started aren't expected yet,
+                // but don't show the host works either
+                tally(match answer {
+                    Answer::Link(_) => Outcome::Passed,
+                    Answer::NotAvailable if !sample.live => Outcome::Unverified,
+                    _ => Outcome::Failed,
+                });
+
+                println!(
+                    "  {:<9} {}, {}{}",
+                    format!("{} {}", sport_name(sample.sport), cdn),
+                    answer,
+                    sample.label,
+                    if sample.live { " (live)" } else { "" }
+                );
+            }
+        }
+    }
+
+    let count = |outcome| outcomes.iter().filter(|o| **o == outcome).count();
+    let (failed, unverified) = (count(Outcome::Failed), count(Outcome::Unverified));
+
+    println!();
+    if failed > 0 {
+        bail!("{} of {} host checks failed", failed, outcomes.len());
+    }
+    if unverified > 0 {
+        println!(
+            "{} of {} host checks passed, {} couldn't be verified as no stream was available",
+            outcomes.len() - unverified,
+            outcomes.len(),
+            unverified
+        );
+    } else {
+        println!("All {} host checks passed", outcomes.len());
+    }
+
+    Ok(())
+}
+
+/// A stream of a live game of `sport` today, or else of a finished one, whose
+/// archive hosts serve, or of the first game that isn't called off. The home
+/// feed is used when there is one.
+async fn sample(opts: &Opt, sport: Sport) -> Result<Option<Sample>, Error> {
+    let mut opts = opts.clone();
+    opts.sports = sport.into();
+
+    let lazy_stream = LazyStream::new(&opts).await?;
+    let games: Vec<_> = lazy_stream
+        .games()
+        .into_iter()
+        .filter(|game| !game.status.is_off())
+        .collect();
+
+    let mut game = match games
+        .iter()
+        .find(|game| game.status == GameStatus::Live)
+        .or_else(|| games.iter().find(|game| game.status == GameStatus::Final))
+        .or_else(|| games.first())
+    {
+        Some(game) => game.clone(),
+        None => return Ok(None),
+    };
+
+    let mut streams = game.streams().await?;
+    let stream = match streams.remove(&FeedType::Home) {
+        Some(stream) => stream,
+        None => match streams.into_iter().next() {
+            Some((_, stream)) => stream,
+            None => return Ok(None),
+        },
+    };
+
+    Ok(Some(Sample {
+        sport,
+        label: format!(
+            "{} @ {} {}",
+            game.away_team.name, game.home_team.name, stream.feed_type
+        ),
+        live: game.status == GameStatus::Live,
+        stream,
+    }))
+}
+
+/// Addresses the name of `host` resolves to, and how long that took
+async fn resolve(host: &str) -> Result<(String, Duration), Error> {
+    let uri = host.parse::<http::Uri>().context("Invalid host")?;
+    let name = uri
+        .host()
+        .ok_or_else(|| format_err!("Invalid host"))?
+        .to_owned();
+    let port = uri
+        .port_u16()
+        .unwrap_or(if uri.scheme_str() == Some("https") {
+            443
+        } else {
+            80
+        });
+
+    let start = Instant::now();
+    let addrs = task::spawn_blocking(move || (name.as_str(), port).to_socket_addrs()).await?;
+    let addrs: Vec<_> = addrs.map(|addr| addr.ip().to_string()).collect();
+
+    Ok((addrs.join(", "), start.elapsed()))
+}
+
+fn sport_name(sport: Sport) -> String {
+    sport.to_string().to_uppercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{net::TcpListener, thread};
+    use tiny_http::{Response, Server};
+
+    /// Stub getM3U8.php host answering every request with `body`
+    fn stub_host(body: &'static str) -> String {
+        stub_host_with_status(200, body)
+    }
+
+    fn stub_host_with_status(status: u16, body: &'static str) -> String {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let addr = server.server_addr();
+
+        thread::spawn(move || {
+            for request in server.incoming_requests() {
+                let response = Response::from_string(body).with_status_code(status);
+                let _ = request.respond(response);
+            }
+        });
+
+        format!("http://{}", addr)
+    }
+
+    /// Host nothing listens on
+    fn dead_host() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        format!("http://{}", listener.local_addr().unwrap())
+    }
+
+    fn hosts(hosts: &[&str]) -> Hosts {
+        hosts.join(",").parse().unwrap()
+    }
+
+    fn link(host: &str) -> String {
+        format!("{}/getM3U8.php?league=nhl&id=1", host)
+    }
+
+    #[test]
+    fn falls_back_to_next_host() {
+        let dead = dead_host();
+        let not_live = stub_host("Not available yet");
+        let live = stub_host("https://cdn.test/master.m3u8");
+
+        let (host, master_link) =
+            task::block_on(master_link(&hosts(&[&dead, &not_live, &live]), link)).unwrap();
+        assert_eq!(host, live);
+        assert_eq!(master_link, "https://cdn.test/master.m3u8");
+    }
+
+    #[test]
+    fn unreachable_hosts() {
+        let e = task::block_on(master_link(&hosts(&[&dead_host()]), link)).unwrap_err();
+        assert!(is_unreachable(&e));
+        assert!(e
+            .to_string()
+            .starts_with("Could not reach stream host http://"));
+
+        let not_live = stub_host("Not available yet");
+        let e = task::block_on(master_link(&hosts(&[&dead_host(), &not_live]), link)).unwrap_err();
+        assert!(!is_unreachable(&e));
+        assert_eq!(e.to_string(), "Stream not available yet");
+    }
+
+    #[test]
+    fn http_errors() {
+        let failing = stub_host_with_status(503, "");
+        let live = stub_host("https://cdn.test/master.m3u8");
+        let (host, _) = task::block_on(master_link(&hosts(&[&failing, &live]), link)).unwrap();
+        assert_eq!(host, live);
+
+        let e = task::block_on(master_link(&hosts(&[&dead_host(), &failing]), link)).unwrap_err();
+        assert!(is_http_error(&e));
+        assert_eq!(
+            e.to_string(),
+            format!(
+                "Stream host answered with an error, {} 503 Service Unavailable",
+                failing
+            )
+        );
+
+        let not_live = stub_host("Not available yet");
+        let e = task::block_on(master_link(&hosts(&[&failing, &not_live]), link)).unwrap_err();
+        assert_eq!(e.to_string(), "Stream not available yet");
+    }
+
+    #[test]
+    fn classifies_answers() {
+        let answer = |host: String| task::block_on(request_link(&link(&host), "https"));
+
+        assert_eq!(
+            answer(stub_host("https://cdn.test/master.m3u8")),
+            Answer::Link("https://cdn.test/master.m3u8".to_owned())
+        );
+        assert_eq!(answer(stub_host("Not available yet")), Answer::NotAvailable);
+        assert_eq!(
+            answer(stub_host("httpd: upstream timed out")),
+            Answer::NotAvailable
+        );
+        assert_eq!(
+            answer(stub_host_with_status(503, "")),
+            Answer::HttpError(http::StatusCode::SERVICE_UNAVAILABLE)
+        );
+        assert!(matches!(answer(dead_host()), Answer::Unreachable(_)));
+
+        // Plain http links only from a host given `--link-scheme http`
+        let http_link = stub_host("http://cdn.test/master.m3u8");
+        assert_eq!(answer(http_link.clone()), Answer::NotAvailable);
+        assert_eq!(
+            task::block_on(request_link(&link(&http_link), "http")),
+            Answer::Link("http://cdn.test/master.m3u8".to_owned())
+        );
+    }
+}
@@ -2,12 +2,23 @@ use crate::opt::OutputType;
 use colored::Colorize;
 
 mod api;
+mod cdn;
 mod completions;
+mod config;
+mod dump;
 mod generate;
+mod hls;
+mod host;
 mod opt;
+mod recorder;
+mod retry;
+mod scores;
 mod select;
+mod serve;
 mod stream;
 mod streamlink;
+mod team;
+mod watch;
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 const BANNER: &str = r#"
@@ -26,8 +37,14 @@ fn main() {
         OutputType::Play(opts) => crate::streamlink::run(opts),
         OutputType::Record(opts) => crate::streamlink::run(opts),
         OutputType::Cast(opts) => crate::streamlink::run(opts),
+        OutputType::Serve(opts) => crate::serve::run(opts),
+        OutputType::Watch(opts) => crate::watch::run(opts),
+        OutputType::Cache => crate::api::cache::run(),
+        OutputType::Scores(opts) => crate::scores::run(opts),
+        OutputType::Dump(opts) => crate::dump::run(opts),
+        OutputType::Config(opts) => crate::config::run(opts),
         OutputType::Completions(opts) => crate::completions::run(opts),
-        OutputType::Host(host) => println!("{}", host),
+        OutputType::Host(opts) => crate::host::run(opts),
     }
 }
 
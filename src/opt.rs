@@ -1,14 +1,23 @@
 use crate::VERSION;
-use chrono::{format::ParseError, NaiveDate};
-use failure::{bail, Error};
+use chrono::{format::ParseError, Duration, Local, NaiveDate};
+use failure::{bail, format_err, Error};
 use isahc::http::Uri;
-use std::{path::PathBuf, str::FromStr};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::{net::SocketAddr, path::PathBuf, str::FromStr};
 use structopt::{clap::AppSettings::DeriveDisplayOrder, StructOpt};
 
 const HOST: &str = "http://freesports.ddns.net";
 
 pub fn parse_opts() -> OutputType {
-    let opts = Opt::from_args();
+    let matches = Opt::clap().get_matches();
+    let mut opts = Opt::from_clap(&matches);
+
+    if let Err(e) = crate::config::apply(&mut opts, &matches) {
+        crate::log_error(e.as_fail());
+        std::process::exit(1);
+    }
+    opts.host.link_scheme = opts.link_scheme.clone();
 
     match opts.command {
         Command::Select { .. } => OutputType::Select(opts),
@@ -16,8 +25,14 @@ pub fn parse_opts() -> OutputType {
         Command::Play { .. } => OutputType::Play(opts),
         Command::Record { .. } => OutputType::Record(opts),
         Command::Cast { .. } => OutputType::Cast(opts),
+        Command::Serve { .. } => OutputType::Serve(opts),
+        Command::Watch { .. } => OutputType::Watch(opts),
+        Command::Cache { .. } => OutputType::Cache,
+        Command::Scores => OutputType::Scores(opts),
+        Command::Dump { .. } => OutputType::Dump(opts),
+        Command::Config { .. } => OutputType::Config(opts),
         Command::Completions { .. } => OutputType::Completions(opts),
-        Command::Host { .. } => OutputType::Host(opts.host),
+        Command::Host { .. } => OutputType::Host(opts),
     }
 }
 
@@ -32,34 +47,123 @@ pub fn parse_opts() -> OutputType {
 pub struct Opt {
     #[structopt(subcommand)]
     pub command: Command,
-    #[structopt(long, parse(try_from_str), default_value = Sport::Nhl.into(), global = true, possible_values(&["mlb","nhl"]))]
+    #[structopt(long = "sport", parse(try_from_str), default_value = Sport::Nhl.into(), global = true, value_name = "sport")]
     /// Specify which sport to get streams for
-    pub sport: Sport,
+    ///
+    /// Can be 'mlb', 'nhl', a comma separated list like 'nhl,mlb' or 'all'.
+    /// Games of every sport are merged together.
+    pub sports: Sports,
     #[structopt(long, parse(try_from_str = parse_date), value_name = "YYYYMMDD", global = true)]
     /// Specify what date to use for games, defaults to today
     pub date: Option<NaiveDate>,
-    #[structopt(long, parse(try_from_str), default_value = Cdn::Akc.into(), global = true, possible_values(&["akc","l3c"]))]
-    /// Specify which CDN to use
+    #[structopt(long, value_name = "N", global = true)]
+    /// Load games for N days, starting from '--date' or today
+    pub days: Option<u32>,
+    #[structopt(long, parse(try_from_str), value_name = "FROM..TO", global = true, conflicts_with_all(&["date", "days"]))]
+    /// Load games for every day from FROM to TO, e.g. '20210401..20210407'
+    pub date_range: Option<DateRange>,
+    #[structopt(long, parse(try_from_str), default_value = Cdn::Akc.into(), global = true, possible_values(&["akc","l3c","auto"]))]
+    /// Specify which CDN to use. 'auto' picks the faster one, and switches to the
+    /// other if it starts failing during 'play', 'record' or 'cast'
+    ///
+    /// Links written by 'generate' without '--server' stay on the CDN picked
+    /// when they're generated.
     pub cdn: Cdn,
-    #[structopt(long, parse(try_from_str), global = true, possible_values(&["720p60","720p","540p","504p","360p","288p","224p","216p"]))]
+    #[structopt(long, parse(try_from_str), global = true)]
     /// Specify a quality to use, otherwise stream will be adaptive
+    ///
+    /// Can be 'best', 'worst', '<height>p[fps]' (e.g. '1080p60' or '540p') or
+    /// 'max-bandwidth=<kbps>'. The highest variant not exceeding the requested
+    /// height & frame rate (30 if not given) or bandwidth is used. If every
+    /// variant exceeds it, the lowest variant is used instead.
     pub quality: Option<Quality>,
-    #[structopt(long, global = true)]
+    #[structopt(long, global = true, overrides_with = "retry")]
     /// Disables unavailable stream retry for `play`, `record`, and `cast` commands. Program will exit instead.
+    ///
+    /// Otherwise the stream is checked for rarely hours before the game, and
+    /// often around the start of the game.
     pub disable_retry: bool,
-    #[structopt(long, global = true, default_value = HOST)]
-    /// Specify a host
-    pub host: String,
+    #[structopt(long, global = true, hidden = true, overrides_with = "disable-retry")]
+    /// Turn off '--disable-retry' set in the config
+    pub retry: bool,
+    #[structopt(long, global = true, default_value = "240", value_name = "minutes")]
+    /// Stop waiting for an unavailable stream this many minutes after the game's
+    /// scheduled start
+    pub give_up_after: u32,
+    #[structopt(long, global = true, overrides_with = "no-spoilers")]
+    /// Show scores and the period or inning of games that started
+    ///
+    /// Hidden by default so games can be watched on delay. XMLTV guides never
+    /// include them.
+    pub spoilers: bool,
+    #[structopt(long, global = true, hidden = true, overrides_with = "spoilers")]
+    /// Turn off '--spoilers' set in the config
+    pub no_spoilers: bool,
+    #[structopt(long, global = true, overrides_with = "cache")]
+    /// Don't use or update the cache of stats API responses
+    pub no_cache: bool,
+    #[structopt(long, global = true, hidden = true, overrides_with = "no-cache")]
+    /// Turn off '--no-cache' set in the config
+    pub cache: bool,
+    #[structopt(long, parse(from_os_str), value_name = "FILE", global = true)]
+    /// Load the schedule from a JSON file instead of the stats API
+    ///
+    /// '{sport}' in the path is replaced by each sport, e.g. 'nhl'. Files are
+    /// saved in this format by 'lazystream dump'.
+    pub schedule_file: Option<PathBuf>,
+    #[structopt(long, parse(from_os_str), value_name = "FILE", global = true)]
+    /// Load teams from a JSON file instead of the stats API
+    pub teams_file: Option<PathBuf>,
+    #[structopt(long, parse(from_os_str), value_name = "DIR", global = true)]
+    /// Load game content from '<DIR>/<game_pk>.json' instead of the stats API
+    pub content_dir: Option<PathBuf>,
+    #[structopt(long, value_name = "URL", global = true)]
+    /// Fetch stats API responses from a mirror in the layout 'lazystream dump'
+    /// saves instead, e.g. a dumped directory served over HTTP
+    pub api_mirror: Option<String>,
+    #[structopt(long, value_name = "HOST[,HOST...]", global = true, default_value = HOST)]
+    /// Specify a host, or several separated by commas to fall back on in order
+    /// when one is down or doesn't have a stream
+    pub host: Hosts,
+    #[structopt(long, global = true, default_value = "https", possible_values(&["https", "http"]))]
+    /// Scheme of the stream links hosts answer with, anything else is taken as a
+    /// message that the stream isn't available
+    ///
+    /// Hosts hand out https links. Use 'http' only for a local stand-in of a
+    /// host that serves plain http, e.g. when testing.
+    pub link_scheme: String,
+    #[structopt(long, parse(from_os_str), value_name = "FILE", global = true)]
+    /// Read defaults for options from this TOML file, instead of
+    /// 'lazystream/config.toml' in the user config directory
+    ///
+    /// Keys are the long names of options, e.g. 'cdn = "l3c"', with options of
+    /// subcommands under their own section, e.g. '[record]'. Options given on
+    /// the command line take precedence, flags set in the file are turned off
+    /// with '--no-<flag>'.
+    pub config: Option<PathBuf>,
+    #[structopt(long, value_name = "NAME", global = true)]
+    /// Use the options of '[profile.NAME]' in the config file, over the rest of
+    /// the file
+    pub profile: Option<String>,
 }
 
 #[derive(StructOpt, Debug, PartialEq, Clone)]
 pub enum Command {
-    #[structopt(usage = "lazystream select [--resolve] [OPTIONS]")]
+    #[structopt(usage = "lazystream select [--resolve --list-qualities] [OPTIONS]")]
     /// Select stream link via command line
     Select {
-        #[structopt(long)]
+        #[structopt(long, overrides_with = "no-resolve")]
         /// Resolve url to the actual hls link, if it's available
         resolve: bool,
+        #[structopt(long, hidden = true, overrides_with = "resolve")]
+        /// Turn off '--resolve' set in the config
+        no_resolve: bool,
+        #[structopt(long, overrides_with = "no-list-qualities")]
+        /// List the qualities offered by the selected stream, if it's available
+        list_qualities: bool,
+        #[structopt(long, hidden = true, overrides_with = "list-qualities")]
+        /// Turn off '--list-qualities' set in the config
+        no_list_qualities: bool,
     },
     #[structopt(usage = "lazystream generate <SUBCOMMAND> [OPTIONS]", setting = DeriveDisplayOrder)]
     /// Generate an xmltv and/or playlist formatted output for all games
@@ -78,7 +182,7 @@ pub enum Command {
         command: PlayCommand,
     },
     #[structopt(usage = "lazystream record <SUBCOMMAND> [OPTIONS]", setting = DeriveDisplayOrder)]
-    /// Record a game, requires StreamLink
+    /// Record a game, requires StreamLink unless '--backend native' is used
     ///
     /// Game can be chosen from command line with 'select' subcommand or supplied
     /// in advanced with 'team' subcommand
@@ -95,6 +199,108 @@ pub enum Command {
         #[structopt(subcommand)]
         command: CastCommand,
     },
+    #[structopt(
+        usage = "lazystream serve [--bind <ADDRESS> --proxy-playlist --ttl <minutes> --hdhomerun] [OPTIONS]"
+    )]
+    /// Run a local HTTP server that resolves stream links on demand
+    ///
+    /// Streams are served at /stream/{sport}/{game_pk}/{feed}, optionally with
+    /// '?date=YYYY-MM-DD' and '?quality=<QUALITY>'. Each request resolves the
+    /// current link, so playlists pointing at the server don't go stale. Use
+    /// 'generate <SUBCOMMAND> --server <URL>' to create them.
+    ///
+    /// An XMLTV playlist & guide for the day, matching 'generate xmltv', are
+    /// served at /playlist.m3u and /epg.xml
+    ///
+    /// With '--hdhomerun' the server also acts as an HDHomeRun tuner, so it can be
+    /// added to Plex, Emby or Jellyfin directly. Bind to an address reachable
+    /// from those, e.g. '--bind 0.0.0.0:8080'.
+    Serve {
+        #[structopt(long, default_value = "127.0.0.1:8080", value_name = "ADDRESS")]
+        /// Address to listen on
+        bind: SocketAddr,
+        #[structopt(long, value_name = "URL")]
+        /// Url clients reach the server at, for links in the playlist, guide &
+        /// lineup. Defaults to the bind address, or the LAN address of this
+        /// machine when listening on every interface
+        advertise_url: Option<String>,
+        #[structopt(long, overrides_with = "no-proxy-playlist")]
+        /// Serve the playlist itself instead of redirecting to it
+        proxy_playlist: bool,
+        #[structopt(long, hidden = true, overrides_with = "proxy-playlist")]
+        /// Turn off '--proxy-playlist' set in the config
+        no_proxy_playlist: bool,
+        #[structopt(long, default_value = "30", value_name = "minutes")]
+        /// Number of minutes to cache /playlist.m3u & /epg.xml before rebuilding
+        /// them. They're always rebuilt once the date changes
+        ttl: u64,
+        #[structopt(long, overrides_with = "no-hdhomerun")]
+        /// Emulate an HDHomeRun tuner & announce it on the network with SSDP
+        hdhomerun: bool,
+        #[structopt(long, hidden = true, overrides_with = "hdhomerun")]
+        /// Turn off '--hdhomerun' set in the config
+        no_hdhomerun: bool,
+        #[structopt(long, default_value = "1000")]
+        /// Specify the starting channel number for the XMLVTV output
+        start_channel: u32,
+        #[structopt(long, default_value = "Lazyman")]
+        /// Specify the channel name prefix
+        channel_prefix: String,
+        #[structopt(long, parse(try_from_str), possible_values(&["HOME", "AWAY", "FRENCH", "COMPOSITE", "NATIONAL"]))]
+        /// Feed types to exclude from output
+        exclude_feeds: Vec<FeedType>,
+        #[structopt(long, default_value = "0", value_name = "minutes")]
+        /// Number of minutes to prepend to the start time of the stream
+        start_prepend: u16,
+        #[structopt(long, overrides_with = "no-trim")]
+        /// Only output the number of channels as there are streams
+        ///
+        /// Default is to output 100 fixed channels
+        trim: bool,
+        #[structopt(long, hidden = true, overrides_with = "trim")]
+        /// Turn off '--trim' set in the config
+        no_trim: bool,
+    },
+    #[structopt(usage = "lazystream watch [--format <format>] [OPTIONS]")]
+    /// Report when each feed goes live, or disappears
+    ///
+    /// Every game's feeds are checked until the program is stopped, more often
+    /// around the start of games that aren't live yet.
+    Watch {
+        #[structopt(long, parse(try_from_str), default_value = WatchFormat::Human.into(), possible_values(&["human", "ndjson"]))]
+        /// Print events as human readable lines or as newline delimited JSON
+        format: WatchFormat,
+    },
+    #[structopt(usage = "lazystream scores [--spoilers] [OPTIONS]")]
+    /// List games with their status, and scores with '--spoilers'
+    Scores,
+    #[structopt(usage = "lazystream cache <SUBCOMMAND>")]
+    /// Manage the cache of stats API responses
+    ///
+    /// Teams are cached for days, schedules for minutes and game content for
+    /// hours. Use '--no-cache' to skip the cache for a single run.
+    Cache {
+        #[structopt(subcommand)]
+        command: CacheCommand,
+    },
+    #[structopt(usage = "lazystream dump <DIR> [OPTIONS]")]
+    /// Save the stats API responses for a day, to replay them offline
+    ///
+    /// The schedule of '--date' (or today), teams and the content of every
+    /// game are saved for each sport under '<DIR>/<sport>'. Replay them with
+    /// '--schedule-file <DIR>/{sport}/schedule.json --teams-file
+    /// <DIR>/{sport}/teams.json --content-dir <DIR>/{sport}/content'.
+    Dump {
+        #[structopt(name = "DIR", parse(from_os_str))]
+        /// Directory to save responses to
+        dir: PathBuf,
+    },
+    #[structopt(usage = "lazystream config <SUBCOMMAND> [OPTIONS]")]
+    /// Inspect the config file
+    Config {
+        #[structopt(subcommand)]
+        command: ConfigCommand,
+    },
     #[structopt(usage = "lazystream completions <SHELL> <TARGET_DIR>")]
     /// Output shell completions to a target directory
     Completions {
@@ -105,9 +311,12 @@ pub enum Command {
         /// Target directory to save completions
         target: PathBuf,
     },
-    #[structopt(usage = "lazystream host [OPTIONS]")]
-    /// Print the host used by 'lazystream'
-    Host,
+    #[structopt(usage = "lazystream host [SUBCOMMAND] [OPTIONS]")]
+    /// Print the hosts used by 'lazystream', or check them with 'host check'
+    Host {
+        #[structopt(subcommand)]
+        command: Option<HostCommand>,
+    },
 }
 
 #[derive(StructOpt, Debug, PartialEq, Clone)]
@@ -117,15 +326,21 @@ pub enum PlayCommand {
     )]
     /// Select a game from the command line to play in VLC (or --custom-player <PATH>)
     Select {
-        #[structopt(long)]
+        #[structopt(long, overrides_with = "no-restart")]
         /// If live, restart the stream from the beginning
         restart: bool,
+        #[structopt(long, hidden = true, overrides_with = "restart")]
+        /// Turn off '--restart' set in the config
+        no_restart: bool,
         #[structopt(long, parse(try_from_str))]
         /// Proxy server address to be passed to Streamlink
         proxy: Option<Uri>,
-        #[structopt(long)]
+        #[structopt(long, overrides_with = "no-passthrough")]
         /// Pass stream directly to VLC, this allows playback seeking
         passthrough: bool,
+        #[structopt(long, hidden = true, overrides_with = "passthrough")]
+        /// Turn off '--passthrough' set in the config
+        no_passthrough: bool,
         #[structopt(long, value_name = "[HH:]MM:SS", parse(try_from_str = parse_offset))]
         /// Amount of time to skip from the beginning of the stream. For live streams, this is a negative offset from the end of the stream (rewind).
         offset: Option<String>,
@@ -136,33 +351,44 @@ pub enum PlayCommand {
         custom_player: Option<PathBuf>,
     },
     #[structopt(
-        usage = "lazystream play team <TEAM> [--restart --feed-type <feed-type> --proxy <PROXY> --passthrough] [OPTIONS]"
+        usage = "lazystream play team <TEAM>[,<TEAM>...] [--restart --feed-type <feed-type> --game-number <N> --proxy <PROXY> --passthrough --priority] [OPTIONS]"
     )]
-    /// Specify team abbreviation. If / when stream is available, will play in VLC (or --custom-player <PATH>)
+    /// Specify a team. If / when stream is available, will play in VLC (or --custom-player <PATH>)
     ///
     /// Example: 'lazystream play team VGK' will play the stream for the
-    /// Golden Knights game in VLC.
+    /// Golden Knights game in VLC. With several teams, e.g. 'VGK,BOS', the first
+    /// game to go live is played.
     ///
     /// The program will stay running if a game is scheduled for the day, but stream is not yet
     /// available. Program will periodically check for the stream availability and once live,
     /// will pass that stream to VLC (or --custom-player <PATH>) to play.
     Team {
-        #[structopt(name = "TEAM")]
-        /// Team abbreviation
-        team_abbrev: String,
-        #[structopt(long)]
+        #[structopt(name = "TEAM", parse(try_from_str))]
+        /// Team abbreviation, name or location, e.g. 'VGK', 'vegas' or 'Golden Knights'.
+        /// Several teams can be given separated by commas, e.g. 'VGK,BOS'
+        teams: Teams,
+        #[structopt(long, overrides_with = "no-restart")]
         /// If live, restart the stream from the beginning and record the entire thing
         restart: bool,
+        #[structopt(long, hidden = true, overrides_with = "restart")]
+        /// Turn off '--restart' set in the config
+        no_restart: bool,
         #[structopt(long, parse(try_from_str), possible_values(&["HOME", "AWAY", "FRENCH", "COMPOSITE", "NATIONAL"]))]
         /// Specify the feed type to download. Will default to supplied
         /// team's applicable Home / Away feed
         feed_type: Option<FeedType>,
+        #[structopt(long, value_name = "N")]
+        /// Only follow game N of a doubleheader. By default every game is followed
+        game_number: Option<u32>,
         #[structopt(long, parse(try_from_str))]
         /// Proxy server address to be passed to Streamlink
         proxy: Option<Uri>,
-        #[structopt(long)]
+        #[structopt(long, overrides_with = "no-passthrough")]
         /// Pass stream directly to VLC, this allows playback seeking
         passthrough: bool,
+        #[structopt(long, hidden = true, overrides_with = "passthrough")]
+        /// Turn off '--passthrough' set in the config
+        no_passthrough: bool,
         #[structopt(long, value_name = "[HH:]MM:SS", parse(try_from_str = parse_offset))]
         /// Amount of time to skip from the beginning of the stream. For live streams, this is a negative offset from the end of the stream (rewind).
         offset: Option<String>,
@@ -171,22 +397,33 @@ pub enum PlayCommand {
         ///
         /// See https://streamlink.github.io/players.html for list of supported players
         custom_player: Option<PathBuf>,
+        #[structopt(long, overrides_with = "no-priority")]
+        /// With several teams, play the game of the team listed first when several
+        /// are live, instead of the game that started first
+        priority: bool,
+        #[structopt(long, hidden = true, overrides_with = "priority")]
+        /// Turn off '--priority' set in the config
+        no_priority: bool,
     },
 }
 
 #[derive(StructOpt, Debug, PartialEq, Clone)]
 pub enum RecordCommand {
     #[structopt(
-        usage = "lazystream record select <OUTPUT_DIR> [--restart --proxy <PROXY>] [OPTIONS]"
+        usage = "lazystream record select [OUTPUT_DIR] [--restart --proxy <PROXY> --backend <backend>] [OPTIONS]"
     )]
     /// Select a game from the command line to record to OUTPUT DIR
     Select {
         #[structopt(name = "OUTPUT_DIR", parse(from_os_str))]
-        /// Directory to save game recordings
-        output: PathBuf,
-        #[structopt(long)]
+        /// Directory to save game recordings, defaults to 'output' under '[record]'
+        /// in the config file
+        output: Option<PathBuf>,
+        #[structopt(long, overrides_with = "no-restart")]
         /// If live, restart the stream from the beginning and record the entire thing
         restart: bool,
+        #[structopt(long, hidden = true, overrides_with = "restart")]
+        /// Turn off '--restart' set in the config
+        no_restart: bool,
         #[structopt(long, parse(try_from_str))]
         /// Proxy server address to be passed to Streamlink
         proxy: Option<Uri>,
@@ -196,32 +433,45 @@ pub enum RecordCommand {
         #[structopt(long)]
         /// Specify the name / language of the audio source you'd like to use E.g. "en" or "English" for English track
         audio_source: Option<String>,
+        #[structopt(long, parse(try_from_str), default_value = RecordBackend::Streamlink.into(), possible_values(&["native", "streamlink"]))]
+        /// Specify what records the stream. 'native' doesn't require Streamlink
+        backend: RecordBackend,
     },
     #[structopt(
-        usage = "lazystream record team <TEAM> <OUTPUT_DIR> [--restart --feed-type <feed-type> --proxy <PROXY>] [OPTIONS]"
+        usage = "lazystream record team <TEAM>[,<TEAM>...] [OUTPUT_DIR] [--restart --feed-type <feed-type> --game-number <N> --proxy <PROXY> --backend <backend>] [OPTIONS]"
     )]
-    /// Specify team abbreviation. If / when stream is available, will record to OUTPUT DIR.
+    /// Specify a team. If / when stream is available, will record to OUTPUT DIR.
     ///
     /// Example: 'lazystream record team VGK /tmp/game.mp4' will download the stream for the
-    /// Golden Knights game to /tmp/game.mp4.
+    /// Golden Knights game to /tmp/game.mp4. With several teams, e.g. 'VGK,BOS', every
+    /// game is recorded, at the same time if they overlap. Both games of a doubleheader
+    /// are recorded one after the other.
     ///
     /// The program will stay running if a game is scheduled for the day, but stream is not yet
     /// available. Program will periodically check for the stream availability and once live,
-    /// will pass that stream to StreamLink to be downloaded.
+    /// will pass that stream to StreamLink (or the native recorder) to be downloaded.
     Team {
-        #[structopt(name = "TEAM")]
-        /// Team abbreviation
-        team_abbrev: String,
+        #[structopt(name = "TEAM", parse(try_from_str))]
+        /// Team abbreviation, name or location, e.g. 'VGK', 'vegas' or 'Golden Knights'.
+        /// Several teams can be given separated by commas, e.g. 'VGK,BOS'
+        teams: Teams,
         #[structopt(name = "OUTPUT_DIR", parse(from_os_str))]
-        /// Directory to save game recordings
-        output: PathBuf,
-        #[structopt(long)]
+        /// Directory to save game recordings, defaults to 'output' under '[record]'
+        /// in the config file
+        output: Option<PathBuf>,
+        #[structopt(long, overrides_with = "no-restart")]
         /// If live, restart the stream from the beginning and record the entire thing
         restart: bool,
+        #[structopt(long, hidden = true, overrides_with = "restart")]
+        /// Turn off '--restart' set in the config
+        no_restart: bool,
         #[structopt(long, parse(try_from_str), possible_values(&["HOME", "AWAY", "FRENCH", "COMPOSITE", "NATIONAL"]))]
         /// Specify the feed type to download. Will default to supplied
         /// team's applicable Home / Away feed
         feed_type: Option<FeedType>,
+        #[structopt(long, value_name = "N")]
+        /// Only follow game N of a doubleheader. By default every game is followed
+        game_number: Option<u32>,
         #[structopt(long, parse(try_from_str))]
         /// Proxy server address to be passed to Streamlink
         proxy: Option<Uri>,
@@ -231,17 +481,32 @@ pub enum RecordCommand {
         #[structopt(long)]
         /// Specify the name / language of the audio source you'd like to use E.g. "en" or "English" for English track
         audio_source: Option<String>,
+        #[structopt(long, parse(try_from_str), default_value = RecordBackend::Streamlink.into(), possible_values(&["native", "streamlink"]))]
+        /// Specify what records the stream. 'native' doesn't require Streamlink
+        backend: RecordBackend,
     },
 }
 
+impl RecordCommand {
+    pub fn backend(&self) -> RecordBackend {
+        match self {
+            RecordCommand::Select { backend, .. } => *backend,
+            RecordCommand::Team { backend, .. } => *backend,
+        }
+    }
+}
+
 #[derive(StructOpt, Debug, PartialEq, Clone)]
 pub enum CastCommand {
     #[structopt(usage = "lazystream cast select [--restart --proxy <PROXY>] [OPTIONS]")]
     /// Select a game and chromecast device from the command line to cast to
     Select {
-        #[structopt(long)]
+        #[structopt(long, overrides_with = "no-restart")]
         /// If live, restart the stream from the beginning and cast the entire thing
         restart: bool,
+        #[structopt(long, hidden = true, overrides_with = "restart")]
+        /// Turn off '--restart' set in the config
+        no_restart: bool,
         #[structopt(long, parse(try_from_str))]
         /// Proxy server address to be passed to Streamlink
         proxy: Option<Uri>,
@@ -253,26 +518,35 @@ pub enum CastCommand {
         audio_source: Option<String>,
     },
     #[structopt(
-        usage = "lazystream cast team <TEAM> <CHROMECAST_HOST> [--restart --feed-type <feed-type> --proxy <PROXY>] [OPTIONS]"
+        usage = "lazystream cast team <TEAM>[,<TEAM>...] [CHROMECAST_HOST] [--restart --feed-type <feed-type> --game-number <N> --proxy <PROXY> --priority] [OPTIONS]"
     )]
-    /// Specify team abbreviation. If / when stream is available, will cast to CHROMECAST_HOST
+    /// Specify a team. If / when stream is available, will cast to CHROMECAST_HOST
     ///
     /// Example: 'lazystream cast team VGK 192.16.0.100' will cast the stream for the
-    /// Golden Knights game to the Chromecast at 192.168.0.100.
+    /// Golden Knights game to the Chromecast at 192.168.0.100. With several teams,
+    /// e.g. 'VGK,BOS', the first game to go live is cast.
     Team {
-        #[structopt(name = "TEAM")]
-        /// Team abbreviation
-        team_abbrev: String,
+        #[structopt(name = "TEAM", parse(try_from_str))]
+        /// Team abbreviation, name or location, e.g. 'VGK', 'vegas' or 'Golden Knights'.
+        /// Several teams can be given separated by commas, e.g. 'VGK,BOS'
+        teams: Teams,
         #[structopt(name = "CHROMECAST_HOST")]
-        /// IP / Hostname of the Chromecast
-        cast_host: String,
-        #[structopt(long)]
+        /// IP / Hostname of the Chromecast, defaults to 'chromecast-host' under
+        /// '[cast]' in the config file
+        cast_host: Option<String>,
+        #[structopt(long, overrides_with = "no-restart")]
         /// If live, restart the stream from the beginning and cast the entire thing
         restart: bool,
+        #[structopt(long, hidden = true, overrides_with = "restart")]
+        /// Turn off '--restart' set in the config
+        no_restart: bool,
         #[structopt(long, parse(try_from_str), possible_values(&["HOME", "AWAY", "FRENCH", "COMPOSITE", "NATIONAL"]))]
         /// Specify the feed type to cast. Will default to supplied
         /// team's applicable Home / Away feed
         feed_type: Option<FeedType>,
+        #[structopt(long, value_name = "N")]
+        /// Only follow game N of a doubleheader. By default every game is followed
+        game_number: Option<u32>,
         #[structopt(long, parse(try_from_str))]
         /// Proxy server address to be passed to Streamlink
         proxy: Option<Uri>,
@@ -282,6 +556,46 @@ pub enum CastCommand {
         #[structopt(long)]
         /// Specify the name / language of the audio source you'd like to use E.g. "en" or "English" for English track
         audio_source: Option<String>,
+        #[structopt(long, overrides_with = "no-priority")]
+        /// With several teams, cast the game of the team listed first when several
+        /// are live, instead of the game that started first
+        priority: bool,
+        #[structopt(long, hidden = true, overrides_with = "priority")]
+        /// Turn off '--priority' set in the config
+        no_priority: bool,
+    },
+}
+
+#[derive(StructOpt, Debug, PartialEq, Clone)]
+pub enum CacheCommand {
+    #[structopt(usage = "lazystream cache clear")]
+    /// Remove everything from the cache
+    Clear,
+}
+
+#[derive(StructOpt, Debug, PartialEq, Clone)]
+pub enum HostCommand {
+    #[structopt(usage = "lazystream host check [OPTIONS]")]
+    /// Check each host can be resolved & reached, and hands out links for the
+    /// streams of today's games on each CDN, for each sport of '--sport'
+    ///
+    /// Exits with a non-zero status if any check fails. Checks without a
+    /// stream to verify the host with, e.g. when there are no games, are only
+    /// reported.
+    Check,
+}
+
+#[derive(StructOpt, Debug, PartialEq, Clone)]
+pub enum ConfigCommand {
+    #[structopt(usage = "lazystream config show [OPTIONS] [-- <COMMAND>...]")]
+    /// Print the options in effect, merged from the config file & command line
+    ///
+    /// Options of a command are shown when it's given after '--', e.g.
+    /// 'lazystream config show -- record team VGK --no-restart'.
+    Show {
+        #[structopt(name = "COMMAND", last = true)]
+        /// Command line to merge with the config file, without 'lazystream'
+        command: Vec<String>,
     },
 }
 
@@ -296,6 +610,17 @@ pub enum GenerateCommand {
         #[structopt(long, parse(try_from_str), possible_values(&["HOME", "AWAY", "FRENCH", "COMPOSITE", "NATIONAL"]))]
         /// Feed types to exclude from output
         exclude_feeds: Vec<FeedType>,
+        #[structopt(long, value_name = "URL")]
+        /// Point entries at a 'lazystream serve' server, e.g. 'http://127.0.0.1:8080'
+        ///
+        /// Links are then resolved when played instead of when generated
+        server: Option<String>,
+        #[structopt(long, overrides_with = "no-exclude-postponed")]
+        /// Leave out postponed & cancelled games
+        exclude_postponed: bool,
+        #[structopt(long, hidden = true, overrides_with = "exclude-postponed")]
+        /// Turn off '--exclude-postponed' set in the config
+        no_exclude_postponed: bool,
     },
     #[structopt(usage = "lazystream generate xmltv <FILE> [--start-channel INT] [OPTIONS]")]
     /// Generate a .xml XMLTV file for all games with corresponding .m3u playlist file
@@ -315,33 +640,150 @@ pub enum GenerateCommand {
         #[structopt(long, default_value = "0", value_name = "minutes")]
         /// Number of minutes to prepend to the start time of the stream
         start_prepend: u16,
-        #[structopt(long)]
+        #[structopt(long, overrides_with = "no-trim")]
         /// Only output the number of channels as there are streams
         ///
         /// Default is to output 100 fixed channels
         trim: bool,
+        #[structopt(long, hidden = true, overrides_with = "trim")]
+        /// Turn off '--trim' set in the config
+        no_trim: bool,
+        #[structopt(long, value_name = "URL")]
+        /// Point entries at a 'lazystream serve' server, e.g. 'http://127.0.0.1:8080'
+        ///
+        /// Links are then resolved when played instead of when generated
+        server: Option<String>,
+        #[structopt(long, overrides_with = "no-exclude-postponed")]
+        /// Leave out postponed & cancelled games
+        exclude_postponed: bool,
+        #[structopt(long, hidden = true, overrides_with = "exclude-postponed")]
+        /// Turn off '--exclude-postponed' set in the config
+        no_exclude_postponed: bool,
     },
 }
 
+impl GenerateCommand {
+    pub fn server(&self) -> Option<&str> {
+        match self {
+            GenerateCommand::Playlist { server, .. } => server.as_deref(),
+            GenerateCommand::Xmltv { server, .. } => server.as_deref(),
+        }
+    }
+
+    pub fn exclude_postponed(&self) -> bool {
+        match self {
+            GenerateCommand::Playlist {
+                exclude_postponed, ..
+            } => *exclude_postponed,
+            GenerateCommand::Xmltv {
+                exclude_postponed, ..
+            } => *exclude_postponed,
+        }
+    }
+}
+
 pub enum OutputType {
     Generate(Opt),
     Select(Opt),
     Play(Opt),
     Record(Opt),
     Cast(Opt),
+    Serve(Opt),
+    Watch(Opt),
+    Cache,
+    Scores(Opt),
+    Dump(Opt),
+    Config(Opt),
     Completions(Opt),
-    Host(String),
+    Host(Opt),
 }
 
-fn parse_date(src: &str) -> Result<NaiveDate, ParseError> {
+pub fn parse_date(src: &str) -> Result<NaiveDate, ParseError> {
     let s = src.replace("-", "");
     NaiveDate::parse_from_str(&s, "%Y%m%d")
 }
 
-#[derive(Debug, Clone, Copy)]
+impl Opt {
+    /// Whether any stats API response is loaded from a local file
+    pub fn uses_local_files(&self) -> bool {
+        self.schedule_file.is_some() || self.teams_file.is_some() || self.content_dir.is_some()
+    }
+
+    /// Dates to load schedules for, `None` being today
+    pub fn schedule_dates(&self) -> Vec<Option<NaiveDate>> {
+        if let Some(date_range) = self.date_range {
+            return date_range.dates().map(Some).collect();
+        }
+
+        match self.days {
+            Some(days) if days > 1 => {
+                let from = self
+                    .date
+                    .unwrap_or_else(|| Local::now().naive_local().date());
+
+                (0..days)
+                    .map(|day| Some(from + Duration::days(day as i64)))
+                    .collect()
+            }
+            _ => vec![self.date],
+        }
+    }
+}
+
+/// `FROM..TO`, both days included
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DateRange {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+}
+
+impl DateRange {
+    pub fn dates(&self) -> impl Iterator<Item = NaiveDate> {
+        let from = self.from;
+        (0..=(self.to - from).num_days()).map(move |day| from + Duration::days(day))
+    }
+}
+
+impl FromStr for DateRange {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<DateRange, Error> {
+        let idx = match s.find("..") {
+            Some(idx) => idx,
+            None => bail!("Date range must be supplied as FROM..TO, e.g. '20210401..20210407'"),
+        };
+
+        let parse = |date| parse_date(date).map_err(|_| format_err!("Invalid date {}", date));
+        let (from, to) = (parse(&s[..idx])?, parse(&s[idx + 2..])?);
+
+        if to < from {
+            bail!("Date range ends before it starts");
+        }
+
+        Ok(DateRange { from, to })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Cdn {
     Akc,
     L3c,
+    /// Whichever of the others is faster
+    Auto,
+}
+
+impl Cdn {
+    /// Every CDN streams are served over
+    pub const ALL: [Cdn; 2] = [Cdn::Akc, Cdn::L3c];
+
+    /// The CDN to switch to when this one fails
+    pub fn other(self) -> Cdn {
+        match self {
+            Cdn::Akc => Cdn::L3c,
+            Cdn::L3c => Cdn::Akc,
+            Cdn::Auto => Cdn::Auto,
+        }
+    }
 }
 
 impl From<Cdn> for &str {
@@ -349,6 +791,7 @@ impl From<Cdn> for &str {
         match cdn {
             Cdn::Akc => "akc",
             Cdn::L3c => "l3c",
+            Cdn::Auto => "auto",
         }
     }
 }
@@ -360,7 +803,8 @@ impl FromStr for Cdn {
         match s {
             "akc" => Ok(Cdn::Akc),
             "l3c" => Ok(Cdn::L3c),
-            _ => bail!("Option must match 'akc' or 'l3c'"),
+            "auto" => Ok(Cdn::Auto),
+            _ => bail!("Option must match 'akc', 'l3c' or 'auto'"),
         }
     }
 }
@@ -372,84 +816,127 @@ impl std::fmt::Display for Cdn {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
-pub enum Quality {
-    _216p,
-    _224p,
-    _288p,
-    _360p,
-    _504p,
-    _540p,
-    _720p,
-    _720p60,
-}
-
-impl Quality {
-    pub const ALL: [Quality; 8] = [
-        Quality::_720p60,
-        Quality::_720p,
-        Quality::_540p,
-        Quality::_504p,
-        Quality::_360p,
-        Quality::_288p,
-        Quality::_224p,
-        Quality::_216p,
-    ];
-}
-
-impl From<Quality> for &str {
-    fn from(quality: Quality) -> &'static str {
-        match quality {
-            Quality::_720p60 => "72060",
-            Quality::_720p => "720",
-            Quality::_540p => "540",
-            Quality::_504p => "504",
-            Quality::_360p => "360",
-            Quality::_288p => "288",
-            Quality::_224p => "224",
-            Quality::_216p => "216",
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RecordBackend {
+    Native,
+    Streamlink,
+}
+
+impl From<RecordBackend> for &str {
+    fn from(backend: RecordBackend) -> &'static str {
+        match backend {
+            RecordBackend::Native => "native",
+            RecordBackend::Streamlink => "streamlink",
         }
     }
 }
 
-impl FromStr for Quality {
+impl FromStr for RecordBackend {
     type Err = Error;
 
-    fn from_str(s: &str) -> Result<Quality, Error> {
+    fn from_str(s: &str) -> Result<RecordBackend, Error> {
         match s {
-            "720p60" => Ok(Quality::_720p60),
-            "720p" => Ok(Quality::_720p),
-            "540p" => Ok(Quality::_540p),
-            "504p" => Ok(Quality::_504p),
-            "360p" => Ok(Quality::_360p),
-            "288p" => Ok(Quality::_288p),
-            "224p" => Ok(Quality::_224p),
-            "216p" => Ok(Quality::_216p),
-            _ => bail!(
-                "Must be one of: '720p60', '720p', '540p', '504p', '360p', '288p', '224p', '216p'"
-            ),
+            "native" => Ok(RecordBackend::Native),
+            "streamlink" => Ok(RecordBackend::Streamlink),
+            _ => bail!("Option must match 'native' or 'streamlink'"),
         }
     }
 }
 
-impl std::fmt::Display for Quality {
+impl std::fmt::Display for RecordBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let s: &str = (*self).into();
+        write!(f, "{}", s)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WatchFormat {
+    Human,
+    Ndjson,
+}
+
+impl From<WatchFormat> for &str {
+    fn from(format: WatchFormat) -> &'static str {
+        match format {
+            WatchFormat::Human => "human",
+            WatchFormat::Ndjson => "ndjson",
+        }
+    }
+}
+
+impl FromStr for WatchFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<WatchFormat, Error> {
+        match s {
+            "human" => Ok(WatchFormat::Human),
+            "ndjson" => Ok(WatchFormat::Ndjson),
+            _ => bail!("Option must match 'human' or 'ndjson'"),
+        }
+    }
+}
+
+impl std::fmt::Display for WatchFormat {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let s: &str = (*self).into();
         write!(f, "{}", s)
     }
 }
 
-impl Quality {
-    pub fn to_streamlink_quality<'a>(self) -> &'a str {
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Quality {
+    /// Variant with the highest bandwidth
+    Best,
+    /// Variant with the lowest bandwidth
+    Worst,
+    /// `<height>p[fps]`, e.g. `720p` or `720p60`. Frame rate defaults to 30
+    Resolution {
+        height: u32,
+        frame_rate: Option<u32>,
+    },
+    /// `max-bandwidth=<kbps>`
+    MaxBandwidth(u32),
+}
+
+/// `<height>p[fps]` quality
+static RESOLUTION: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(\d+)p(\d+)?$").unwrap());
+
+impl FromStr for Quality {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Quality, Error> {
+        if s == "best" {
+            Ok(Quality::Best)
+        } else if s == "worst" {
+            Ok(Quality::Worst)
+        } else if let Some(kbps) = s.strip_prefix("max-bandwidth=") {
+            match kbps.parse() {
+                Ok(kbps) => Ok(Quality::MaxBandwidth(kbps)),
+                Err(_) => bail!("Bandwidth must be supplied in kbps, e.g. 'max-bandwidth=3000'"),
+            }
+        } else if let Some(captures) = RESOLUTION.captures(s) {
+            Ok(Quality::Resolution {
+                height: captures[1].parse()?,
+                frame_rate: captures.get(2).map(|m| m.as_str().parse()).transpose()?,
+            })
+        } else {
+            bail!("Must be one of: 'best', 'worst', '<height>p[fps]' or 'max-bandwidth=<kbps>'");
+        }
+    }
+}
+
+impl std::fmt::Display for Quality {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Quality::_720p60 => "720p_alt",
-            Quality::_720p => "720p",
-            Quality::_540p => "540p",
-            Quality::_504p => "504p",
-            Quality::_360p => "360p",
-            Quality::_288p => "288p",
-            Quality::_224p => "224p",
-            Quality::_216p => "216p",
+            Quality::Best => write!(f, "best"),
+            Quality::Worst => write!(f, "worst"),
+            Quality::Resolution {
+                height,
+                frame_rate: Some(frame_rate),
+            } => write!(f, "{}p{}", height, frame_rate),
+            Quality::Resolution { height, .. } => write!(f, "{}p", height),
+            Quality::MaxBandwidth(kbps) => write!(f, "max-bandwidth={}", kbps),
         }
     }
 }
@@ -497,7 +984,7 @@ impl std::fmt::Display for FeedType {
     }
 }
 
-fn parse_offset(s: &str) -> Result<String, Error> {
+pub fn parse_offset(s: &str) -> Result<String, Error> {
     let re = regex::Regex::new(r"^(\d{2}:)?\d{2}:\d{2}$").unwrap();
     if re.is_match(s) {
         return Ok(s.to_owned());
@@ -505,12 +992,17 @@ fn parse_offset(s: &str) -> Result<String, Error> {
     bail!("Offset must be supplied as [HH:]MM:SS");
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum Sport {
     Mlb,
     Nhl,
 }
 
+impl Sport {
+    /// Every sport, in the order `--sport all` uses
+    pub const ALL: [Sport; 2] = [Sport::Nhl, Sport::Mlb];
+}
+
 impl From<Sport> for &str {
     fn from(sport: Sport) -> &'static str {
         match sport {
@@ -538,3 +1030,191 @@ impl std::fmt::Display for Sport {
         write!(f, "{}", s)
     }
 }
+
+/// Sports to get streams for, in the order they were given
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sports(Vec<Sport>);
+
+impl Sports {
+    pub fn iter(&self) -> impl Iterator<Item = Sport> + '_ {
+        self.0.iter().copied()
+    }
+
+    pub fn as_slice(&self) -> &[Sport] {
+        &self.0
+    }
+}
+
+impl From<Sport> for Sports {
+    fn from(sport: Sport) -> Sports {
+        Sports(vec![sport])
+    }
+}
+
+impl FromStr for Sports {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Sports, Error> {
+        if s == "all" {
+            return Ok(Sports(Sport::ALL.to_vec()));
+        }
+
+        let mut sports = vec![];
+        for sport in s.split(',').map(str::trim) {
+            let sport = Sport::from_str(sport).map_err(|_| {
+                format_err!("Option must match 'mlb', 'nhl', a list like 'nhl,mlb' or 'all'")
+            })?;
+
+            if !sports.contains(&sport) {
+                sports.push(sport);
+            }
+        }
+
+        Ok(Sports(sports))
+    }
+}
+
+impl std::fmt::Display for Sports {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let sports: Vec<_> = self
+            .iter()
+            .map(|sport| sport.to_string().to_uppercase())
+            .collect();
+        write!(f, "{}", sports.join(", "))
+    }
+}
+
+/// Stream hosts given separated by commas, in order of preference
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hosts {
+    hosts: Vec<String>,
+    /// Scheme of the links the hosts answer with, from `--link-scheme`
+    pub link_scheme: String,
+}
+
+impl Hosts {
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.hosts.iter().map(String::as_str)
+    }
+
+    pub fn first(&self) -> &str {
+        &self.hosts[0]
+    }
+}
+
+impl FromStr for Hosts {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Hosts, Error> {
+        let mut hosts = vec![];
+        for host in s.split(',').map(str::trim).filter(|host| !host.is_empty()) {
+            let host = host.trim_end_matches('/').to_owned();
+            if !hosts.contains(&host) {
+                hosts.push(host);
+            }
+        }
+
+        if hosts.is_empty() {
+            bail!("At least one host must be given");
+        }
+
+        Ok(Hosts {
+            hosts,
+            link_scheme: "https".to_owned(),
+        })
+    }
+}
+
+impl std::fmt::Display for Hosts {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.hosts.join(","))
+    }
+}
+
+/// Teams given separated by commas, e.g. `VGK,BOS`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Teams(Vec<String>);
+
+impl Teams {
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(String::as_str)
+    }
+}
+
+impl FromStr for Teams {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Teams, Error> {
+        let teams: Vec<_> = s
+            .split(',')
+            .map(str::trim)
+            .filter(|team| !team.is_empty())
+            .map(str::to_owned)
+            .collect();
+
+        if teams.is_empty() {
+            bail!("At least one team must be given");
+        }
+
+        Ok(Teams(teams))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_sports() {
+        assert_eq!(Sports::from_str("mlb").unwrap().as_slice(), &[Sport::Mlb]);
+        assert_eq!(
+            Sports::from_str("mlb, nhl,mlb").unwrap().as_slice(),
+            &[Sport::Mlb, Sport::Nhl]
+        );
+        assert_eq!(Sports::from_str("all").unwrap().as_slice(), &Sport::ALL);
+        assert!(Sports::from_str("nba").is_err());
+        assert!(Sports::from_str("").is_err());
+    }
+
+    #[test]
+    fn parse_teams() {
+        let teams = Teams::from_str("VGK, bos,,Golden Knights").unwrap();
+        assert_eq!(
+            teams.iter().collect::<Vec<_>>(),
+            vec!["VGK", "bos", "Golden Knights"]
+        );
+        assert!(Teams::from_str(" , ").is_err());
+    }
+
+    #[test]
+    fn parse_hosts() {
+        let hosts = Hosts::from_str("http://a.net/, https://b.net,,http://a.net").unwrap();
+        assert_eq!(
+            hosts.iter().collect::<Vec<_>>(),
+            vec!["http://a.net", "https://b.net"]
+        );
+        assert_eq!(hosts.first(), "http://a.net");
+        assert_eq!(hosts.to_string(), "http://a.net,https://b.net");
+        assert!(Hosts::from_str(",").is_err());
+    }
+
+    #[test]
+    fn parse_date_range() {
+        let date = |day| NaiveDate::from_ymd_opt(2021, 4, day).unwrap();
+
+        let range = DateRange::from_str("20210401..2021-04-03").unwrap();
+        assert_eq!(
+            range.dates().collect::<Vec<_>>(),
+            vec![date(1), date(2), date(3)]
+        );
+        assert_eq!(
+            DateRange::from_str("20210401..20210401")
+                .unwrap()
+                .dates()
+                .count(),
+            1
+        );
+        assert!(DateRange::from_str("20210403..20210401").is_err());
+        assert!(DateRange::from_str("20210401").is_err());
+    }
+}
@@ -0,0 +1,706 @@
+use crate::{
+    hls::{MasterPlaylist, MediaPlaylist, Segment},
+    log_error,
+    opt::{Cdn, Quality},
+    stream::SHARED_CLIENT,
+};
+use aes::Aes128;
+use async_std::{fs::File, task};
+use block_modes::{block_padding::Pkcs7, BlockMode, Cbc};
+use failure::{bail, format_err, Error, ResultExt};
+use futures::{stream, AsyncReadExt, AsyncWriteExt, StreamExt};
+use isahc::{
+    config::{Configurable, SslOption},
+    http::Uri,
+    AsyncBody, Request,
+};
+use std::{collections::HashMap, io::Write, path::PathBuf, time::Duration};
+
+type Aes128Cbc = Cbc<Aes128, Pkcs7>;
+
+const USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 \
+                          (KHTML, like Gecko Chrome/59.0.3071.115 Safari/537.36";
+
+/// Segments downloaded at once, same as `--hls-segment-threads` passed to Streamlink
+const SEGMENT_THREADS: usize = 4;
+
+/// Number of segments from the end of a live playlist to start recording at,
+/// same as Streamlink's default `--hls-live-edge`
+const LIVE_EDGE: usize = 3;
+
+/// Attempts for each playlist reload, key & segment download before giving up
+const RETRIES: usize = 3;
+
+/// Wait before retrying a key or segment download, doubled on each attempt
+const RETRY_DELAY: Duration = Duration::from_millis(500);
+
+/// Size of an MPEG-TS packet
+const TS_PACKET: usize = 188;
+
+pub struct RecorderArgs {
+    pub link: String,
+    /// CDN to switch to if the playlist of `link` can't be reloaded, and the
+    /// link on it
+    pub fallback: Option<(Cdn, String)>,
+    pub output: PathBuf,
+    pub restart: bool,
+    pub proxy: Option<Uri>,
+    pub offset: Option<String>,
+    pub audio_source: Option<String>,
+}
+
+/// Record the HLS stream at `link` to a single MPEG-TS file, without Streamlink.
+///
+/// `link` can be either a master playlist, where the best variant is recorded,
+/// or the media playlist of a single variant.
+pub async fn record(args: RecorderArgs) -> Result<(), Error> {
+    let mut recorder = Recorder {
+        proxy: args.proxy,
+        keys: HashMap::new(),
+    };
+
+    let mut media_link = recorder
+        .media_link(&args.link, args.audio_source.as_deref())
+        .await?;
+    let mut fallback = args.fallback;
+    let offset = args.offset.as_deref().map(parse_offset).transpose()?;
+
+    let mut file = File::create(&args.output)
+        .await
+        .context(format_err!("Failed to create {}", args.output.display()))?;
+    println!("Writing output to {}\n", args.output.display());
+
+    let mut last_sequence: Option<u64> = None;
+    // File name of the last segment, to find it on the other CDN after switching
+    let mut last_name: Option<String> = None;
+    let mut switched = false;
+    let mut written_segments = 0;
+    let mut written_bytes = 0;
+    let mut failed_reloads = 0;
+    let mut failed_keys = 0;
+
+    loop {
+        let playlist = match recorder.media_playlist(&media_link).await {
+            Ok(playlist) => {
+                failed_reloads = 0;
+                playlist
+            }
+            Err(e) if failed_reloads < RETRIES => {
+                failed_reloads += 1;
+                log_error(e.as_fail());
+                task::sleep(Duration::from_secs(2)).await;
+                continue;
+            }
+            Err(e) => match fallback.take() {
+                Some((cdn, link)) => {
+                    log_error(e.as_fail());
+                    println!("\nSwitching to CDN {}", cdn);
+                    media_link = recorder
+                        .media_link(&link, args.audio_source.as_deref())
+                        .await?;
+                    failed_reloads = 0;
+                    switched = true;
+                    continue;
+                }
+                None => return Err(e),
+            },
+        };
+
+        // The other CDN serves the same segments, but may number them
+        // differently, so recording picks up after the last one written
+        if switched && last_sequence.is_some() {
+            let start = resume_index(&playlist, last_name.as_deref());
+            last_sequence = Some(match playlist.segments.get(start) {
+                Some(segment) => segment.sequence.saturating_sub(1),
+                None => playlist_end(&playlist),
+            });
+        }
+        switched = false;
+
+        let mut segments = match last_sequence {
+            None => {
+                let start = start_index(&playlist, args.restart, offset);
+                playlist.segments[start..].to_vec()
+            }
+            // Sequence numbers went backwards, stream was restarted upstream
+            Some(last) if playlist_end(&playlist) < last => {
+                println!("\nStream sequence was reset, continuing from the new sequence");
+                playlist.segments.clone()
+            }
+            Some(last) => playlist
+                .segments
+                .iter()
+                .filter(|segment| segment.sequence > last)
+                .cloned()
+                .collect(),
+        };
+
+        // Segments from the first one with a key that failed to load are left
+        // for the next reload to try the key again. Once out of attempts
+        // they're downloaded anyway, and each is skipped as undecryptable.
+        let mut hold_from = None;
+        let mut failed_uris = vec![];
+        for (idx, segment) in segments.iter().enumerate() {
+            let uri = match &segment.key {
+                Some(key) if !failed_uris.contains(&key.uri) => key.uri.clone(),
+                _ => continue,
+            };
+            if let Err(e) = recorder.load_key(&uri).await {
+                log_error(e.as_fail());
+                if failed_keys < RETRIES {
+                    hold_from = Some(idx);
+                    break;
+                }
+                failed_uris.push(uri);
+            }
+        }
+        let held_back = hold_from.is_some();
+        match hold_from {
+            Some(idx) => {
+                failed_keys += 1;
+                segments.truncate(idx);
+            }
+            None => failed_keys = 0,
+        }
+
+        let has_new_segments = !segments.is_empty();
+        let recorder_ref = &recorder;
+        let mut downloads = stream::iter(segments)
+            .map(|segment| async move {
+                let (sequence, discontinuity) = (segment.sequence, segment.discontinuity);
+                let name = segment_name(&segment.uri).to_owned();
+                (
+                    sequence,
+                    discontinuity,
+                    name,
+                    recorder_ref.segment(segment).await,
+                )
+            })
+            .buffered(SEGMENT_THREADS);
+
+        while let Some((sequence, discontinuity, name, data)) = downloads.next().await {
+            last_sequence = Some(sequence);
+            last_name = Some(name);
+
+            match data {
+                Ok(data) => {
+                    if discontinuity {
+                        println!("\nDiscontinuity at segment {}", sequence);
+                    }
+                    file.write_all(&data).await?;
+                    written_segments += 1;
+                    written_bytes += data.len();
+
+                    print!(
+                        "\rRecorded {} segments, {:.1} MB",
+                        written_segments,
+                        written_bytes as f64 / 1_000_000.0
+                    );
+                    let _ = std::io::stdout().flush();
+                }
+                Err(e) => log_error(&e.context(format!("Skipping segment {}", sequence))),
+            }
+        }
+
+        if playlist.end_list && !held_back {
+            break;
+        }
+
+        // Reload at the target duration, or sooner if the playlist hasn't changed
+        let reload = if has_new_segments {
+            playlist.target_duration.max(1) * 1000
+        } else {
+            playlist.target_duration.max(1) * 500
+        };
+        task::sleep(Duration::from_millis(reload)).await;
+    }
+
+    file.flush().await?;
+    println!();
+
+    Ok(())
+}
+
+struct Recorder {
+    proxy: Option<Uri>,
+    keys: HashMap<String, Vec<u8>>,
+}
+
+impl Recorder {
+    /// Media playlist to record. The best variant of a master playlist is chosen.
+    async fn media_link(&self, link: &str, audio_source: Option<&str>) -> Result<String, Error> {
+        let text = String::from_utf8(self.get(link).await?)?;
+
+        let master_playlist = match MasterPlaylist::parse(link, &text) {
+            Ok(master_playlist) => master_playlist,
+            // Already a media playlist, e.g. link for a specific quality
+            Err(_) => return Ok(link.to_owned()),
+        };
+
+        // '*' takes whatever tracks the variant carries, it doesn't ask for one
+        if let Some(audio_source) = audio_source.filter(|source| *source != "*") {
+            let separate_track = master_playlist
+                .audio
+                .iter()
+                .find(|rendition| rendition.matches(audio_source) && !rendition.default)
+                .and_then(|rendition| rendition.uri.as_ref());
+
+            if separate_track.is_some() {
+                bail!(
+                    "Audio source {} is a separate audio track, which the native recorder \
+                     can't mux. Use '--backend streamlink' instead",
+                    audio_source
+                );
+            }
+        }
+
+        master_playlist
+            .variant(Quality::Best)
+            .map(|variant| variant.uri.clone())
+            .ok_or_else(|| format_err!("Master playlist doesn't contain any variant streams"))
+    }
+
+    async fn media_playlist(&self, link: &str) -> Result<MediaPlaylist, Error> {
+        let text = String::from_utf8(self.get(link).await?)?;
+        let playlist =
+            MediaPlaylist::parse(link, &text).context("Failed to parse media playlist")?;
+
+        Ok(playlist)
+    }
+
+    async fn load_key(&mut self, uri: &str) -> Result<(), Error> {
+        if !self.keys.contains_key(uri) {
+            let key = self
+                .get_with_retries(uri)
+                .await
+                .context("Failed to download decryption key")?;
+
+            if key.len() != 16 {
+                bail!("Decryption key is {} bytes, expected 16", key.len());
+            }
+
+            self.keys.insert(uri.to_owned(), key);
+        }
+
+        Ok(())
+    }
+
+    /// Download & decrypt a segment. Keys must already be loaded with `load_key`.
+    async fn segment(&self, segment: Segment) -> Result<Vec<u8>, Error> {
+        let data = self.get_with_retries(&segment.uri).await?;
+
+        let mut data = match segment.key {
+            Some(key) => {
+                let key_data = self
+                    .keys
+                    .get(&key.uri)
+                    .ok_or_else(|| format_err!("Decryption key wasn't loaded"))?;
+
+                decrypt(&data, key_data, &key.iv(segment.sequence))?
+            }
+            None => data,
+        };
+
+        if segment.discontinuity {
+            mark_discontinuity(&mut data);
+        }
+
+        Ok(data)
+    }
+
+    async fn get_with_retries(&self, url: &str) -> Result<Vec<u8>, Error> {
+        let mut delay = RETRY_DELAY;
+        let mut attempt = 1;
+        loop {
+            match self.get(url).await {
+                Ok(data) => return Ok(data),
+                Err(_) if attempt < RETRIES => {
+                    task::sleep(delay).await;
+                    delay *= 2;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    async fn get(&self, url: &str) -> Result<Vec<u8>, Error> {
+        let uri = url.parse::<Uri>().context("Failed to build URI")?;
+        let request = Request::builder()
+            .method("GET")
+            .uri(uri)
+            .header("User-Agent", USER_AGENT)
+            .proxy(self.proxy.clone())
+            .ssl_options(SslOption::DANGER_ACCEPT_INVALID_CERTS)
+            .body(AsyncBody::empty())
+            .unwrap();
+
+        let resp = SHARED_CLIENT.send_async(request).await?;
+
+        if !resp.status().is_success() {
+            bail!("Request to {} failed with {}", url, resp.status());
+        }
+
+        let mut body = resp.into_body();
+        let mut data = vec![];
+        body.read_to_end(&mut data)
+            .await
+            .context("Failed to read response body")?;
+
+        Ok(data)
+    }
+}
+
+fn decrypt(data: &[u8], key: &[u8], iv: &[u8; 16]) -> Result<Vec<u8>, Error> {
+    let cipher = Aes128Cbc::new_from_slices(key, iv)
+        .map_err(|_| format_err!("Invalid decryption key length"))?;
+
+    cipher
+        .decrypt_vec(data)
+        .map_err(|_| format_err!("Failed to decrypt segment"))
+}
+
+/// Set the discontinuity indicator on the first packet of each PID that has an
+/// adaptation field, so players reset their continuity counter & clock state at
+/// a splice instead of treating it as packet loss
+fn mark_discontinuity(data: &mut [u8]) {
+    let mut marked = vec![];
+
+    for packet in data.chunks_exact_mut(TS_PACKET) {
+        if packet[0] != 0x47 {
+            continue;
+        }
+
+        let pid = u16::from(packet[1] & 0x1f) << 8 | u16::from(packet[2]);
+        let has_adaptation = packet[3] & 0x20 != 0 && packet[4] > 0;
+        if has_adaptation && !marked.contains(&pid) {
+            packet[5] |= 0x80;
+            marked.push(pid);
+        }
+    }
+}
+
+/// Highest sequence number in the playlist
+fn playlist_end(playlist: &MediaPlaylist) -> u64 {
+    playlist
+        .segments
+        .last()
+        .map_or(playlist.media_sequence, |segment| segment.sequence)
+}
+
+/// File name of a segment, without its directory & query
+fn segment_name(uri: &str) -> &str {
+    let path = uri.split('?').next().unwrap_or_default();
+    path.rsplit('/').next().unwrap_or_default()
+}
+
+/// Index of the segment after the one named `last_name` in the playlist of the
+/// CDN switched to, or the live edge if it isn't listed
+fn resume_index(playlist: &MediaPlaylist, last_name: Option<&str>) -> usize {
+    last_name
+        .and_then(|name| {
+            playlist
+                .segments
+                .iter()
+                .position(|segment| segment_name(&segment.uri) == name)
+        })
+        .map(|index| index + 1)
+        .unwrap_or_else(|| start_index(playlist, false, None))
+}
+
+/// Index of the first segment to record, following Streamlink's behaviour.
+///
+/// Finished streams & restarted live streams begin at the first segment, other
+/// live streams at the live edge. An offset skips that many seconds from the
+/// beginning, or for live streams rewinds that many seconds from the end.
+fn start_index(playlist: &MediaPlaylist, restart: bool, offset: Option<u64>) -> usize {
+    let segments = &playlist.segments;
+    let from_start = playlist.end_list || restart;
+
+    match offset {
+        Some(offset) if from_start => {
+            let mut elapsed = 0.0;
+            segments
+                .iter()
+                .position(|segment| {
+                    elapsed += segment.duration;
+                    elapsed > offset as f64
+                })
+                .unwrap_or_else(|| segments.len().saturating_sub(1))
+        }
+        Some(offset) => {
+            let mut remaining = 0.0;
+            segments
+                .iter()
+                .rposition(|segment| {
+                    remaining += segment.duration;
+                    remaining >= offset as f64
+                })
+                .unwrap_or(0)
+        }
+        None if from_start => 0,
+        None => segments.len().saturating_sub(LIVE_EDGE),
+    }
+}
+
+/// Seconds in a `[HH:]MM:SS` offset
+fn parse_offset(offset: &str) -> Result<u64, Error> {
+    offset.split(':').try_fold(0, |seconds, part| {
+        part.parse::<u64>()
+            .map(|part| seconds * 60 + part)
+            .map_err(|_| format_err!("Offset must be supplied as [HH:]MM:SS"))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::serve::response;
+    use std::{net::SocketAddr, thread};
+    use tiny_http::Server;
+
+    const ENCRYPTED_MEDIA: &str = include_str!("../tests/fixtures/hls/encrypted_media.m3u8");
+    const NHL_MASTER: &str = include_str!("../tests/fixtures/hls/nhl_master.m3u8");
+    const KEY: [u8; 16] = [7; 16];
+
+    fn playlist(end_list: bool) -> MediaPlaylist {
+        let mut playlist =
+            MediaPlaylist::parse("https://host.com/a.m3u8", ENCRYPTED_MEDIA).unwrap();
+        playlist.end_list = end_list;
+        playlist
+    }
+
+    #[test]
+    fn start_index_follows_streamlink() {
+        assert_eq!(start_index(&playlist(true), false, None), 0);
+        assert_eq!(start_index(&playlist(false), true, None), 0);
+        assert_eq!(start_index(&playlist(false), false, None), 2);
+
+        // 5.005 + 5.005 + 6.006 = 16.016 seconds in
+        assert_eq!(start_index(&playlist(true), false, Some(16)), 2);
+        assert_eq!(start_index(&playlist(true), false, Some(17)), 3);
+        assert_eq!(start_index(&playlist(true), false, Some(600)), 4);
+
+        // 2.002 + 6.006 = 8.008 seconds from the end
+        assert_eq!(start_index(&playlist(false), false, Some(8)), 3);
+        assert_eq!(start_index(&playlist(false), false, Some(9)), 2);
+        assert_eq!(start_index(&playlist(false), false, Some(600)), 0);
+    }
+
+    #[test]
+    fn offsets() {
+        assert_eq!(parse_offset("01:02:03").unwrap(), 3723);
+        assert_eq!(parse_offset("10:00").unwrap(), 600);
+        assert!(parse_offset("1h").is_err());
+    }
+
+    /// TS packet of `pid`, with an adaptation field unless it's `None`
+    fn packet(pid: u16, adaptation: Option<u8>) -> Vec<u8> {
+        let mut packet = vec![0xff; TS_PACKET];
+        packet[0] = 0x47;
+        packet[1] = (pid >> 8) as u8;
+        packet[2] = pid as u8;
+        match adaptation {
+            Some(flags) => {
+                packet[3] = 0x30;
+                packet[4] = 1;
+                packet[5] = flags;
+            }
+            None => packet[3] = 0x10,
+        }
+        packet
+    }
+
+    #[test]
+    fn marks_discontinuity() {
+        // Key rotation in the fixture is spliced in with a discontinuity
+        let playlist = playlist(true);
+        let spliced: Vec<bool> = playlist
+            .segments
+            .iter()
+            .map(|segment| {
+                let mut data = [packet(256, Some(0x10)), packet(257, None)].concat();
+                if segment.discontinuity {
+                    mark_discontinuity(&mut data);
+                }
+                data[5] & 0x80 != 0
+            })
+            .collect();
+        assert_eq!(spliced, [false, false, true, false, false]);
+
+        let mut data = [
+            packet(256, None),
+            packet(256, Some(0x10)),
+            packet(256, Some(0x10)),
+            packet(257, Some(0)),
+        ]
+        .concat();
+        mark_discontinuity(&mut data);
+
+        let flags: Vec<u8> = data.chunks(TS_PACKET).map(|packet| packet[5]).collect();
+        assert_eq!(flags, [0xff, 0x90, 0x10, 0x80]);
+    }
+
+    /// Stub of two CDNs serving the same segments, numbered differently. The
+    /// playlist on `/a` is only served to check it's a media playlist & to load
+    /// it once, after that the CDN is down. `/master.m3u8` is a master playlist
+    /// with a separate French audio track. `/k` is a stream of one encrypted
+    /// segment, whose key fails for one attempt at loading it.
+    fn stub_cdns() -> SocketAddr {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let addr = server.server_addr();
+
+        thread::spawn(move || {
+            let mut a_served = 0;
+            let mut key_requests = 0;
+            for request in server.incoming_requests() {
+                let url = request.url().to_owned();
+                let (status, body) = match url.as_str() {
+                    "/master.m3u8" => (200, NHL_MASTER.as_bytes().to_vec()),
+                    "/k/media.m3u8" => {
+                        let playlist = "#EXTM3U\n#EXT-X-TARGETDURATION:1\n\
+                                        #EXT-X-MEDIA-SEQUENCE:1\n\
+                                        #EXT-X-KEY:METHOD=AES-128,URI=\"key\"\n\
+                                        #EXTINF:1,\nseg1.ts\n#EXT-X-ENDLIST\n";
+                        (200, playlist.as_bytes().to_vec())
+                    }
+                    "/k/key" => {
+                        key_requests += 1;
+                        if key_requests <= RETRIES {
+                            (503, vec![])
+                        } else {
+                            (200, KEY.to_vec())
+                        }
+                    }
+                    "/k/seg1.ts" => {
+                        let iv = u128::from(1u64).to_be_bytes();
+                        let data = Aes128Cbc::new_from_slices(&KEY, &iv)
+                            .unwrap()
+                            .encrypt_vec(&[vec![0x47], vec![1; TS_PACKET - 1]].concat());
+                        (200, data)
+                    }
+                    "/a/media.m3u8" if a_served < 2 => {
+                        a_served += 1;
+                        let playlist = "#EXTM3U\n#EXT-X-TARGETDURATION:1\n\
+                                        #EXT-X-MEDIA-SEQUENCE:100\n#EXTINF:1,\nseg1.ts\n\
+                                        #EXTINF:1,\nseg2.ts\n#EXTINF:1,\nseg3.ts\n";
+                        (200, playlist.as_bytes().to_vec())
+                    }
+                    "/b/media.m3u8" => {
+                        let playlist = "#EXTM3U\n#EXT-X-TARGETDURATION:1\n\
+                                        #EXT-X-MEDIA-SEQUENCE:7\n#EXTINF:1,\nseg2.ts\n\
+                                        #EXTINF:1,\nseg3.ts\n#EXTINF:1,\nseg4.ts\n\
+                                        #EXTINF:1,\nseg5.ts\n#EXT-X-ENDLIST\n";
+                        (200, playlist.as_bytes().to_vec())
+                    }
+                    // Segment N is a single packet of N
+                    url if url.ends_with(".ts") => {
+                        let n = url[url.len() - 4..url.len() - 3].parse::<u8>().unwrap();
+                        (200, [vec![0x47], vec![n; TS_PACKET - 1]].concat())
+                    }
+                    _ => (404, vec![]),
+                };
+
+                let _ = response::respond(request, status, &[], &body);
+            }
+        });
+
+        addr
+    }
+
+    #[test]
+    fn switches_cdn_after_last_segment_written() {
+        let addr = stub_cdns();
+        let output =
+            std::env::temp_dir().join(format!("lazystream-switch-{}.ts", std::process::id()));
+
+        task::block_on(record(RecorderArgs {
+            link: format!("http://{}/a/media.m3u8", addr),
+            fallback: Some((Cdn::L3c, format!("http://{}/b/media.m3u8", addr))),
+            output: output.clone(),
+            restart: false,
+            proxy: None,
+            offset: None,
+            audio_source: None,
+        }))
+        .unwrap();
+
+        let recorded: Vec<u8> = std::fs::read(&output)
+            .unwrap()
+            .chunks(TS_PACKET)
+            .map(|packet| packet[1])
+            .collect();
+        assert_eq!(recorded, [1, 2, 3, 4, 5]);
+
+        std::fs::remove_file(output).unwrap();
+    }
+
+    #[test]
+    fn retries_segment_whose_key_failed() {
+        let addr = stub_cdns();
+        let output = std::env::temp_dir().join(format!("lazystream-key-{}.ts", std::process::id()));
+
+        task::block_on(record(RecorderArgs {
+            link: format!("http://{}/k/media.m3u8", addr),
+            fallback: None,
+            output: output.clone(),
+            restart: false,
+            proxy: None,
+            offset: None,
+            audio_source: None,
+        }))
+        .unwrap();
+
+        let recorded = std::fs::read(&output).unwrap();
+        assert_eq!(recorded, [vec![0x47], vec![1; TS_PACKET - 1]].concat());
+
+        std::fs::remove_file(output).unwrap();
+    }
+
+    #[test]
+    fn separate_audio_track() {
+        let addr = stub_cdns();
+        let recorder = Recorder {
+            proxy: None,
+            keys: HashMap::new(),
+        };
+        let link = format!("http://{}/master.m3u8", addr);
+        let media_link = |source| task::block_on(recorder.media_link(&link, Some(source)));
+
+        assert!(media_link("*").is_ok());
+        assert!(media_link("English").is_ok());
+        assert!(media_link("fr")
+            .unwrap_err()
+            .to_string()
+            .starts_with("Audio source fr is a separate audio track"));
+    }
+
+    #[test]
+    fn resumes_after_segment_of_same_name() {
+        let playlist = playlist(false);
+
+        assert_eq!(resume_index(&playlist, Some("6600K_1201.ts")), 2);
+        assert_eq!(resume_index(&playlist, Some("6600K_1204.ts")), 5);
+        // Not listed, so the live edge
+        assert_eq!(resume_index(&playlist, Some("6600K_1100.ts")), 2);
+        assert_eq!(
+            segment_name("https://host.com/00/6600K_1200.ts?token=1"),
+            "6600K_1200.ts"
+        );
+    }
+
+    #[test]
+    fn decrypt_segment() {
+        let key = [7u8; 16];
+        let iv = u128::from(1200u64).to_be_bytes();
+        let data = b"\x47 mpeg-ts packet data, not a multiple of sixteen".to_vec();
+
+        let encrypted = Aes128Cbc::new_from_slices(&key, &iv)
+            .unwrap()
+            .encrypt_vec(&data);
+
+        assert_ne!(encrypted, data);
+        assert_eq!(decrypt(&encrypted, &key, &iv).unwrap(), data);
+        assert!(decrypt(&encrypted, &key[..8], &iv).is_err());
+    }
+}
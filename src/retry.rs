@@ -0,0 +1,133 @@
+use crate::opt::Opt;
+use async_std::task;
+use chrono::{DateTime, Utc};
+use failure::{bail, Error};
+use std::{io::Write, time::Duration};
+
+/// When to check again for a stream that isn't available yet
+pub struct RetryPolicy {
+    disabled: bool,
+    /// Give up once this long has passed since the scheduled start of the game
+    give_up_after: chrono::Duration,
+}
+
+impl RetryPolicy {
+    pub fn new(opts: &Opt) -> Self {
+        RetryPolicy {
+            disabled: opts.disable_retry,
+            give_up_after: chrono::Duration::minutes(opts.give_up_after as i64),
+        }
+    }
+
+    /// Time to wait before checking again for the stream of a game starting at
+    /// `game_date`, or an error once the policy gives up
+    pub fn wait(&self, game_date: DateTime<Utc>, now: DateTime<Utc>) -> Result<Duration, Error> {
+        if self.disabled {
+            bail!("Stream not available yet");
+        }
+
+        if now >= game_date + self.give_up_after {
+            bail!(
+                "Stream still not available {} minutes after the game started, giving up",
+                self.give_up_after.num_minutes()
+            );
+        }
+
+        Ok(interval(game_date, now))
+    }
+}
+
+/// How long to wait between checks for a game starting at `game_date`. Checks
+/// are sparse hours before the game and tight around the start time.
+fn interval(game_date: DateTime<Utc>, now: DateTime<Utc>) -> Duration {
+    let until_start = game_date - now;
+
+    if until_start > chrono::Duration::hours(3) {
+        Duration::from_secs(30 * 60)
+    } else if until_start > chrono::Duration::hours(1) {
+        Duration::from_secs(10 * 60)
+    } else if until_start > chrono::Duration::minutes(15) {
+        Duration::from_secs(2 * 60)
+    } else if until_start > chrono::Duration::hours(-1) {
+        Duration::from_secs(20)
+    } else {
+        Duration::from_secs(60)
+    }
+}
+
+/// Sleep for `wait`, counting down the time left after `message`
+pub async fn countdown(message: &str, wait: Duration) {
+    let mut left = wait.as_secs();
+
+    while left > 0 {
+        print!(
+            "\r{}, checking again in {}  ",
+            message,
+            format_duration(left)
+        );
+        let _ = std::io::stdout().flush();
+
+        task::sleep(Duration::from_secs(1)).await;
+        left -= 1;
+    }
+
+    println!("\r{}, checking again now{}", message, " ".repeat(8));
+}
+
+pub fn format_duration(seconds: u64) -> String {
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn start() -> DateTime<Utc> {
+        "2021-04-01T23:00:00Z".parse().unwrap()
+    }
+
+    fn before(minutes: i64) -> DateTime<Utc> {
+        start() - chrono::Duration::minutes(minutes)
+    }
+
+    #[test]
+    fn sparse_before_tight_around_start() {
+        assert_eq!(interval(start(), before(300)).as_secs(), 30 * 60);
+        assert_eq!(interval(start(), before(120)).as_secs(), 10 * 60);
+        assert_eq!(interval(start(), before(30)).as_secs(), 2 * 60);
+        assert_eq!(interval(start(), before(10)).as_secs(), 20);
+        assert_eq!(interval(start(), before(-45)).as_secs(), 20);
+        assert_eq!(interval(start(), before(-90)).as_secs(), 60);
+    }
+
+    #[test]
+    fn gives_up_after_deadline() {
+        let policy = RetryPolicy {
+            disabled: false,
+            give_up_after: chrono::Duration::minutes(60),
+        };
+
+        assert!(policy.wait(start(), before(120)).is_ok());
+        assert!(policy.wait(start(), before(-59)).is_ok());
+        assert!(policy.wait(start(), before(-60)).is_err());
+
+        let disabled = RetryPolicy {
+            disabled: true,
+            ..policy
+        };
+        assert!(disabled.wait(start(), before(120)).is_err());
+    }
+
+    #[test]
+    fn countdown_format() {
+        assert_eq!(format_duration(59), "0:59");
+        assert_eq!(format_duration(30 * 60), "30:00");
+        assert_eq!(format_duration(3 * 3600 + 61), "3:01:01");
+    }
+}
@@ -0,0 +1,102 @@
+use crate::{
+    api::model::GameStatus,
+    log_error,
+    opt::Opt,
+    stream::{Game, LazyStream},
+};
+use async_std::{process, task};
+use chrono::Local;
+use failure::Error;
+
+pub fn run(opts: Opt) {
+    task::block_on(async {
+        if let Err(e) = process(&opts).await {
+            log_error(e.as_fail());
+            process::exit(1);
+        };
+    });
+}
+
+async fn process(opts: &Opt) -> Result<(), Error> {
+    let lazy_stream = LazyStream::new(opts).await?;
+    let games = lazy_stream.games();
+
+    if games.is_empty() {
+        println!(
+            "There are no games for {}",
+            lazy_stream.date().format("%Y-%m-%d")
+        );
+        return Ok(());
+    }
+
+    println!("Games for {}...\n", lazy_stream.date().format("%Y-%m-%d"));
+    for game in games.iter() {
+        println!("{}", describe(opts, game));
+    }
+
+    if !opts.spoilers {
+        println!("\nScores are hidden, use '--spoilers' to show them");
+    }
+
+    Ok(())
+}
+
+/// e.g. `7:00 PM - Vegas Golden Knights @ Boston Bruins - Live`, with the
+/// score & period or inning when `--spoilers` is used
+pub fn describe(opts: &Opt, game: &Game) -> String {
+    let time_format = if opts.schedule_dates().len() > 1 {
+        "%a %b %-d %-I:%M %p"
+    } else {
+        "%-I:%M %p"
+    };
+
+    // Tag games with their sport when several are listed together
+    let sport = if opts.sports.as_slice().len() > 1 {
+        format!("[{}] ", game.sport().to_string().to_uppercase())
+    } else {
+        String::new()
+    };
+
+    let summary = summary(game, opts.spoilers)
+        .map(|summary| format!(" - {}", summary))
+        .unwrap_or_default();
+
+    format!(
+        "{} - {}{} @ {}{}{}",
+        game.game_date.with_timezone(&Local).format(time_format),
+        sport,
+        game.away_team.name,
+        game.home_team.name,
+        game.game_number_label(),
+        summary
+    )
+}
+
+/// Status of a game that has started or was called off, e.g. `Final`. With
+/// `spoilers` the score (away first) and period or inning are added, e.g.
+/// `Live 2-3, 2nd 12:34`.
+pub fn summary(game: &Game, spoilers: bool) -> Option<String> {
+    let score = game
+        .away_score
+        .zip(game.home_score)
+        .filter(|_| spoilers)
+        .map(|(away, home)| format!(" {}-{}", away, home))
+        .unwrap_or_default();
+
+    match game.status {
+        GameStatus::Preview => None,
+        GameStatus::Live => {
+            let progress = game
+                .linescore
+                .as_ref()
+                .and_then(|linescore| linescore.progress())
+                .filter(|_| spoilers)
+                .map(|progress| format!(", {}", progress))
+                .unwrap_or_default();
+
+            Some(format!("{}{}{}", game.status, score, progress))
+        }
+        GameStatus::Final => Some(format!("{}{}", game.status, score)),
+        GameStatus::Postponed | GameStatus::Cancelled => Some(game.status.to_string()),
+    }
+}
@@ -5,7 +5,6 @@ use crate::{
     BANNER,
 };
 use async_std::{process, task};
-use chrono::Local;
 use failure::{bail, Error};
 use read_input::prelude::*;
 
@@ -25,10 +24,15 @@ pub fn run(opts: Opt) {
 pub async fn process(opts: &Opt, need_return: bool) -> Result<(Game, Stream), Error> {
     println!("{}", BANNER);
 
-    let resolve = if let Command::Select { resolve } = opts.command {
-        resolve
+    let (resolve, list_qualities) = if let Command::Select {
+        resolve,
+        list_qualities,
+        ..
+    } = opts.command
+    {
+        (resolve, list_qualities)
     } else {
-        false
+        (false, false)
     };
 
     let lazy_stream = LazyStream::new(opts).await?;
@@ -39,17 +43,7 @@ pub async fn process(opts: &Opt, need_return: bool) -> Result<(Game, Stream), Er
         lazy_stream.date().format("%Y-%m-%d")
     );
     for (idx, game) in games.iter().enumerate() {
-        println!(
-            "{}) {} - {} @ {}",
-            idx + 1,
-            game.game_date
-                .with_timezone(&Local)
-                .time()
-                .format("%-I:%M %p")
-                .to_string(),
-            game.away_team.name,
-            game.home_team.name
-        );
+        println!("{}) {}", idx + 1, crate::scores::describe(opts, game));
     }
 
     let game_count = games.len();
@@ -59,6 +53,10 @@ pub async fn process(opts: &Opt, need_return: bool) -> Result<(Game, Stream), Er
         .get();
     let mut game = games.remove(game_choice - 1);
 
+    if game.status.is_off() {
+        bail!("That game is {}", game.status.to_string().to_lowercase());
+    }
+
     let mut streams = game.streams().await?;
 
     if streams.is_empty() {
@@ -67,7 +65,7 @@ pub async fn process(opts: &Opt, need_return: bool) -> Result<(Game, Stream), Er
 
     println!("\nPick a stream...\n");
 
-    let feeds: Vec<FeedType> = streams.clone().into_iter().map(|(k, _)| k).collect();
+    let feeds: Vec<FeedType> = streams.keys().copied().collect();
 
     for (idx, feed_type) in feeds.iter().enumerate() {
         println!("{}) {}", idx + 1, feed_type);
@@ -78,15 +76,58 @@ pub async fn process(opts: &Opt, need_return: bool) -> Result<(Game, Stream), Er
         .msg("\n>>> ")
         .add_test(move |input| *input > 0 && *input <= feed_count)
         .get();
-    let feed_choice = &feeds[(feed_choice - 1)];
+    let feed_choice = &feeds[feed_choice - 1];
     let mut stream = streams.remove(feed_choice).unwrap();
 
-    let host_link = stream.host_link(lazy_stream.opts.cdn);
-
     let cdn = lazy_stream.opts.cdn;
+
+    // When returning, the caller waits for the stream to go live before
+    // resolving anything. Otherwise only the host link is printed unless the
+    // stream is resolved anyway, so the audio tracks are listed then.
     if !need_return {
+        let resolves = list_qualities || lazy_stream.opts.quality.is_some() || resolve;
+        if resolves {
+            if let Ok(audio) = stream.audio_renditions(cdn).await {
+                if !audio.is_empty() {
+                    println!("\nAudio tracks...\n");
+                    for rendition in audio.iter() {
+                        println!("{}", rendition);
+                    }
+                }
+            }
+        }
+
+        // Resolved along with the audio tracks, if the stream is live
+        if let Some(host) = stream.served_by() {
+            println!("\nServed by {}", host);
+        }
+        let host_link = stream.host_link(cdn);
+
         println!();
-        if let Some(quality) = lazy_stream.opts.quality {
+        if list_qualities {
+            let master_playlist = stream.master_playlist(cdn).await?;
+
+            println!("Available qualities...\n");
+            for variant in master_playlist.variants.iter() {
+                let resolution = variant
+                    .resolution
+                    .map(|resolution| format!("{}x{}", resolution.width, resolution.height))
+                    .unwrap_or_default();
+                let frame_rate = variant
+                    .frame_rate
+                    .map(|frame_rate| format!("{} fps", frame_rate))
+                    .unwrap_or_default();
+
+                println!(
+                    "{:<10} {:>6} kbps  {:<10} {:<10} {}",
+                    variant.name(),
+                    variant.bandwidth / 1000,
+                    resolution,
+                    frame_rate,
+                    variant.codecs.as_deref().unwrap_or_default(),
+                );
+            }
+        } else if let Some(quality) = lazy_stream.opts.quality {
             let quality_link = stream.quality_link(cdn, quality).await?;
             println!("{}", quality_link);
         } else if resolve {
@@ -0,0 +1,736 @@
+use crate::{
+    generate, hls, log_error,
+    opt::{parse_date, Cdn, Command, FeedType, Opt, Quality, Sport},
+    stream::{self, LazyStream, Stream},
+    BANNER,
+};
+use async_std::{process, sync::Mutex, task};
+use chrono::{Local, NaiveDate};
+use failure::{bail, format_err, Error};
+use percent_encoding::percent_decode_str;
+use std::{
+    net::{Ipv4Addr, SocketAddr, UdpSocket},
+    str::FromStr,
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+use tiny_http::{Request, Server};
+
+mod hdhomerun;
+pub mod response;
+
+/// Requests handled at the same time, each can wait on the stream hosts
+const WORKERS: usize = 8;
+
+pub fn run(opts: Opt) {
+    if let Err(e) = process(opts) {
+        log_error(e.as_fail());
+        process::exit(1);
+    }
+}
+
+fn process(opts: Opt) -> Result<(), Error> {
+    let state = if let Command::Serve {
+        bind,
+        advertise_url,
+        proxy_playlist,
+        ttl,
+        hdhomerun,
+        start_channel,
+        channel_prefix,
+        exclude_feeds,
+        start_prepend,
+        trim,
+        ..
+    } = opts.command.clone()
+    {
+        State {
+            opts,
+            bind,
+            url: advertise_url
+                .map(|url| url.trim_end_matches('/').to_owned())
+                .unwrap_or_else(|| base_url(bind)),
+            proxy_playlist,
+            ttl: Duration::from_secs(ttl * 60),
+            hdhomerun,
+            start_channel,
+            channel_prefix,
+            exclude_feeds,
+            start_prepend,
+            trim,
+            guide: Mutex::new(None),
+        }
+    } else {
+        unreachable!()
+    };
+
+    let server = Server::http(state.bind)
+        .map_err(|e| format_err!("Failed to listen on {}: {}", state.bind, e))?;
+
+    println!("{}", BANNER);
+    println!(
+        "Serving streams at {}/stream/{{sport}}/{{game_pk}}/{{feed}}",
+        state.url
+    );
+    println!(
+        "Serving playlist & guide at {0}/playlist.m3u and {0}/epg.xml\n",
+        state.url
+    );
+
+    if state.hdhomerun {
+        println!("Emulating HDHomeRun tuner at {}/discover.json\n", state.url);
+
+        if let Err(e) = hdhomerun::announce(server.server_addr()) {
+            log_error(&e.context("Tuner won't be discovered automatically, add it by address"));
+        }
+    }
+
+    // Each worker takes the next request once it's done with one
+    let (server, state) = (Arc::new(server), Arc::new(state));
+    let workers: Vec<_> = (0..WORKERS)
+        .map(|_| {
+            let (server, state) = (server.clone(), state.clone());
+            thread::spawn(move || {
+                for request in server.incoming_requests() {
+                    handle(&state, request);
+                }
+            })
+        })
+        .collect();
+    for worker in workers {
+        let _ = worker.join();
+    }
+
+    Ok(())
+}
+
+struct State {
+    opts: Opt,
+    bind: SocketAddr,
+    /// Url the server is reached at, for links in playlists & lineups
+    url: String,
+    proxy_playlist: bool,
+    ttl: Duration,
+    hdhomerun: bool,
+    start_channel: u32,
+    channel_prefix: String,
+    exclude_feeds: Vec<FeedType>,
+    start_prepend: u16,
+    trim: bool,
+    guide: Mutex<Option<Guide>>,
+}
+
+impl State {
+    /// Playlist & guide for the day, with entries pointing at the server.
+    /// Rebuilt once older than the TTL, or when the date changes.
+    async fn guide(&self) -> Result<Guide, Error> {
+        let mut cached = self.guide.lock().await;
+
+        if let Some(guide) = cached.as_ref() {
+            if !guide.is_stale(self.ttl, today()) {
+                return Ok(guide.clone());
+            }
+        }
+
+        let server = self.url.as_str();
+        let date = today();
+        let games = generate::games(&self.opts, Some(server)).await?;
+        let channels = generate::channels(
+            &games,
+            self.opts.sports.as_slice(),
+            self.start_channel,
+            &self.channel_prefix,
+            &self.exclude_feeds,
+        );
+        generate::warn_dropped_streams(&games, self.opts.sports.as_slice(), &self.exclude_feeds);
+
+        let playlist = generate::create_playlist(
+            games.clone(),
+            self.opts.sports.as_slice(),
+            self.opts.cdn,
+            self.opts.quality,
+            Some(server),
+            true,
+            self.start_channel,
+            Some(&self.channel_prefix),
+            &self.exclude_feeds,
+            self.trim,
+        )
+        .await;
+
+        let xmltv = generate::create_xmltv(
+            games,
+            self.start_channel,
+            self.opts.sports.as_slice(),
+            &self.channel_prefix,
+            &self.exclude_feeds,
+            self.start_prepend,
+            self.trim,
+        )
+        .await;
+
+        let guide = Guide {
+            built: Instant::now(),
+            date,
+            playlist,
+            xmltv,
+            channels,
+        };
+        *cached = Some(guide.clone());
+
+        Ok(guide)
+    }
+
+    /// Stream currently on the HDHomeRun channel `number`
+    async fn tune(&self, number: u32) -> Reply {
+        let guide = match self.guide().await {
+            Ok(guide) => guide,
+            Err(e) => return Reply::Error(e.to_string()),
+        };
+
+        let channel = match guide
+            .channels
+            .iter()
+            .find(|channel| channel.number == number)
+        {
+            Some(channel) => channel,
+            None => return Reply::NotFound(format!("Nothing is on channel {}", number)),
+        };
+
+        let route = StreamRoute {
+            sport: channel.sport,
+            game_pk: channel.game_pk,
+            feed_type: channel.feed_type,
+            date: Some(channel.selected_date),
+            quality: None,
+        };
+
+        stream_reply(&self.opts, route, self.proxy_playlist).await
+    }
+}
+
+#[derive(Clone)]
+struct Guide {
+    built: Instant,
+    date: NaiveDate,
+    playlist: String,
+    xmltv: String,
+    channels: Vec<generate::Channel>,
+}
+
+impl Guide {
+    fn is_stale(&self, ttl: Duration, today: NaiveDate) -> bool {
+        self.built.elapsed() >= ttl || self.date != today
+    }
+}
+
+/// Url of the server listening on `bind`, using the interface `peer` is
+/// reached on when listening on every interface
+fn base_url_for(bind: SocketAddr, peer: SocketAddr) -> Option<String> {
+    let ip = if bind.ip().is_unspecified() {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).ok()?;
+        socket.connect(peer).ok()?;
+        socket.local_addr().ok()?.ip()
+    } else {
+        bind.ip()
+    };
+
+    Some(format!("http://{}", SocketAddr::new(ip, bind.port())))
+}
+
+/// Url of the server listening on `bind`, as reached from the LAN
+fn base_url(bind: SocketAddr) -> String {
+    base_url_for(bind, hdhomerun::SSDP_GROUP).unwrap_or_else(|| format!("http://{}", bind))
+}
+
+fn today() -> NaiveDate {
+    Local::now().naive_local().date()
+}
+
+/// Url of a stream served by `lazystream serve` running at `server`
+pub fn stream_url(
+    server: &str,
+    sport: Sport,
+    game_pk: u64,
+    date: NaiveDate,
+    feed_type: FeedType,
+) -> String {
+    format!(
+        "{}/stream/{}/{}/{}?date={}",
+        server.trim_end_matches('/'),
+        sport.to_string().to_lowercase(),
+        game_pk,
+        feed_type,
+        date.format("%Y-%m-%d"),
+    )
+}
+
+fn handle(state: &State, request: Request) {
+    let path = request.url().split('?').next().unwrap_or_default();
+    let server = state.url.as_str();
+
+    let json = |body| Reply::Ok {
+        body,
+        content_type: "application/json",
+    };
+
+    let reply = match path {
+        "/playlist.m3u" | "/epg.xml" => match task::block_on(state.guide()) {
+            Ok(guide) if path == "/playlist.m3u" => Reply::Ok {
+                body: guide.playlist,
+                content_type: "audio/x-mpegurl",
+            },
+            Ok(guide) => Reply::Ok {
+                body: guide.xmltv,
+                content_type: "application/xml",
+            },
+            Err(e) => Reply::Error(e.to_string()),
+        },
+        "/discover.json" if state.hdhomerun => json(hdhomerun::discover(server)),
+        "/lineup_status.json" if state.hdhomerun => json(hdhomerun::lineup_status()),
+        "/lineup.json" if state.hdhomerun => match task::block_on(state.guide()) {
+            Ok(guide) => json(hdhomerun::lineup(server, &guide.channels)),
+            Err(e) => Reply::Error(e.to_string()),
+        },
+        "/lineup.post" if state.hdhomerun => Reply::Ok {
+            body: String::new(),
+            content_type: "text/plain",
+        },
+        "/device.xml" if state.hdhomerun => Reply::Ok {
+            body: hdhomerun::device_xml(server),
+            content_type: "application/xml",
+        },
+        _ => match hdhomerun::tune_channel(path).filter(|_| state.hdhomerun) {
+            Some(number) => task::block_on(state.tune(number)),
+            None => match StreamRoute::parse(request.url()) {
+                Ok(route) => task::block_on(stream_reply(&state.opts, route, state.proxy_playlist)),
+                Err(e) => Reply::NotFound(e.to_string()),
+            },
+        },
+    };
+
+    println!("{} {} -> {}", request.method(), request.url(), reply);
+
+    let (status, headers, body) = reply.into_parts();
+    if let Err(e) = response::respond(request, status, &headers, body.as_bytes()) {
+        log_error(&failure::Error::from(e).context("Failed to send response"));
+    }
+}
+
+async fn stream_reply(opts: &Opt, route: StreamRoute, proxy_playlist: bool) -> Reply {
+    let mut opts = opts.clone();
+    opts.sports = route.sport.into();
+    if route.date.is_some() {
+        opts.date = route.date;
+        opts.days = None;
+        opts.date_range = None;
+    }
+
+    let lazy_stream = match LazyStream::new(&opts).await {
+        Ok(lazy_stream) => lazy_stream,
+        Err(e) => return Reply::Error(e.to_string()),
+    };
+
+    let game = lazy_stream
+        .games()
+        .into_iter()
+        .find(|game| game.game_pk == route.game_pk);
+    let mut game = match game {
+        Some(game) => game,
+        None => {
+            return Reply::NotFound(format!(
+                "Game {} isn't scheduled for {}",
+                route.game_pk,
+                lazy_stream.date().format("%Y-%m-%d")
+            ))
+        }
+    };
+
+    let stream = match game.streams().await {
+        Ok(mut streams) => streams.remove(&route.feed_type),
+        Err(e) => return Reply::Error(e.to_string()),
+    };
+    let mut stream = match stream {
+        Some(stream) => stream,
+        None => {
+            return Reply::NotFound(format!(
+                "Game {} doesn't have a {} feed",
+                route.game_pk, route.feed_type
+            ))
+        }
+    };
+
+    let quality = route.quality.or(opts.quality);
+    resolve(&mut stream, opts.cdn, quality, proxy_playlist).await
+}
+
+/// Resolve the current link of `stream`, falling back to the master link if
+/// no variant matches `quality`
+async fn resolve(
+    stream: &mut Stream,
+    cdn: Cdn,
+    quality: Option<Quality>,
+    proxy_playlist: bool,
+) -> Reply {
+    let quality_link = match quality {
+        Some(quality) => stream.quality_link(cdn, quality).await.ok(),
+        None => None,
+    };
+
+    let link = match quality_link {
+        Some(link) => link,
+        None => match stream.master_link(cdn).await {
+            Ok(link) => link,
+            Err(e) => return Reply::Unavailable(e.to_string()),
+        },
+    };
+    if let Some(host) = stream.served_by() {
+        println!("{} stream served by {}", stream.feed_type, host);
+    }
+
+    if !proxy_playlist {
+        return Reply::Redirect(link);
+    }
+
+    match stream::get_m3u8(&link).await {
+        Ok(playlist) => Reply::Ok {
+            body: hls::absolutize(&link, &playlist),
+            content_type: "application/vnd.apple.mpegurl",
+        },
+        Err(e) => Reply::Error(e.to_string()),
+    }
+}
+
+/// `/stream/{sport}/{game_pk}/{feed}[?date=YYYY-MM-DD&quality=QUALITY]`
+#[derive(Debug, PartialEq)]
+struct StreamRoute {
+    sport: Sport,
+    game_pk: u64,
+    feed_type: FeedType,
+    date: Option<NaiveDate>,
+    quality: Option<Quality>,
+}
+
+impl StreamRoute {
+    fn parse(url: &str) -> Result<StreamRoute, Error> {
+        let (path, query) = match url.find('?') {
+            Some(idx) => (&url[..idx], &url[idx + 1..]),
+            None => (url, ""),
+        };
+
+        let segments: Vec<_> = path.trim_matches('/').split('/').collect();
+        let (sport, game_pk, feed_type) = match segments[..] {
+            ["stream", sport, game_pk, feed_type] => (sport, game_pk, feed_type),
+            _ => bail!("Streams are served at /stream/{{sport}}/{{game_pk}}/{{feed}}"),
+        };
+
+        let mut route = StreamRoute {
+            sport: Sport::from_str(&sport.to_lowercase())?,
+            game_pk: game_pk
+                .parse()
+                .map_err(|_| format_err!("Invalid game_pk {}", game_pk))?,
+            feed_type: FeedType::from_str(&feed_type.to_uppercase())?,
+            date: None,
+            quality: None,
+        };
+
+        for (key, value) in query.split('&').filter_map(|pair| {
+            let idx = pair.find('=')?;
+            Some((&pair[..idx], &pair[idx + 1..]))
+        }) {
+            let value = percent_decode_str(value)
+                .decode_utf8()
+                .map_err(|_| format_err!("Invalid {} {}", key, value))?;
+            let value = value.as_ref();
+            match key {
+                "date" => {
+                    route.date =
+                        Some(parse_date(value).map_err(|_| format_err!("Invalid date {}", value))?)
+                }
+                "quality" => route.quality = Some(Quality::from_str(value)?),
+                _ => {}
+            }
+        }
+
+        Ok(route)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Reply {
+    Redirect(String),
+    Ok {
+        body: String,
+        content_type: &'static str,
+    },
+    NotFound(String),
+    /// Stream isn't live yet
+    Unavailable(String),
+    Error(String),
+}
+
+impl Reply {
+    fn status_code(&self) -> u16 {
+        match self {
+            Reply::Redirect(_) => 302,
+            Reply::Ok { .. } => 200,
+            Reply::NotFound(_) => 404,
+            Reply::Unavailable(_) => 503,
+            Reply::Error(_) => 502,
+        }
+    }
+
+    /// Status, headers & body of the response
+    fn into_parts(self) -> (u16, Vec<(&'static str, String)>, String) {
+        let status = self.status_code();
+        let (headers, body) = match self {
+            Reply::Redirect(link) => (vec![("Location", link)], String::new()),
+            Reply::Ok { body, content_type } => {
+                (vec![("Content-Type", content_type.to_owned())], body)
+            }
+            Reply::NotFound(message) | Reply::Unavailable(message) | Reply::Error(message) => {
+                (vec![], message)
+            }
+        };
+
+        (status, headers, body)
+    }
+}
+
+impl std::fmt::Display for Reply {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Reply::Redirect(link) => write!(f, "{} {}", self.status_code(), link),
+            Reply::Ok { content_type, .. } => write!(f, "{} {}", self.status_code(), content_type),
+            Reply::NotFound(message) | Reply::Unavailable(message) | Reply::Error(message) => {
+                write!(f, "{} {}", self.status_code(), message)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::opt::Hosts;
+    use chrono::Utc;
+    use tiny_http::Response;
+
+    const NHL_MASTER: &str = include_str!("../tests/fixtures/hls/nhl_master.m3u8");
+
+    /// Stub of the getM3U8.php host. Streams with id `live` are available,
+    /// anything else isn't live yet.
+    fn stub_host() -> SocketAddr {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let addr = server.server_addr();
+
+        thread::spawn(move || {
+            for request in server.incoming_requests() {
+                let body = if request.url().starts_with("/getM3U8.php") {
+                    if request.url().contains("id=live") {
+                        format!("http://{}/hls/master.m3u8", addr)
+                    } else {
+                        "Not available yet".to_owned()
+                    }
+                } else if request.url() == "/hls/master.m3u8" {
+                    NHL_MASTER.to_owned()
+                } else {
+                    String::new()
+                };
+
+                let _ = request.respond(Response::from_string(body));
+            }
+        });
+
+        addr
+    }
+
+    /// The stub host, which hands out plain http links
+    fn stub_hosts(addr: SocketAddr) -> Hosts {
+        let mut hosts: Hosts = format!("http://{}", addr).parse().unwrap();
+        hosts.link_scheme = "http".to_owned();
+        hosts
+    }
+
+    fn stream(addr: SocketAddr, id: &str) -> Stream {
+        Stream::new(
+            id.to_owned(),
+            stub_hosts(addr),
+            "nhl",
+            FeedType::Home,
+            Utc::now().naive_utc().date(),
+        )
+    }
+
+    #[test]
+    fn parse_routes() {
+        assert_eq!(
+            StreamRoute::parse("/stream/nhl/2019020700/HOME").unwrap(),
+            StreamRoute {
+                sport: Sport::Nhl,
+                game_pk: 2019020700,
+                feed_type: FeedType::Home,
+                date: None,
+                quality: None,
+            }
+        );
+        assert_eq!(
+            StreamRoute::parse("/stream/mlb/631377/away?date=2021-04-01&quality=720p60").unwrap(),
+            StreamRoute {
+                sport: Sport::Mlb,
+                game_pk: 631377,
+                feed_type: FeedType::Away,
+                date: Some(NaiveDate::from_ymd_opt(2021, 4, 1).unwrap()),
+                quality: Some(Quality::Resolution {
+                    height: 720,
+                    frame_rate: Some(60),
+                }),
+            }
+        );
+
+        // Values as encoded by clients, e.g. the '=' of a bandwidth
+        assert_eq!(
+            StreamRoute::parse("/stream/nhl/1/HOME?quality=max-bandwidth%3D3000")
+                .unwrap()
+                .quality,
+            Some(Quality::MaxBandwidth(3000))
+        );
+
+        assert!(StreamRoute::parse("/").is_err());
+        assert!(StreamRoute::parse("/stream/nhl/1/HOME?quality=%FF").is_err());
+        assert!(StreamRoute::parse("/stream/nba/1/HOME").is_err());
+        assert!(StreamRoute::parse("/stream/nhl/abc/HOME").is_err());
+        assert!(StreamRoute::parse("/stream/nhl/1/SPANISH").is_err());
+    }
+
+    #[test]
+    fn stream_urls_parse_back() {
+        let url = stream_url(
+            "http://127.0.0.1:8080/",
+            Sport::Mlb,
+            631377,
+            NaiveDate::from_ymd_opt(2021, 4, 1).unwrap(),
+            FeedType::National,
+        );
+        assert_eq!(
+            url,
+            "http://127.0.0.1:8080/stream/mlb/631377/NATIONAL?date=2021-04-01"
+        );
+
+        let route = StreamRoute::parse(url.trim_start_matches("http://127.0.0.1:8080")).unwrap();
+        assert_eq!(route.sport, Sport::Mlb);
+        assert_eq!(route.game_pk, 631377);
+        assert_eq!(route.feed_type, FeedType::National);
+        assert_eq!(
+            route.date,
+            Some(NaiveDate::from_ymd_opt(2021, 4, 1).unwrap())
+        );
+    }
+
+    #[test]
+    fn redirects_to_current_link() {
+        let addr = stub_host();
+
+        let reply = task::block_on(resolve(&mut stream(addr, "live"), Cdn::Akc, None, false));
+        assert_eq!(
+            reply,
+            Reply::Redirect(format!("http://{}/hls/master.m3u8", addr))
+        );
+
+        let reply = task::block_on(resolve(
+            &mut stream(addr, "live"),
+            Cdn::Akc,
+            Some(Quality::Best),
+            false,
+        ));
+        assert_eq!(
+            reply,
+            Reply::Redirect(format!(
+                "http://{}/hls/6600K/6600_complete-trimmed.m3u8",
+                addr
+            ))
+        );
+    }
+
+    #[test]
+    fn proxies_playlist_with_absolute_uris() {
+        let addr = stub_host();
+
+        let reply = task::block_on(resolve(&mut stream(addr, "live"), Cdn::Akc, None, true));
+        let playlist = match reply {
+            Reply::Ok { body, .. } => body,
+            reply => panic!("Expected playlist, got {}", reply),
+        };
+
+        assert!(playlist.starts_with("#EXTM3U"));
+        assert!(playlist.contains(&format!(
+            "\nhttp://{}/hls/6600K/6600_complete-trimmed.m3u8\n",
+            addr
+        )));
+        assert!(playlist.contains(&format!("URI=\"http://{}/hls/audio/fr/index.m3u8\"", addr)));
+    }
+
+    #[test]
+    fn base_urls() {
+        let peer = SocketAddr::from(([127, 0, 0, 1], 1900));
+
+        assert_eq!(
+            base_url_for(SocketAddr::from(([192, 168, 0, 10], 8080)), peer).as_deref(),
+            Some("http://192.168.0.10:8080")
+        );
+        assert_eq!(
+            base_url_for(SocketAddr::from(([0, 0, 0, 0], 8080)), peer).as_deref(),
+            Some("http://127.0.0.1:8080")
+        );
+        assert_eq!(
+            base_url(SocketAddr::from(([127, 0, 0, 1], 8080))),
+            "http://127.0.0.1:8080"
+        );
+    }
+
+    #[test]
+    fn guide_is_rebuilt_after_ttl_or_date_change() {
+        let date = NaiveDate::from_ymd_opt(2021, 4, 1).unwrap();
+        let guide = |age| Guide {
+            built: Instant::now() - Duration::from_secs(age),
+            date,
+            playlist: String::new(),
+            xmltv: String::new(),
+            channels: vec![],
+        };
+        let ttl = Duration::from_secs(30 * 60);
+
+        assert!(!guide(0).is_stale(ttl, date));
+        assert!(!guide(29 * 60).is_stale(ttl, date));
+        assert!(guide(30 * 60).is_stale(ttl, date));
+        assert!(guide(0).is_stale(ttl, date.succ_opt().unwrap()));
+    }
+
+    #[test]
+    fn replies_close_the_connection() {
+        let response = |reply: Reply, head| {
+            let (status, headers, body) = reply.into_parts();
+            let mut response = vec![];
+            response::write_response(&mut response, status, &headers, body.as_bytes(), head)
+                .unwrap();
+            String::from_utf8(response).unwrap()
+        };
+
+        assert_eq!(
+            response(Reply::Redirect("http://cdn/master.m3u8".to_owned()), false),
+            "HTTP/1.1 302 Found\r\nLocation: http://cdn/master.m3u8\r\n\
+             Content-Length: 0\r\nConnection: close\r\n\r\n"
+        );
+        assert!(response(Reply::NotFound("No such game".to_owned()), true)
+            .ends_with("Content-Length: 12\r\nConnection: close\r\n\r\n"));
+    }
+
+    #[test]
+    fn unavailable_before_stream_is_live() {
+        let addr = stub_host();
+
+        let reply = task::block_on(resolve(&mut stream(addr, "later"), Cdn::Akc, None, false));
+        assert_eq!(reply.status_code(), 503);
+    }
+}
@@ -0,0 +1,311 @@
+use crate::{generate::Channel, log_error, VERSION};
+use failure::{Error, ResultExt};
+use serde_json::json;
+use std::{
+    net::{Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket},
+    thread,
+    time::Duration,
+};
+
+const DEVICE_ID: &str = "1A2B3C4D";
+const DEVICE_UUID: &str = "6c617a79-7374-7265-616d-00001a2b3c4d";
+const TUNER_COUNT: u32 = 4;
+
+const SSDP_ADDR: Ipv4Addr = Ipv4Addr::new(239, 255, 255, 250);
+const SSDP_PORT: u16 = 1900;
+/// Multicast group announcements are sent to, reached on the LAN interface
+pub const SSDP_GROUP: SocketAddr = SocketAddr::V4(SocketAddrV4::new(SSDP_ADDR, SSDP_PORT));
+/// How long announcements are valid for, announcements are repeated at half this
+const SSDP_MAX_AGE: u64 = 1800;
+const DEVICE_TYPE: &str = "urn:schemas-upnp-org:device:MediaServer:1";
+
+pub fn discover(server: &str) -> String {
+    json!({
+        "FriendlyName": "lazystream",
+        "Manufacturer": "Silicondust",
+        "ModelNumber": "HDTC-2US",
+        "FirmwareName": "hdhomeruntc_atsc",
+        "FirmwareVersion": VERSION,
+        "DeviceID": DEVICE_ID,
+        "DeviceAuth": "lazystream",
+        "BaseURL": server,
+        "LineupURL": format!("{}/lineup.json", server),
+        "TunerCount": TUNER_COUNT,
+    })
+    .to_string()
+}
+
+pub fn lineup_status() -> String {
+    json!({
+        "ScanInProgress": 0,
+        "ScanPossible": 1,
+        "Source": "Cable",
+        "SourceList": ["Cable"],
+    })
+    .to_string()
+}
+
+pub fn lineup(server: &str, channels: &[Channel]) -> String {
+    let lineup: Vec<_> = channels
+        .iter()
+        .map(|channel| {
+            json!({
+                "GuideNumber": channel.number.to_string(),
+                "GuideName": channel.name,
+                "URL": format!("{}/auto/v{}", server, channel.number),
+            })
+        })
+        .collect();
+
+    serde_json::Value::from(lineup).to_string()
+}
+
+/// UPnP device description, linked to from SSDP announcements
+pub fn device_xml(server: &str) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+         \n<root xmlns=\"urn:schemas-upnp-org:device-1-0\">\
+         \n  <specVersion>\
+         \n    <major>1</major>\
+         \n    <minor>0</minor>\
+         \n  </specVersion>\
+         \n  <URLBase>{}</URLBase>\
+         \n  <device>\
+         \n    <deviceType>{}</deviceType>\
+         \n    <friendlyName>lazystream</friendlyName>\
+         \n    <manufacturer>Silicondust</manufacturer>\
+         \n    <modelName>HDTC-2US</modelName>\
+         \n    <modelNumber>HDTC-2US</modelNumber>\
+         \n    <serialNumber>{}</serialNumber>\
+         \n    <UDN>uuid:{}</UDN>\
+         \n  </device>\
+         \n</root>",
+        server, DEVICE_TYPE, DEVICE_ID, DEVICE_UUID,
+    )
+}
+
+/// Channel number requested by a tune path, e.g. `/auto/v1005`
+pub fn tune_channel(path: &str) -> Option<u32> {
+    path.strip_prefix("/auto/v")?.parse().ok()
+}
+
+/// Answer SSDP searches & periodically announce the device, so it's found
+/// without entering its address. `bind` is the address the HTTP server
+/// listens on.
+pub fn announce(bind: SocketAddr) -> Result<(), Error> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, SSDP_PORT))
+        .context("Failed to bind SSDP port 1900")?;
+    socket
+        .join_multicast_v4(&SSDP_ADDR, &Ipv4Addr::UNSPECIFIED)
+        .context("Failed to join SSDP multicast group")?;
+
+    let notify_socket = socket.try_clone()?;
+    thread::spawn(move || loop {
+        let group = SSDP_GROUP;
+
+        if let Some(location) = location(bind, group) {
+            for target in targets() {
+                let _ = notify_socket.send_to(notify(&location, &target).as_bytes(), group);
+            }
+        }
+
+        thread::sleep(Duration::from_secs(SSDP_MAX_AGE / 2));
+    });
+
+    thread::spawn(move || {
+        let mut buf = [0; 2048];
+
+        loop {
+            let (len, peer) = match socket.recv_from(&mut buf) {
+                Ok(received) => received,
+                Err(e) => {
+                    log_error(&failure::Error::from(e).context("SSDP receive failed"));
+                    thread::sleep(Duration::from_secs(1));
+                    continue;
+                }
+            };
+
+            let message = String::from_utf8_lossy(&buf[..len]);
+            let search_target = match search_target(&message) {
+                Some(search_target) => search_target,
+                None => continue,
+            };
+
+            if let Some(location) = location(bind, peer) {
+                for target in matching_targets(&search_target) {
+                    let _ = socket.send_to(search_response(&location, &target).as_bytes(), peer);
+                }
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// Url of the device description, using the interface `peer` is reached on
+/// when the server listens on every interface
+fn location(bind: SocketAddr, peer: SocketAddr) -> Option<String> {
+    super::base_url_for(bind, peer).map(|url| format!("{}/device.xml", url))
+}
+
+fn targets() -> Vec<String> {
+    vec![
+        "upnp:rootdevice".to_owned(),
+        DEVICE_TYPE.to_owned(),
+        format!("uuid:{}", DEVICE_UUID),
+    ]
+}
+
+/// `ST` header of an `M-SEARCH` request
+fn search_target(message: &str) -> Option<String> {
+    let mut lines = message.lines();
+
+    if !lines.next()?.starts_with("M-SEARCH") {
+        return None;
+    }
+
+    lines.find_map(|line| {
+        let idx = line.find(':')?;
+        if line[..idx].trim().eq_ignore_ascii_case("ST") {
+            Some(line[idx + 1..].trim().to_owned())
+        } else {
+            None
+        }
+    })
+}
+
+fn matching_targets(search_target: &str) -> Vec<String> {
+    targets()
+        .into_iter()
+        .filter(|target| search_target == "ssdp:all" || search_target == target)
+        .collect()
+}
+
+fn usn(target: &str) -> String {
+    if target.starts_with("uuid:") {
+        target.to_owned()
+    } else {
+        format!("uuid:{}::{}", DEVICE_UUID, target)
+    }
+}
+
+fn search_response(location: &str, target: &str) -> String {
+    format!(
+        "HTTP/1.1 200 OK\r\n\
+         CACHE-CONTROL: max-age={}\r\n\
+         EXT:\r\n\
+         LOCATION: {}\r\n\
+         SERVER: lazystream/{} UPnP/1.0\r\n\
+         ST: {}\r\n\
+         USN: {}\r\n\r\n",
+        SSDP_MAX_AGE,
+        location,
+        VERSION,
+        target,
+        usn(target),
+    )
+}
+
+fn notify(location: &str, target: &str) -> String {
+    format!(
+        "NOTIFY * HTTP/1.1\r\n\
+         HOST: {}:{}\r\n\
+         CACHE-CONTROL: max-age={}\r\n\
+         LOCATION: {}\r\n\
+         NT: {}\r\n\
+         NTS: ssdp:alive\r\n\
+         SERVER: lazystream/{} UPnP/1.0\r\n\
+         USN: {}\r\n\r\n",
+        SSDP_ADDR,
+        SSDP_PORT,
+        SSDP_MAX_AGE,
+        location,
+        target,
+        VERSION,
+        usn(target),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::opt::{FeedType, Sport};
+    use chrono::NaiveDate;
+
+    const SERVER: &str = "http://192.168.0.10:8080";
+
+    #[test]
+    fn discover_points_at_lineup() {
+        let discover: serde_json::Value = serde_json::from_str(&discover(SERVER)).unwrap();
+
+        assert_eq!(discover["BaseURL"], SERVER);
+        assert_eq!(
+            discover["LineupURL"],
+            "http://192.168.0.10:8080/lineup.json"
+        );
+        assert_eq!(discover["DeviceID"], DEVICE_ID);
+    }
+
+    #[test]
+    fn lineup_uses_channel_numbers() {
+        let channel = |number, name: &str| Channel {
+            number,
+            name: name.to_owned(),
+            sport: Sport::Nhl,
+            game_pk: 2019020700,
+            selected_date: NaiveDate::from_ymd_opt(2021, 4, 1).unwrap(),
+            feed_type: FeedType::Home,
+        };
+        let channels = vec![channel(1000, "Lazyman 1"), channel(1001, "Lazyman 2")];
+
+        let lineup: serde_json::Value = serde_json::from_str(&lineup(SERVER, &channels)).unwrap();
+
+        assert_eq!(lineup[1]["GuideNumber"], "1001");
+        assert_eq!(lineup[1]["GuideName"], "Lazyman 2");
+        assert_eq!(lineup[1]["URL"], "http://192.168.0.10:8080/auto/v1001");
+    }
+
+    #[test]
+    fn tune_paths() {
+        assert_eq!(tune_channel("/auto/v1001"), Some(1001));
+        assert_eq!(tune_channel("/auto/v"), None);
+        assert_eq!(tune_channel("/lineup.json"), None);
+    }
+
+    #[test]
+    fn answers_searches() {
+        let search = "M-SEARCH * HTTP/1.1\r\n\
+                      HOST: 239.255.255.250:1900\r\n\
+                      MAN: \"ssdp:discover\"\r\n\
+                      st: ssdp:all\r\n\
+                      MX: 1\r\n\r\n";
+
+        assert_eq!(search_target(search).as_deref(), Some("ssdp:all"));
+        assert_eq!(matching_targets("ssdp:all").len(), 3);
+        assert_eq!(matching_targets(DEVICE_TYPE), vec![DEVICE_TYPE.to_owned()]);
+        assert!(matching_targets("urn:dial-multiscreen-org:service:dial:1").is_empty());
+
+        assert_eq!(
+            search_target(&notify("http://x/device.xml", DEVICE_TYPE)),
+            None
+        );
+
+        let response = search_response("http://192.168.0.10:8080/device.xml", DEVICE_TYPE);
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("\r\nLOCATION: http://192.168.0.10:8080/device.xml\r\n"));
+        assert!(response.contains(&format!(
+            "\r\nUSN: uuid:{}::{}\r\n",
+            DEVICE_UUID, DEVICE_TYPE
+        )));
+    }
+
+    #[test]
+    fn location_uses_bind_address() {
+        let peer = SocketAddr::from(([192, 168, 0, 20], 1900));
+
+        assert_eq!(
+            location(SocketAddr::from(([192, 168, 0, 10], 8080)), peer).as_deref(),
+            Some("http://192.168.0.10:8080/device.xml")
+        );
+    }
+}
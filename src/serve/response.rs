@@ -0,0 +1,47 @@
+//! Responses that close the connection after they're sent. tiny_http's worker
+//! pool can leave a new connection queued while every worker waits on an idle
+//! keep-alive one. The stub servers of the tests respond this way too, which
+//! is why this module only depends on tiny_http.
+
+use std::io::{self, Write};
+use tiny_http::{Method, Request, StatusCode};
+
+/// Respond to `request` and close the connection. The body is left out for a
+/// HEAD request.
+pub fn respond(
+    request: Request,
+    status: u16,
+    headers: &[(&str, String)],
+    body: &[u8],
+) -> io::Result<()> {
+    let head = *request.method() == Method::Head;
+    write_response(request.into_writer(), status, headers, body, head)
+}
+
+/// Write a complete HTTP response asking the client to close the connection
+pub fn write_response<W: Write>(
+    mut writer: W,
+    status: u16,
+    headers: &[(&str, String)],
+    body: &[u8],
+    head: bool,
+) -> io::Result<()> {
+    write!(
+        writer,
+        "HTTP/1.1 {} {}\r\n",
+        status,
+        StatusCode(status).default_reason_phrase()
+    )?;
+    for (name, value) in headers {
+        write!(writer, "{}: {}\r\n", name, value)?;
+    }
+    write!(
+        writer,
+        "Content-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    )?;
+    if !head {
+        writer.write_all(body)?;
+    }
+    writer.flush()
+}
@@ -2,19 +2,24 @@ use crate::{
     api::{
         client::Client,
         model::{
-            GameContentArticleMediaImageCut, GameContentEditorialItem, GameContentResponse, Team,
+            GameContentArticleMediaImageCut, GameContentEditorialItem, GameContentResponse,
+            GameStatus, ScheduleGameLinescore, Team,
         },
     },
-    opt::{Cdn, FeedType, Opt, Quality, Sport},
+    cdn,
+    hls::{AudioRendition, MasterPlaylist},
+    host,
+    opt::{Cdn, FeedType, Hosts, Opt, Quality, Sport},
+    team,
 };
-use chrono::{DateTime, NaiveDate, Utc};
-use failure::{bail, format_err, Error, ResultExt};
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use failure::{bail, Error, ResultExt};
 use futures::{future, AsyncReadExt};
 use isahc::{http, AsyncBody, HttpClient, Request};
 use once_cell::sync::Lazy;
-use std::{collections::BTreeMap, str::FromStr};
+use std::{collections::BTreeMap, str::FromStr, sync::Arc};
 
-static SHARED_CLIENT: Lazy<HttpClient> = Lazy::new(|| {
+pub static SHARED_CLIENT: Lazy<HttpClient> = Lazy::new(|| {
     HttpClient::builder()
         .max_connections_per_host(6)
         .build()
@@ -29,49 +34,68 @@ pub struct LazyStream {
 }
 
 impl LazyStream {
+    /// Games of every selected sport & date, sorted by start time
     pub async fn new(opts: &Opt) -> Result<Self, Error> {
-        let client = Client::new(opts.sport);
-
-        let schedule = if let Some(date) = opts.date {
-            client.get_schedule_for(date).await?
-        } else {
-            client.get_todays_schedule().await?
-        };
-
-        let date = schedule.date;
+        let mut games = vec![];
+        let mut teams = vec![];
+        let mut schedule_date = None;
 
-        let teams = client.get_teams().await?;
+        for sport in opts.sports.iter() {
+            let client = Arc::new(Client::new(sport, opts));
+            let sport_teams = client.get_teams().await?;
 
-        let mut games = vec![];
-        for game in schedule.games {
-            let game_pk = game.game_pk;
-            let game_date = game.date;
-            let home_team = teams
-                .iter()
-                .find(|team| team.id == game.teams.home.detail.id);
-            let away_team = teams
-                .iter()
-                .find(|team| team.id == game.teams.away.detail.id);
-            if let Some((home_team, away_team)) = home_team.zip(away_team) {
-                let game = Game::new(
-                    opts.sport,
-                    opts.host.clone(),
-                    game_pk,
-                    game_date,
-                    date,
-                    home_team.clone(),
-                    away_team.clone(),
-                );
-                games.push(game);
+            for date in opts.schedule_dates() {
+                let schedule = if let Some(date) = date {
+                    client.get_schedule_for(date).await?
+                } else {
+                    client.get_todays_schedule().await?
+                };
+
+                let date = schedule.date;
+                schedule_date.get_or_insert(date);
+
+                for game in schedule.games {
+                    let game_pk = game.game_pk;
+                    let game_date = game.date;
+                    let status = game.status;
+                    let away_score = game.teams.away.score;
+                    let home_score = game.teams.home.score;
+                    let linescore = game.linescore.clone();
+                    let home_team = sport_teams
+                        .iter()
+                        .find(|team| team.id == game.teams.home.detail.id);
+                    let away_team = sport_teams
+                        .iter()
+                        .find(|team| team.id == game.teams.away.detail.id);
+                    if let Some((home_team, away_team)) = home_team.zip(away_team) {
+                        let mut game = Game::new(
+                            client.clone(),
+                            opts.host.clone(),
+                            game_pk,
+                            game_date,
+                            date,
+                            home_team.clone(),
+                            away_team.clone(),
+                        );
+                        game.status = status;
+                        game.away_score = away_score;
+                        game.home_score = home_score;
+                        game.linescore = linescore;
+                        games.push(game);
+                    }
+                }
             }
+
+            teams.extend(sport_teams);
         }
         games.sort_by_key(|game| (game.game_date, game.away_team.name.clone()));
+        number_doubleheaders(&mut games);
 
         Ok(LazyStream {
             opts: opts.clone(),
             games,
             teams,
-            schedule_date: date,
+            schedule_date: schedule_date.unwrap_or_else(|| Local::now().naive_local().date()),
         })
     }
 
@@ -83,49 +107,70 @@ impl LazyStream {
         self.games.clone()
     }
 
-    pub fn check_team_abbrev(&self, team_abbrev: &str) -> Result<(), Error> {
-        if self
-            .teams
+    /// Team matching `query`, see `team::find`
+    pub fn find_team(&self, query: &str) -> Result<Team, Error> {
+        team::find(&self.teams, query).cloned()
+    }
+
+    /// Every game `team` plays, in order, e.g. both games of a doubleheader
+    pub fn games_with_team(&self, team: &Team) -> Vec<Game> {
+        self.games
             .iter()
-            .any(|team| team.abbreviation.as_deref() == Some(team_abbrev))
-        {
-            Ok(())
-        } else {
-            bail!("Team abbreviation {} does not exist", team_abbrev);
-        }
+            .filter(|game| game.has_team(team))
+            .cloned()
+            .collect()
     }
 
-    pub fn game_with_team_abbrev(&self, team_abbrev: &str) -> Option<Game> {
-        let game_idx = self.games.iter().position(|game| {
-            game.home_team.abbreviation.as_deref() == Some(team_abbrev)
-                || game.away_team.abbreviation.as_deref() == Some(team_abbrev)
-        });
+    /// Games of the first day whose streams resolved
+    fn first_day_games(&mut self) -> impl Iterator<Item = &mut Game> {
+        let first_day = self.schedule_date;
 
-        game_idx.map(|idx| self.games[idx].clone())
+        self.games
+            .iter_mut()
+            .filter(move |game| game.selected_date == first_day && game.streams.is_some())
     }
 
-    #[allow(clippy::drop_ref)]
-    pub async fn resolve_with_master_link(&mut self, cdn: Cdn) {
+    /// Resolve the streams of every game, without resolving any links
+    pub async fn resolve_streams(&mut self) {
         let tasks: Vec<_> = self
             .games
             .iter_mut()
-            .map(|game| async {
+            .map(|game| async move {
+                if let Err(e) = game.resolve_streams().await {
+                    crate::log_error(&e.context(format!(
+                        "Failed to resolve stream for game {}",
+                        game.game_pk
+                    )));
+                }
+            })
+            .collect();
+
+        future::join_all(tasks).await;
+    }
+
+    /// Resolve the streams of every game, and master links for the games of
+    /// the first day. Streams of later days can't be live yet.
+    pub async fn resolve_with_master_link(&mut self, cdn: Cdn) {
+        self.resolve_streams().await;
+
+        let tasks: Vec<_> = self
+            .first_day_games()
+            .map(|game| async move {
                 game.resolve_streams_master_link(cdn).await;
-                drop(game);
             })
             .collect();
 
         future::join_all(tasks).await;
     }
 
-    #[allow(clippy::drop_ref)]
+    /// Like `resolve_with_master_link`, with links for `quality`
     pub async fn resolve_with_quality_link(&mut self, cdn: Cdn, quality: Quality) {
+        self.resolve_streams().await;
+
         let tasks: Vec<_> = self
-            .games
-            .iter_mut()
-            .map(|game| async {
+            .first_day_games()
+            .map(|game| async move {
                 game.resolve_streams_quality_link(cdn, quality).await;
-                drop(game);
             })
             .collect();
 
@@ -133,13 +178,39 @@ impl LazyStream {
     }
 }
 
+/// Number the games of teams playing each other more than once a day, in the
+/// order they start
+fn number_doubleheaders(games: &mut [Game]) {
+    for idx in 0..games.len() {
+        let game = &games[idx];
+        let earlier = games[..idx]
+            .iter()
+            .filter(|other| other.same_matchup(game))
+            .count();
+        let total = games
+            .iter()
+            .filter(|other| other.same_matchup(game))
+            .count();
+
+        if total > 1 {
+            games[idx].game_number = Some(earlier as u32 + 1);
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct Game {
-    sport: Sport,
-    host: String,
+    client: Arc<Client>,
+    hosts: Hosts,
     pub game_pk: u64,
     pub game_date: DateTime<Utc>,
     pub selected_date: NaiveDate,
+    /// Game 1 or 2 of a doubleheader, `None` when the teams only play once that day
+    pub game_number: Option<u32>,
+    pub status: GameStatus,
+    pub away_score: Option<u8>,
+    pub home_score: Option<u8>,
+    pub linescore: Option<ScheduleGameLinescore>,
     pub streams: Option<BTreeMap<FeedType, Stream>>,
     pub home_team: Team,
     pub away_team: Team,
@@ -148,8 +219,8 @@ pub struct Game {
 
 impl Game {
     fn new(
-        sport: Sport,
-        host: String,
+        client: Arc<Client>,
+        hosts: Hosts,
         game_pk: u64,
         game_date: DateTime<Utc>,
         selected_date: NaiveDate,
@@ -157,11 +228,16 @@ impl Game {
         away_team: Team,
     ) -> Self {
         Game {
-            sport,
-            host,
+            client,
+            hosts,
             game_pk,
             game_date,
             selected_date,
+            game_number: None,
+            status: GameStatus::default(),
+            away_score: None,
+            home_score: None,
+            linescore: None,
             streams: None,
             home_team,
             away_team,
@@ -169,22 +245,62 @@ impl Game {
         }
     }
 
+    pub fn sport(&self) -> Sport {
+        self.client.sport()
+    }
+
+    pub fn has_team(&self, team: &Team) -> bool {
+        team::is_same(&self.home_team, team) || team::is_same(&self.away_team, team)
+    }
+
+    /// Status from a fresh copy of the schedule, which may have changed since
+    /// the game was loaded
+    pub async fn refresh_status(&mut self) -> Result<GameStatus, Error> {
+        let schedule = self.client.refresh_schedule_for(self.selected_date).await?;
+        if let Some(game) = schedule
+            .games
+            .iter()
+            .find(|game| game.game_pk == self.game_pk)
+        {
+            self.status = game.status;
+        }
+
+        Ok(self.status)
+    }
+
+    /// ` (Game N)` for games of a doubleheader, empty otherwise
+    pub fn game_number_label(&self) -> String {
+        self.game_number
+            .map(|number| format!(" (Game {})", number))
+            .unwrap_or_default()
+    }
+
+    /// Whether both games are between the same teams on the same day
+    fn same_matchup(&self, other: &Game) -> bool {
+        self.sport() == other.sport()
+            && self.selected_date == other.selected_date
+            && self.has_team(&other.home_team)
+            && self.has_team(&other.away_team)
+    }
+
     pub async fn streams(&mut self) -> Result<BTreeMap<FeedType, Stream>, Error> {
         if self.streams.is_none() {
             let mut streams = BTreeMap::new();
             let game_content = self.game_content().await?;
 
+            let provider = self.client.provider();
+
             if let Some(epg) = game_content.media.and_then(|media| media.epg) {
                 for epg in epg {
-                    if epg.title == "NHLTV" || epg.title == "MLBTV" {
+                    if epg.title == provider.epg_title() {
                         if let Some(items) = epg.items {
                             for item in items {
-                                if let Some(feed_type) = item.media_feed_type {
-                                    let id = match self.sport {
-                                        Sport::Mlb => format!("{}", item.id.unwrap()),
-                                        Sport::Nhl => item.media_playback_id.unwrap(),
-                                    };
+                                let id = match provider.stream_id(&item) {
+                                    Some(id) => id,
+                                    None => continue,
+                                };
 
+                                if let Some(feed_type) = item.media_feed_type {
                                     let feed_type = match FeedType::from_str(feed_type.as_str()) {
                                         Ok(feed_type) => feed_type,
                                         Err(_) => continue,
@@ -192,10 +308,9 @@ impl Game {
 
                                     let stream = Stream::new(
                                         id,
-                                        self.host.clone(),
-                                        self.sport,
+                                        self.hosts.clone(),
+                                        provider.host_league(),
                                         feed_type,
-                                        self.game_date,
                                         self.selected_date,
                                     );
                                     streams.insert(feed_type, stream);
@@ -214,8 +329,7 @@ impl Game {
 
     pub async fn game_content(&mut self) -> Result<GameContentResponse, Error> {
         if self.game_content.is_none() {
-            let client = Client::new(self.sport);
-            let game_content = client.get_game_content(self.game_pk).await?;
+            let game_content = self.client.get_game_content(self.game_pk).await?;
             self.game_content = Some(game_content.clone());
             Ok(game_content)
         } else {
@@ -230,7 +344,7 @@ impl Game {
             items: Some(items), ..
         }) = game_content.editorial.preview
         {
-            let item = items.get(0)?;
+            let item = items.first()?;
 
             if let Some(media) = item.media.clone() {
                 return Some(media.image.cuts);
@@ -246,7 +360,7 @@ impl Game {
             items: Some(items), ..
         }) = game_content.editorial.preview
         {
-            let item = &items.get(0)?;
+            let item = &items.first()?;
 
             return Some(item.subhead.clone());
         }
@@ -256,7 +370,7 @@ impl Game {
     pub async fn stream_with_feed_or_default(
         &mut self,
         feed_type: Option<FeedType>,
-        team_abbrev: &str,
+        team: &Team,
     ) -> Result<Stream, Error> {
         let mut streams = if self.streams.is_none() {
             self.streams().await?
@@ -266,7 +380,7 @@ impl Game {
 
         let mut feed_type: FeedType = if let Some(feed_type) = feed_type {
             feed_type
-        } else if self.home_team.abbreviation.as_deref() == Some(team_abbrev) {
+        } else if team::is_same(&self.home_team, team) {
             FeedType::Home
         } else {
             FeedType::Away
@@ -293,7 +407,6 @@ impl Game {
         self.streams().await.map(|_| ())
     }
 
-    #[allow(clippy::drop_ref)]
     async fn resolve_streams_master_link(&mut self, cdn: Cdn) {
         if self.streams.is_none() {
             if let Err(e) = self.resolve_streams().await {
@@ -311,16 +424,14 @@ impl Game {
             .as_mut()
             .unwrap()
             .iter_mut()
-            .map(|(_, stream)| async {
+            .map(|(_, stream)| async move {
                 stream.resolve_master_link(cdn).await;
-                drop(stream);
             })
             .collect();
 
         future::join_all(tasks).await;
     }
 
-    #[allow(clippy::drop_ref)]
     async fn resolve_streams_quality_link(&mut self, cdn: Cdn, quality: Quality) {
         if self.streams.is_none() {
             if let Err(e) = self.resolve_streams().await {
@@ -337,9 +448,8 @@ impl Game {
             .as_mut()
             .unwrap()
             .iter_mut()
-            .map(|(_, stream)| async {
+            .map(|(_, stream)| async move {
                 stream.resolve_quality_link(cdn, quality).await;
-                drop(stream);
             })
             .collect();
 
@@ -351,90 +461,181 @@ impl Game {
 #[allow(clippy::option_option)]
 pub struct Stream {
     id: String,
-    host: String,
-    sport: Sport,
+    hosts: Hosts,
+    /// Host the master link was last resolved from
+    served_by: Option<String>,
+    /// CDN the master link was last resolved on
+    cdn: Option<Cdn>,
+    /// `league=` value the host expects
+    league: &'static str,
     pub feed_type: FeedType,
-    game_date: DateTime<Utc>,
     selected_date: NaiveDate,
     master_link: Option<Option<String>>,
-    master_m3u8: Option<String>,
+    master_playlist: Option<MasterPlaylist>,
     quality_link: Option<Option<String>>,
 }
 
 impl Stream {
-    fn new(
+    pub fn new(
         id: String,
-        host: String,
-        sport: Sport,
+        hosts: Hosts,
+        league: &'static str,
         feed_type: FeedType,
-        game_date: DateTime<Utc>,
         selected_date: NaiveDate,
     ) -> Self {
         Stream {
             id,
-            host,
-            sport,
+            hosts,
+            served_by: None,
+            cdn: None,
+            league,
             feed_type,
-            game_date,
             selected_date,
             master_link: None,
-            master_m3u8: None,
+            master_playlist: None,
             quality_link: None,
         }
     }
 
+    /// Link on the host that served the stream, or the first host until one has.
+    /// With `Cdn::Auto` it's on the CDN that served the stream, or the first, as
+    /// a link can't switch CDN once it's handed out.
     pub fn host_link(&self, cdn: Cdn) -> String {
+        let cdn = match cdn {
+            Cdn::Auto => self.cdn.unwrap_or(Cdn::ALL[0]),
+            cdn => cdn,
+        };
+
+        self.link_on(self.served_by().unwrap_or_else(|| self.hosts.first()), cdn)
+    }
+
+    pub fn link_on(&self, host: &str, cdn: Cdn) -> String {
         format!(
             "{}/getM3U8.php?league={}&date={}&id={}&cdn={}",
-            self.host,
-            self.sport,
+            host,
+            self.league,
             self.selected_date.format("%Y-%m-%d"),
             self.id,
             cdn,
         )
     }
 
+    /// Host the master link was resolved from, once it has been
+    pub fn served_by(&self) -> Option<&str> {
+        self.served_by.as_deref()
+    }
+
+    /// Master link if it has been resolved, without resolving it
+    pub fn resolved_master_link(&self) -> Option<&str> {
+        self.master_link.as_ref().and_then(Option::as_deref)
+    }
+
+    /// CDN the master link was resolved on, once it has been
+    pub fn cdn(&self) -> Option<Cdn> {
+        self.cdn
+    }
+
+    pub fn hosts(&self) -> &Hosts {
+        &self.hosts
+    }
+
+    /// Master link of the stream on `cdn`. With `Cdn::Auto` the faster CDN is
+    /// tried first, then the other. CDNs are only timed once the stream
+    /// resolves, so polling a stream that isn't live doesn't time them.
     pub async fn master_link(&mut self, cdn: Cdn) -> Result<String, Error> {
         if self.master_link.is_none() {
-            match get_master_link(&self.host_link(cdn)).await {
-                Ok(master_link) => {
-                    self.master_link = Some(Some(master_link.clone()));
-                    Ok(master_link)
+            let cdns = match cdn {
+                Cdn::Auto => {
+                    let first = cdn::picked().unwrap_or(Cdn::ALL[0]);
+                    vec![first, first.other()]
                 }
-                Err(e) => {
-                    self.master_link = Some(None);
-                    bail!(e);
+                cdn => vec![cdn],
+            };
+
+            let mut last_error = None;
+            for cdn in cdns {
+                match self.resolve_on(cdn).await {
+                    Ok(()) => {
+                        last_error = None;
+                        break;
+                    }
+                    Err(e) => last_error = Some(e),
+                }
+            }
+            if let Some(e) = last_error {
+                self.master_link = Some(None);
+                return Err(e);
+            }
+
+            if let (Cdn::Auto, None) = (cdn, cdn::picked()) {
+                let resolved = self.cdn;
+                if let Some(fastest) = cdn::fastest(self).await.filter(|f| Some(*f) != resolved) {
+                    // Keep the link already resolved if the faster CDN fails now
+                    let _ = self.resolve_on(fastest).await;
                 }
             }
-        } else if let Some(master_link) = self.master_link.clone().unwrap() {
+        }
+
+        if let Some(master_link) = self.master_link.clone().unwrap() {
             Ok(master_link)
         } else {
-            bail!("Master link is not avaialable");
+            bail!("Master link is not available");
+        }
+    }
+
+    async fn resolve_on(&mut self, cdn: Cdn) -> Result<(), Error> {
+        let (host, master_link) =
+            host::master_link(&self.hosts, |host| self.link_on(host, cdn)).await?;
+
+        self.served_by = Some(host);
+        self.cdn = Some(cdn);
+        self.master_link = Some(Some(master_link));
+        Ok(())
+    }
+
+    /// Forget resolved links, so they're resolved again on next use
+    pub fn clear_links(&mut self) {
+        self.master_link = None;
+        self.master_playlist = None;
+        self.quality_link = None;
+    }
+
+    /// Variant streams listed in the master playlist of this stream
+    pub async fn master_playlist(&mut self, cdn: Cdn) -> Result<MasterPlaylist, Error> {
+        if let Some(master_playlist) = self.master_playlist.clone() {
+            return Ok(master_playlist);
         }
+
+        let master_link = match self.master_link(cdn).await {
+            Ok(master_link) => master_link,
+            Err(e) if host::is_unreachable(&e) || host::is_http_error(&e) => return Err(e),
+            Err(_) => bail!("Master link not available yet"),
+        };
+        let master_m3u8 = get_m3u8(&master_link).await?;
+        let master_playlist = MasterPlaylist::parse(&master_link, &master_m3u8)?;
+
+        self.master_playlist = Some(master_playlist.clone());
+        Ok(master_playlist)
+    }
+
+    /// Audio tracks listed in the master playlist of this stream
+    pub async fn audio_renditions(&mut self, cdn: Cdn) -> Result<Vec<AudioRendition>, Error> {
+        self.master_playlist(cdn)
+            .await
+            .map(|master_playlist| master_playlist.audio)
     }
 
     pub async fn quality_link(&mut self, cdn: Cdn, quality: Quality) -> Result<String, Error> {
         if self.quality_link.is_none() {
-            if self.master_m3u8.is_none() {
-                if let Ok(master_link) = self.master_link(cdn).await {
-                    match get_master_m3u8(&master_link).await {
-                        Err(e) => {
-                            self.quality_link = Some(None);
-                            bail!(e);
-                        }
-                        Ok(master_m3u8) => {
-                            self.master_m3u8 = Some(master_m3u8);
-                        }
-                    }
-                } else {
+            let master_playlist = match self.master_playlist(cdn).await {
+                Ok(master_playlist) => master_playlist,
+                Err(e) => {
                     self.quality_link = Some(None);
-                    bail!("Master link not available yet");
+                    return Err(e);
                 }
-            }
-            let master_link = self.master_link.as_ref().unwrap().as_ref().unwrap();
-            let master_m3u8 = self.master_m3u8.as_ref().unwrap();
+            };
 
-            if let Ok(quality_link) = get_quality_link(master_link, master_m3u8, quality) {
+            if let Ok(quality_link) = get_quality_link(&master_playlist, quality) {
                 self.quality_link = Some(Some(quality_link.clone()));
                 Ok(quality_link)
             } else {
@@ -457,30 +658,8 @@ impl Stream {
     }
 }
 
-async fn get_master_link(url: &str) -> Result<String, Error> {
-    let uri = url.parse::<http::Uri>().context("Failed to build URI")?;
-    let request = Request::builder()
-        .method("GET")
-        .uri(uri)
-        .body(AsyncBody::empty())
-        .unwrap();
-
-    let resp = SHARED_CLIENT.send_async(request).await?;
-
-    let mut body = resp.into_body();
-    let mut body_text = String::new();
-    body.read_to_string(&mut body_text)
-        .await
-        .context("Failed to read response body text")?;
-
-    if !&body_text[..].starts_with("https") {
-        bail!("Stream not available yet");
-    }
-
-    Ok(body_text)
-}
-
-async fn get_master_m3u8(url: &str) -> Result<String, Error> {
+/// Download the playlist at `url`
+pub async fn get_m3u8(url: &str) -> Result<String, Error> {
     let uri = url.parse::<http::Uri>().context("Failed to build URI")?;
     let request = Request::builder()
         .method("GET")
@@ -500,48 +679,12 @@ async fn get_master_m3u8(url: &str) -> Result<String, Error> {
         return Ok(body_text);
     }
 
-    bail!("Failed to get master m3u8");
+    bail!("Failed to get m3u8 playlist");
 }
 
-fn get_quality_link(
-    master_link: &str,
-    master_m3u8: &str,
-    quality: Quality,
-) -> Result<String, Error> {
-    let all_qualities = Quality::ALL;
-
-    let mut available_qualities = vec![];
-
-    for (idx, line) in master_m3u8.lines().enumerate() {
-        for quality in all_qualities.iter().cloned() {
-            let quality_str: &str = quality.into();
-            let quality_check = format!("x{}", quality_str);
-
-            if (quality == Quality::_720p60 && line.contains("FRAME-RATE=59.94"))
-                || (quality != Quality::_720p60
-                    && line.contains(&quality_check)
-                    && !line.contains("FRAME-RATE=59.94"))
-            {
-                available_qualities.push((quality, idx));
-                continue;
-            }
-        }
-    }
-
-    available_qualities.sort_by(|(a, _), (b, _)| a.cmp(b).reverse());
-
-    if let Some((_, idx)) = available_qualities.iter().find(|(q, _)| *q <= quality) {
-        let quality_line = master_m3u8
-            .lines()
-            .nth(*idx + 1)
-            .ok_or_else(|| format_err!("No stream found matching quality specified"))?;
-
-        let master_link_parts = master_link.rsplitn(2, '/').collect::<Vec<&str>>();
-        if master_link_parts.len() == 2 {
-            let quality_link = format!("{}/{}", master_link_parts[1], quality_line);
-
-            return Ok(quality_link);
-        }
+fn get_quality_link(master_playlist: &MasterPlaylist, quality: Quality) -> Result<String, Error> {
+    if let Some(variant) = master_playlist.variant(quality) {
+        return Ok(variant.uri.clone());
     }
 
     bail!("No stream found matching quality specified");
@@ -1,11 +1,18 @@
 use crate::{
-    log_error,
-    opt::{CastCommand, Command, Opt, PlayCommand, Quality, RecordCommand},
+    api::model::Team,
+    host, log_error,
+    opt::{
+        CastCommand, Cdn, Command, FeedType, Opt, PlayCommand, Quality, RecordBackend,
+        RecordCommand, Teams,
+    },
+    recorder::RecorderArgs,
+    retry::{self, RetryPolicy},
     stream::{Game, LazyStream, Stream},
 };
 use async_std::{process, task};
-use chrono::Local;
+use chrono::{Local, Utc};
 use failure::{bail, format_err, Error, ResultExt};
+use futures::future;
 use isahc::http::Uri;
 use mdns::RecordKind;
 use read_input::prelude::*;
@@ -28,14 +35,21 @@ pub fn run(opts: Opt) {
 }
 
 async fn process(opts: Opt) -> Result<(), Error> {
-    task::spawn_blocking(check_streamlink)
-        .await
-        .context(format_err!(
-            "Could not find and run Streamlink. Please ensure it is installed \
-             and accessible from your PATH"
-        ))?;
-
-    let (game, mut stream, command, restart, proxy, offset, quality) = match &opts.command {
+    let native_record = matches!(
+        &opts.command,
+        Command::Record { command } if command.backend() == RecordBackend::Native
+    );
+
+    if !native_record {
+        task::spawn_blocking(check_streamlink)
+            .await
+            .context(format_err!(
+                "Could not find and run Streamlink. Please ensure it is installed \
+                 and accessible from your PATH"
+            ))?;
+    }
+
+    let selected = match &opts.command {
         Command::Play { command } => process_play(&opts, command).await?,
         Command::Record { command } => process_record(&opts, command).await?,
         Command::Cast { command } => process_cast(&opts, command).await?,
@@ -43,51 +57,439 @@ async fn process(opts: Opt) -> Result<(), Error> {
     };
 
     println!();
-    while stream.master_link(opts.cdn).await.is_err() {
-        if opts.disable_retry {
-            bail!("Stream not available yet");
+
+    if let StreamlinkCommand::Record { .. } = selected.command {
+        // Every game is recorded, at the same time if they overlap, except
+        // games of the same team which are recorded in order
+        let count = selected.streams.len();
+        let tasks: Vec<_> = recording_order(selected.streams.clone())
+            .into_iter()
+            .map(|streams| {
+                let selected = &selected;
+                let opts = &opts;
+                async move {
+                    let mut results = vec![];
+                    for (game, stream) in streams {
+                        let label = label(&game, &stream);
+                        let result = async {
+                            let (game, stream) =
+                                wait_until_live(opts, vec![(game, stream)], false, count > 1)
+                                    .await?;
+                            start(opts, game, stream, selected).await
+                        }
+                        .await;
+
+                        results.push(
+                            result.map_err(|e| e.context(format!("Failed to record {}", label))),
+                        );
+                    }
+                    results
+                }
+            })
+            .collect();
+
+        let failed: Vec<_> = future::join_all(tasks)
+            .await
+            .into_iter()
+            .flatten()
+            .filter_map(Result::err)
+            .collect();
+
+        match failed.len() {
+            0 => {}
+            1 if count == 1 => return Err(failed.into_iter().next().unwrap().into()),
+            _ => {
+                for e in failed.iter() {
+                    log_error(e);
+                }
+                bail!("{} of {} recordings failed", failed.len(), count);
+            }
         }
+    } else {
+        let (game, stream) =
+            wait_until_live(&opts, selected.streams.clone(), selected.priority, false).await?;
+        start(&opts, game, stream, &selected).await?;
+    }
 
-        println!("Stream not available yet, will check again soon...");
-        task::sleep(Duration::from_secs(60 * 30)).await;
+    Ok(())
+}
+
+/// Streams picked from the command line, and how to stream them
+struct Selected {
+    streams: Vec<(Game, Stream)>,
+    command: StreamlinkCommand,
+    restart: bool,
+    proxy: Option<Uri>,
+    offset: Option<String>,
+    quality: Option<Quality>,
+    /// Prefer streams listed first over streams of games that started first,
+    /// when several are live
+    priority: bool,
+}
+
+impl Selected {
+    fn new(
+        streams: Vec<(Game, Stream)>,
+        command: StreamlinkCommand,
+        restart: bool,
+        proxy: &Option<Uri>,
+        offset: &Option<String>,
+        opts: &Opt,
+    ) -> Self {
+        Selected {
+            streams,
+            command,
+            restart,
+            proxy: proxy.clone(),
+            offset: offset.clone(),
+            quality: opts.quality,
+            priority: false,
+        }
     }
-    let link = if let Some(quality) = quality {
+}
+
+/// e.g. `Vegas Golden Knights @ Boston Bruins HOME`, or
+/// `New York Yankees @ Boston Red Sox (Game 2) HOME` in a doubleheader
+fn label(game: &Game, stream: &Stream) -> String {
+    format!(
+        "{} @ {}{} {}",
+        game.away_team.name,
+        game.home_team.name,
+        game.game_number_label(),
+        stream.feed_type
+    )
+}
+
+/// Error for a postponed or cancelled game
+fn called_off(game: &Game) -> Error {
+    format_err!(
+        "{} @ {}{} is {}",
+        game.away_team.name,
+        game.home_team.name,
+        game.game_number_label(),
+        game.status.to_string().to_lowercase()
+    )
+}
+
+/// Group streams to record so games sharing a team are recorded one after the
+/// other, in the order they start
+fn recording_order(streams: Vec<(Game, Stream)>) -> Vec<Vec<(Game, Stream)>> {
+    let team = |team: &Team| (team.id, team.name.clone());
+    let matchups: Vec<_> = streams
+        .iter()
+        .map(|(game, _)| (team(&game.home_team), team(&game.away_team)))
+        .collect();
+
+    let mut streams: Vec<_> = streams.into_iter().map(Some).collect();
+    let mut groups: Vec<Vec<(Game, Stream)>> = group_by_team(&matchups)
+        .into_iter()
+        .map(|group| {
+            group
+                .into_iter()
+                .filter_map(|idx| streams[idx].take())
+                .collect()
+        })
+        .collect();
+
+    for group in groups.iter_mut() {
+        group.sort_by_key(|(game, _)| game.game_date);
+    }
+
+    groups
+}
+
+/// Indices of `matchups` grouped so matchups sharing a team, directly or
+/// through other matchups, are in the same group
+fn group_by_team<T: PartialEq>(matchups: &[(T, T)]) -> Vec<Vec<usize>> {
+    fn root(parent: &[usize], mut idx: usize) -> usize {
+        while parent[idx] != idx {
+            idx = parent[idx];
+        }
+        idx
+    }
+
+    // Union-find, each matchup pointing at another of its group
+    let mut parent: Vec<usize> = (0..matchups.len()).collect();
+    for (idx, (home, away)) in matchups.iter().enumerate() {
+        for (other, (other_home, other_away)) in matchups[..idx].iter().enumerate() {
+            if home == other_home || home == other_away || away == other_home || away == other_away
+            {
+                let (a, b) = (root(&parent, idx), root(&parent, other));
+                parent[a.max(b)] = a.min(b);
+            }
+        }
+    }
+
+    let mut groups: Vec<Vec<usize>> = vec![];
+    let mut roots = vec![];
+    for idx in 0..matchups.len() {
+        let root = root(&parent, idx);
+        match roots.iter().position(|other| *other == root) {
+            Some(group) => groups[group].push(idx),
+            None => {
+                roots.push(root);
+                groups.push(vec![idx]);
+            }
+        }
+    }
+
+    groups
+}
+
+/// Wait until one of `streams` is live. When several are, the one listed first
+/// is used with `priority`, otherwise the one whose game started first.
+/// `quiet` logs a line per check instead of counting down, for waits that run
+/// side by side.
+async fn wait_until_live(
+    opts: &Opt,
+    mut streams: Vec<(Game, Stream)>,
+    priority: bool,
+    quiet: bool,
+) -> Result<(Game, Stream), Error> {
+    let retry_policy = RetryPolicy::new(opts);
+
+    loop {
+        let mut live = vec![];
+        // Set when no stream host could be reached, which says nothing about
+        // whether the streams are live
+        let mut unreachable = None;
+        // Set when the hosts answered with errors, which waiting won't fix
+        let mut http_error = None;
+        for (idx, (_, stream)) in streams.iter_mut().enumerate() {
+            match stream.master_link(opts.cdn).await {
+                Ok(_) => live.push(idx),
+                Err(e) if host::is_unreachable(&e) => unreachable = Some(e),
+                Err(e) if host::is_http_error(&e) => http_error = Some(e),
+                Err(_) => {}
+            }
+        }
+
+        if !priority {
+            live.sort_by_key(|idx| streams[*idx].0.game_date);
+        }
+        if let Some(idx) = live.first() {
+            return Ok(streams.remove(*idx));
+        }
+        if let Some(e) = http_error {
+            return Err(e);
+        }
+
+        // Stop waiting for streams the policy gave up on, or of games that
+        // were called off in the meantime
+        let mut waits = vec![];
+        let mut last_error = None;
+        for (mut game, stream) in streams.drain(..) {
+            if let Ok(status) = game.refresh_status().await {
+                if status.is_off() {
+                    last_error = Some(called_off(&game));
+                    continue;
+                }
+            }
+
+            match retry_policy.wait(game.game_date, Utc::now()) {
+                Ok(wait) => waits.push((wait, (game, stream))),
+                Err(e) => last_error = Some(e),
+            }
+        }
+        if waits.is_empty() {
+            return Err(unreachable.or(last_error).unwrap());
+        }
+
+        let wait = waits.iter().map(|(wait, _)| *wait).min().unwrap();
+        streams = waits.into_iter().map(|(_, stream)| stream).collect();
+
+        let message = unreachable
+            .map(|e| e.to_string())
+            .unwrap_or_else(|| "Stream not available yet".to_owned());
+        if quiet {
+            let (game, stream) = &streams[0];
+            println!(
+                "{}: {}, checking again in {}",
+                label(game, stream),
+                message,
+                retry::format_duration(wait.as_secs())
+            );
+            task::sleep(wait).await;
+        } else {
+            retry::countdown(&message, wait).await;
+        }
+
+        for (_, stream) in streams.iter_mut() {
+            stream.clear_links();
+        }
+    }
+}
+
+/// Play, record or cast a live stream
+async fn start(
+    opts: &Opt,
+    game: Game,
+    mut stream: Stream,
+    selected: &Selected,
+) -> Result<(), Error> {
+    let command = selected.command.clone();
+
+    if let Some(audio_source) = command.audio_source() {
+        check_audio_source(&mut stream, opts.cdn, audio_source).await?;
+    }
+
+    let link = if let Some(quality) = selected.quality {
         stream.quality_link(opts.cdn, quality).await?
     } else {
         stream.master_link(opts.cdn).await?
     };
+    if let (Some(host), Some(cdn)) = (stream.served_by(), stream.cdn()) {
+        println!(
+            "{}: Served by {} on CDN {}",
+            label(&game, &stream),
+            host,
+            cdn
+        );
+    }
+
+    // With `--cdn auto`, switch to the other CDN if this one starts failing
+    let fallback = match opts.cdn {
+        Cdn::Auto => fallback_link(&stream, selected.quality).await,
+        _ => None,
+    };
 
     let args = StreamlinkArgs {
         link,
+        fallback,
         game,
         stream,
         command,
-        restart,
-        proxy,
-        offset,
-        quality,
+        restart: selected.restart,
+        proxy: selected.proxy.clone(),
+        offset: selected.offset.clone(),
+        quality: selected.quality,
+        part: 1,
     };
 
-    task::spawn_blocking(move || streamlink(args)).await?;
+    if let StreamlinkCommand::Record {
+        output,
+        audio_source,
+        backend: RecordBackend::Native,
+    } = &args.command
+    {
+        println!(
+            "Recording {}...\n\n============================\n",
+            label(&args.game, &args.stream)
+        );
+
+        let args = RecorderArgs {
+            output: recording_path(output, &args.game, &args.stream, 1),
+            audio_source: audio_source.clone(),
+            link: args.link,
+            fallback: args.fallback,
+            restart: args.restart,
+            proxy: args.proxy,
+            offset: args.offset,
+        };
+        crate::recorder::record(args).await?;
+
+        println!("\n============================\n\nRecording finished");
+    } else {
+        task::spawn_blocking(move || streamlink(args)).await?;
+    }
 
     Ok(())
 }
 
-async fn process_play(
+/// Link of `stream` on the CDN other than the one it's served on
+async fn fallback_link(stream: &Stream, quality: Option<Quality>) -> Option<(Cdn, String)> {
+    let cdn = stream.cdn()?.other();
+
+    let mut stream = stream.clone();
+    stream.clear_links();
+    let link = match quality {
+        Some(quality) => stream.quality_link(cdn, quality).await,
+        None => stream.master_link(cdn).await,
+    };
+
+    link.ok().map(|link| (cdn, link))
+}
+
+/// Streams of every game today for `teams`, in the order the teams are given,
+/// or only game `game_number` of a doubleheader
+async fn team_streams(
     opts: &Opt,
-    command: &PlayCommand,
-) -> Result<
-    (
-        Game,
-        Stream,
-        StreamlinkCommand,
-        bool,
-        Option<Uri>,
-        Option<String>,
-        Option<Quality>,
-    ),
-    Error,
-> {
+    teams: &Teams,
+    feed_type: Option<FeedType>,
+    game_number: Option<u32>,
+) -> Result<Vec<(Game, Stream)>, Error> {
+    let lazy_stream = LazyStream::new(opts).await?;
+
+    let mut streams: Vec<(Game, Stream)> = vec![];
+    let mut without_games = vec![];
+    let mut called_off_games = vec![];
+    for query in teams.iter() {
+        let team = lazy_stream.find_team(query)?;
+        println!("Found matching team for {}: {}", query, team.name);
+
+        let mut games = lazy_stream.games_with_team(&team);
+        if let Some(number) = game_number {
+            games.retain(|game| game.game_number.unwrap_or(1) == number);
+        }
+
+        if games.is_empty() {
+            without_games.push(team.name);
+            continue;
+        }
+
+        for mut game in games {
+            // Two of the teams may play each other
+            if streams
+                .iter()
+                .any(|(other, _)| other.game_pk == game.game_pk)
+            {
+                continue;
+            }
+
+            match game.game_number {
+                Some(number) => println!("Game {} found for today", number),
+                None => println!("Game found for today"),
+            }
+
+            // It won't go live, so there's nothing to wait for
+            if game.status.is_off() {
+                called_off_games.push(called_off(&game));
+                continue;
+            }
+
+            let stream = game.stream_with_feed_or_default(feed_type, &team).await?;
+            println!("Using stream feed {}", stream.feed_type);
+
+            streams.push((game, stream));
+        }
+    }
+
+    let no_games = |names: &str| match game_number {
+        Some(number) => format!("There is no game {} today for {}", number, names),
+        None => format!("There are no games today for {}", names),
+    };
+    let last_called_off = if streams.is_empty() {
+        match called_off_games.pop() {
+            Some(e) => Some(e),
+            None => bail!(no_games(&without_games.join(", "))),
+        }
+    } else {
+        None
+    };
+    for name in without_games {
+        println!("{}", no_games(&name));
+    }
+    for e in called_off_games {
+        println!("{}", e);
+    }
+    if let Some(e) = last_called_off {
+        return Err(e);
+    }
+
+    Ok(streams)
+}
+
+async fn process_play(opts: &Opt, command: &PlayCommand) -> Result<Selected, Error> {
     match command {
         PlayCommand::Select {
             restart,
@@ -95,71 +497,40 @@ async fn process_play(
             offset,
             ..
         } => {
-            let (game, stream) = crate::select::process(opts, true).await?;
+            let stream = crate::select::process(opts, true).await?;
 
             let streamlink_command = StreamlinkCommand::from(command);
-            Ok((
-                game,
-                stream,
+            Ok(Selected::new(
+                vec![stream],
                 streamlink_command,
                 *restart,
-                proxy.clone(),
-                offset.clone(),
-                opts.quality,
+                proxy,
+                offset,
+                opts,
             ))
         }
         PlayCommand::Team {
-            team_abbrev,
+            teams,
             restart,
             feed_type,
+            game_number,
             proxy,
             offset,
+            priority,
             ..
         } => {
-            let lazy_stream = LazyStream::new(opts).await?;
-            lazy_stream.check_team_abbrev(team_abbrev)?;
-            println!("Found matching team for {}", team_abbrev);
-
-            if let Some(mut game) = lazy_stream.game_with_team_abbrev(team_abbrev) {
-                println!("Game found for today");
-
-                let stream = game
-                    .stream_with_feed_or_default(*feed_type, team_abbrev)
-                    .await?;
-                println!("Using stream feed {}", stream.feed_type);
-
-                let streamlink_command = StreamlinkCommand::from(command);
-                Ok((
-                    game,
-                    stream,
-                    streamlink_command,
-                    *restart,
-                    proxy.clone(),
-                    offset.clone(),
-                    opts.quality,
-                ))
-            } else {
-                bail!("There are no games today for {}", team_abbrev);
-            }
+            let streams = team_streams(opts, teams, *feed_type, *game_number).await?;
+
+            let streamlink_command = StreamlinkCommand::from(command);
+            Ok(Selected {
+                priority: *priority,
+                ..Selected::new(streams, streamlink_command, *restart, proxy, offset, opts)
+            })
         }
     }
 }
 
-async fn process_record(
-    opts: &Opt,
-    command: &RecordCommand,
-) -> Result<
-    (
-        Game,
-        Stream,
-        StreamlinkCommand,
-        bool,
-        Option<Uri>,
-        Option<String>,
-        Option<Quality>,
-    ),
-    Error,
-> {
+async fn process_record(opts: &Opt, command: &RecordCommand) -> Result<Selected, Error> {
     match command {
         RecordCommand::Select {
             output,
@@ -169,23 +540,23 @@ async fn process_record(
             ..
         } => {
             check_output(output)?;
-            let (game, stream) = crate::select::process(opts, true).await?;
+            let stream = crate::select::process(opts, true).await?;
 
             let streamlink_command = StreamlinkCommand::from(command);
-            Ok((
-                game,
-                stream,
+            Ok(Selected::new(
+                vec![stream],
                 streamlink_command,
                 *restart,
-                proxy.clone(),
-                offset.clone(),
-                opts.quality,
+                proxy,
+                offset,
+                opts,
             ))
         }
         RecordCommand::Team {
-            team_abbrev,
+            teams,
             restart,
             feed_type,
+            game_number,
             output,
             proxy,
             offset,
@@ -193,50 +564,22 @@ async fn process_record(
         } => {
             check_output(output)?;
 
-            let lazy_stream = LazyStream::new(opts).await?;
-            lazy_stream.check_team_abbrev(team_abbrev)?;
-            println!("Found matching team for {}", team_abbrev);
-
-            if let Some(mut game) = lazy_stream.game_with_team_abbrev(team_abbrev) {
-                println!("Game found for today");
-
-                let stream = game
-                    .stream_with_feed_or_default(*feed_type, team_abbrev)
-                    .await?;
-                println!("Using stream feed {}", stream.feed_type);
-
-                let streamlink_command = StreamlinkCommand::from(command);
-                Ok((
-                    game,
-                    stream,
-                    streamlink_command,
-                    *restart,
-                    proxy.clone(),
-                    offset.clone(),
-                    opts.quality,
-                ))
-            } else {
-                bail!("There are no games today for {}", team_abbrev);
-            }
+            let streams = team_streams(opts, teams, *feed_type, *game_number).await?;
+
+            let streamlink_command = StreamlinkCommand::from(command);
+            Ok(Selected::new(
+                streams,
+                streamlink_command,
+                *restart,
+                proxy,
+                offset,
+                opts,
+            ))
         }
     }
 }
 
-async fn process_cast(
-    opts: &Opt,
-    command: &CastCommand,
-) -> Result<
-    (
-        Game,
-        Stream,
-        StreamlinkCommand,
-        bool,
-        Option<Uri>,
-        Option<String>,
-        Option<Quality>,
-    ),
-    Error,
-> {
+async fn process_cast(opts: &Opt, command: &CastCommand) -> Result<Selected, Error> {
     task::spawn_blocking(check_vlc).await.context(format_err!(
         "Could not find and run VLC. Please ensure it is installed \
          and accessible from your PATH"
@@ -248,8 +591,9 @@ async fn process_cast(
             proxy,
             offset,
             audio_source,
+            ..
         } => {
-            let (game, stream) = crate::select::process(opts, true).await?;
+            let stream = crate::select::process(opts, true).await?;
 
             let cast_devices = task::spawn_blocking(|| {
                 print!("\nSearching for cast devices...");
@@ -264,54 +608,40 @@ async fn process_cast(
             let streamlink_command =
                 StreamlinkCommand::cast_with_ip(cast_ip.to_string(), audio_source.clone());
 
-            Ok((
-                game,
-                stream,
+            Ok(Selected::new(
+                vec![stream],
                 streamlink_command,
                 *restart,
-                proxy.clone(),
-                offset.clone(),
-                opts.quality,
+                proxy,
+                offset,
+                opts,
             ))
         }
         CastCommand::Team {
-            team_abbrev,
+            teams,
+            cast_host,
             restart,
             feed_type,
+            game_number,
             proxy,
             offset,
+            priority,
             ..
         } => {
-            let lazy_stream = LazyStream::new(opts).await?;
-            lazy_stream.check_team_abbrev(team_abbrev)?;
-            println!("Found matching team for {}", team_abbrev);
-
-            if let Some(mut game) = lazy_stream.game_with_team_abbrev(team_abbrev) {
-                println!("Game found for today");
-
-                let stream = game
-                    .stream_with_feed_or_default(*feed_type, team_abbrev)
-                    .await?;
-                println!("Using stream feed {}", stream.feed_type);
-
-                let streamlink_command = StreamlinkCommand::from(command);
-                Ok((
-                    game,
-                    stream,
-                    streamlink_command,
-                    *restart,
-                    proxy.clone(),
-                    offset.clone(),
-                    opts.quality,
-                ))
-            } else {
-                bail!("There are no games today for {}", team_abbrev);
-            }
+            check_cast_host(cast_host)?;
+
+            let streams = team_streams(opts, teams, *feed_type, *game_number).await?;
+
+            let streamlink_command = StreamlinkCommand::from(command);
+            Ok(Selected {
+                priority: *priority,
+                ..Selected::new(streams, streamlink_command, *restart, proxy, offset, opts)
+            })
         }
     }
 }
 
-#[derive(PartialEq)]
+#[derive(PartialEq, Clone)]
 enum StreamlinkCommand {
     Play {
         passthrough: bool,
@@ -320,6 +650,7 @@ enum StreamlinkCommand {
     Record {
         output: PathBuf,
         audio_source: Option<String>,
+        backend: RecordBackend,
     },
     Cast {
         cast_host: String,
@@ -334,6 +665,14 @@ impl StreamlinkCommand {
             audio_source,
         }
     }
+
+    fn audio_source(&self) -> Option<&str> {
+        match self {
+            StreamlinkCommand::Play { .. } => None,
+            StreamlinkCommand::Record { audio_source, .. } => audio_source.as_deref(),
+            StreamlinkCommand::Cast { audio_source, .. } => audio_source.as_deref(),
+        }
+    }
 }
 
 impl From<&PlayCommand> for StreamlinkCommand {
@@ -365,18 +704,24 @@ impl From<&RecordCommand> for StreamlinkCommand {
             RecordCommand::Select {
                 output,
                 audio_source,
+                backend,
                 ..
             } => StreamlinkCommand::Record {
-                output: output.clone(),
+                // Checked by `check_output` beforehand
+                output: output.clone().unwrap_or_default(),
                 audio_source: audio_source.clone(),
+                backend: *backend,
             },
             RecordCommand::Team {
                 output,
                 audio_source,
+                backend,
                 ..
             } => StreamlinkCommand::Record {
-                output: output.clone(),
+                // Checked by `check_output` beforehand
+                output: output.clone().unwrap_or_default(),
                 audio_source: audio_source.clone(),
+                backend: *backend,
             },
         }
     }
@@ -394,7 +739,8 @@ impl From<&CastCommand> for StreamlinkCommand {
                 audio_source,
                 ..
             } => StreamlinkCommand::Cast {
-                cast_host: cast_host.clone(),
+                // Checked by `check_cast_host` beforehand
+                cast_host: cast_host.clone().unwrap_or_default(),
                 audio_source: audio_source.clone(),
             },
         }
@@ -403,6 +749,8 @@ impl From<&CastCommand> for StreamlinkCommand {
 
 struct StreamlinkArgs {
     link: String,
+    /// CDN to switch to if Streamlink fails, and the link on it
+    fallback: Option<(Cdn, String)>,
     game: Game,
     stream: Stream,
     command: StreamlinkCommand,
@@ -410,6 +758,8 @@ struct StreamlinkArgs {
     proxy: Option<Uri>,
     offset: Option<String>,
     quality: Option<Quality>,
+    /// Recordings after switching CDN go to a new file, numbered from 2
+    part: u32,
 }
 
 fn streamlink(mut args: StreamlinkArgs) -> Result<(), Error> {
@@ -425,6 +775,39 @@ fn streamlink(mut args: StreamlinkArgs) -> Result<(), Error> {
         }
     }
 
+    while let Err(e) = run_streamlink(&mut args) {
+        let (cdn, link) = match args.fallback.take() {
+            Some(fallback) => fallback,
+            None => return Err(e),
+        };
+
+        log_error(e.as_fail());
+        println!("\nSwitching to CDN {}...\n", cdn);
+
+        // Continue from where the stream is now
+        args.link = link;
+        args.restart = false;
+        args.offset = None;
+        args.part += 1;
+    }
+
+    match &args.command {
+        StreamlinkCommand::Play { .. } => {
+            println!("\n============================\n\nPlayback finished")
+        }
+        StreamlinkCommand::Record { .. } => {
+            println!("\n============================\n\nRecording finished")
+        }
+        StreamlinkCommand::Cast { .. } => {
+            println!("\n============================\n\nCasting finished")
+        }
+    }
+
+    Ok(())
+}
+
+/// Run Streamlink once, failing if it exits with an error
+fn run_streamlink(args: &mut StreamlinkArgs) -> Result<(), Error> {
     let cmd = if cfg!(target_os = "windows") {
         "streamlink.exe"
     } else {
@@ -474,8 +857,8 @@ fn streamlink(mut args: StreamlinkArgs) -> Result<(), Error> {
     }
 
     let mut _offset = String::new();
-    if let Some(offset) = args.offset {
-        _offset = offset;
+    if let Some(offset) = &args.offset {
+        _offset = offset.clone();
         command_args.push("--hls-start-offset");
         command_args.push(&_offset);
     }
@@ -487,9 +870,10 @@ fn streamlink(mut args: StreamlinkArgs) -> Result<(), Error> {
             custom_player,
         } => {
             let title = format!(
-                "{} @ {} - {} - {}",
+                "{} @ {}{} - {} - {}",
                 args.game.away_team.name,
                 args.game.home_team.name,
+                args.game.game_number_label(),
                 args.stream.feed_type,
                 args.game
                     .game_date
@@ -517,25 +901,16 @@ fn streamlink(mut args: StreamlinkArgs) -> Result<(), Error> {
         StreamlinkCommand::Record {
             output,
             audio_source,
+            ..
         } => {
-            let filename = format!(
-                "{} {} @ {} {}.mp4",
-                args.game
-                    .game_date
-                    .with_timezone(&Local)
-                    .format("%Y-%m-%d %H%M"),
-                args.game.away_team.name,
-                args.game.home_team.name,
-                args.stream.feed_type
-            );
-            output.push(filename);
-
             if let Some(source) = audio_source {
                 command_args.push("--hls-audio-select");
                 command_args.push(source.as_str());
             }
 
-            _arg = output.display().to_string();
+            _arg = recording_path(output, &args.game, &args.stream, args.part)
+                .display()
+                .to_string();
 
             command_args.push("-o");
             command_args.push(_arg.as_str());
@@ -571,21 +946,31 @@ fn streamlink(mut args: StreamlinkArgs) -> Result<(), Error> {
         bail!("StreamLink failed");
     }
 
-    match &args.command {
-        StreamlinkCommand::Play { .. } => {
-            println!("\n============================\n\nPlayback finshed")
-        }
-        StreamlinkCommand::Record { .. } => {
-            println!("\n============================\n\nRecording finshed")
-        }
-        StreamlinkCommand::Cast { .. } => {
-            println!("\n============================\n\nCasting finshed")
-        }
-    }
-
     Ok(())
 }
 
+/// File to record `stream` to within the output directory, with e.g. ` (Part 2)`
+/// after `part` 1. Both record backends write an MPEG-TS stream to it.
+fn recording_path(output: &Path, game: &Game, stream: &Stream, part: u32) -> PathBuf {
+    let part = if part > 1 {
+        format!(" (Part {})", part)
+    } else {
+        String::new()
+    };
+
+    let filename = format!(
+        "{} {} @ {}{} {}{}.mp4",
+        game.game_date.with_timezone(&Local).format("%Y-%m-%d %H%M"),
+        game.away_team.name,
+        game.home_team.name,
+        game.game_number_label(),
+        stream.feed_type,
+        part
+    );
+
+    output.join(filename)
+}
+
 fn check_streamlink() -> Result<(), Error> {
     let cmd = if cfg!(target_os = "windows") {
         "streamlink.exe"
@@ -618,15 +1003,70 @@ fn check_vlc() -> Result<(), Error> {
     Ok(())
 }
 
+/// Make sure the audio source matches one of the audio tracks offered by the stream
+async fn check_audio_source(
+    stream: &mut Stream,
+    cdn: Cdn,
+    audio_source: &str,
+) -> Result<(), Error> {
+    if audio_source == "*" {
+        return Ok(());
+    }
+
+    let audio = stream.audio_renditions(cdn).await?;
+
+    if audio.is_empty() {
+        println!(
+            "Stream doesn't list any alternate audio tracks, audio source {} may not apply",
+            audio_source
+        );
+    } else if let Some(rendition) = audio
+        .iter()
+        .find(|rendition| rendition.matches(audio_source))
+    {
+        println!("Using audio track {}", rendition);
+    } else {
+        let available = audio
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", ");
+        bail!(
+            "Audio source {} isn't offered by this stream. Available tracks: {}",
+            audio_source,
+            available
+        );
+    }
+
+    Ok(())
+}
+
 /// Make sure output directory exists and can be written to
-fn check_output(directory: impl AsRef<Path>) -> Result<(), Error> {
-    if !directory.as_ref().is_dir() {
+fn check_output(directory: &Option<PathBuf>) -> Result<(), Error> {
+    let directory = match directory {
+        Some(directory) => directory,
+        None => bail!(
+            "No output directory given, pass OUTPUT_DIR or set 'output' under '[record]' in the config file"
+        ),
+    };
+
+    if !directory.is_dir() {
         bail!("Output diretory does not exist, please create it");
     }
 
     Ok(())
 }
 
+fn check_cast_host(cast_host: &Option<String>) -> Result<(), Error> {
+    if cast_host.is_none() {
+        bail!(
+            "No Chromecast given, pass CHROMECAST_HOST or set 'chromecast-host' under '[cast]' in the config file"
+        );
+    }
+
+    Ok(())
+}
+
 const SERVICE_NAME: &str = "_googlecast._tcp.local";
 
 #[allow(clippy::unnecessary_unwrap)]
@@ -681,7 +1121,34 @@ fn select_cast_device(devices: HashMap<Ipv4Addr, String>) -> Result<Ipv4Addr, Er
         .msg("\n>>> ")
         .add_test(move |input| *input > 0 && *input <= device_count)
         .get();
-    let addrs = device_addrs[(device_choice - 1)];
+    let addrs = device_addrs[device_choice - 1];
 
     Ok(*addrs)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn groups_games_sharing_teams() {
+        assert_eq!(
+            group_by_team(&[("A", "B"), ("C", "D"), ("E", "F")]),
+            vec![vec![0], vec![1], vec![2]]
+        );
+        assert_eq!(
+            group_by_team(&[("A", "B"), ("C", "D"), ("B", "A")]),
+            vec![vec![0, 2], vec![1]]
+        );
+
+        // B-C links the groups of A-B and C-D
+        assert_eq!(
+            group_by_team(&[("A", "B"), ("C", "D"), ("B", "C"), ("E", "F")]),
+            vec![vec![0, 1, 2], vec![3]]
+        );
+        assert_eq!(
+            group_by_team(&[("A", "B"), ("C", "D"), ("E", "F"), ("D", "E"), ("F", "A")]),
+            vec![vec![0, 1, 2, 3, 4]]
+        );
+    }
+}
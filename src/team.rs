@@ -0,0 +1,238 @@
+use crate::api::model::Team;
+use failure::{bail, Error};
+
+/// Abbreviations people commonly use that the stats API doesn't, and the
+/// abbreviation they refer to
+const ALIASES: &[(&str, &str)] = &[
+    // MLB
+    ("CHW", "CWS"),
+    ("KCR", "KC"),
+    ("SDP", "SD"),
+    ("SFG", "SF"),
+    ("TBR", "TB"),
+    ("WSN", "WSH"),
+    ("WAS", "WSH"),
+    ("ARI", "AZ"),
+    ("AZ", "ARI"),
+    // NHL
+    ("LA", "LAK"),
+    ("NJ", "NJD"),
+    ("SJ", "SJS"),
+    ("TB", "TBL"),
+    ("LV", "VGK"),
+    ("VEG", "VGK"),
+    ("MON", "MTL"),
+    ("CLB", "CBJ"),
+    ("NAS", "NSH"),
+    ("WIN", "WPG"),
+    ("PHX", "ARI"),
+];
+
+/// Maximum edit distance for a name to be suggested
+const MAX_DISTANCE: usize = 2;
+
+/// Team matching `query` by abbreviation, name, team name, location or short
+/// name, ignoring case & punctuation, or by a common alias of its abbreviation
+pub fn find<'a>(teams: &'a [Team], query: &str) -> Result<&'a Team, Error> {
+    let mut matching: Vec<_> = teams.iter().filter(|team| matches(team, query)).collect();
+
+    if matching.is_empty() {
+        let query = normalize(query);
+        if let Some((_, abbreviation)) = ALIASES.iter().find(|(alias, _)| normalize(alias) == query)
+        {
+            matching = teams
+                .iter()
+                .filter(|team| matches(team, abbreviation))
+                .collect();
+        }
+    }
+
+    match matching[..] {
+        [team] => Ok(team),
+        [] => {
+            let suggestions: Vec<_> = suggestions(teams, query)
+                .into_iter()
+                .map(describe)
+                .collect();
+
+            if suggestions.is_empty() {
+                bail!("Team {} does not exist", query);
+            } else {
+                bail!(
+                    "Team {} does not exist, did you mean: {}?",
+                    query,
+                    suggestions.join(", ")
+                );
+            }
+        }
+        _ => {
+            let teams: Vec<_> = matching.into_iter().map(describe).collect();
+            bail!(
+                "{} matches several teams, use one of: {}",
+                query,
+                teams.join(", ")
+            );
+        }
+    }
+}
+
+/// Whether `a` and `b` are the same team
+pub fn is_same(a: &Team, b: &Team) -> bool {
+    a.id == b.id && a.name == b.name
+}
+
+fn names(team: &Team) -> impl Iterator<Item = &str> {
+    vec![
+        team.abbreviation.as_deref(),
+        Some(team.name.as_str()),
+        Some(team.team_name.as_str()),
+        team.location_name.as_deref(),
+        team.short_name.as_deref(),
+    ]
+    .into_iter()
+    .flatten()
+    .filter(|name| !name.is_empty())
+}
+
+fn matches(team: &Team, query: &str) -> bool {
+    let query = normalize(query);
+    names(team).any(|name| normalize(name) == query)
+}
+
+/// Lowercase letters & digits only, so `st. louis` matches `St Louis`
+fn normalize(s: &str) -> String {
+    s.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+fn describe(team: &Team) -> String {
+    match &team.abbreviation {
+        Some(abbreviation) => format!("{} ({})", abbreviation, team.name),
+        None => team.name.clone(),
+    }
+}
+
+/// Teams with a name close to `query`, closest first
+fn suggestions<'a>(teams: &'a [Team], query: &str) -> Vec<&'a Team> {
+    let query = normalize(query);
+
+    let mut suggestions: Vec<_> = teams
+        .iter()
+        .filter_map(|team| {
+            names(team)
+                .map(|name| {
+                    let name = normalize(name);
+                    if query.len() >= 3 && name.contains(&query) {
+                        0
+                    } else {
+                        distance(&name, &query)
+                    }
+                })
+                .min()
+                .filter(|distance| *distance <= MAX_DISTANCE)
+                .map(|distance| (distance, team))
+        })
+        .collect();
+
+    suggestions.sort_by_key(|(distance, team)| (*distance, team.name.clone()));
+    suggestions
+        .into_iter()
+        .take(5)
+        .map(|(_, team)| team)
+        .collect()
+}
+
+/// Levenshtein distance
+fn distance(a: &str, b: &str) -> usize {
+    let b: Vec<_> = b.chars().collect();
+    let mut previous: Vec<_> = (0..=b.len()).collect();
+
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + if a == *b { 0 } else { 1 };
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn team(id: u32, abbreviation: &str, location_name: &str, team_name: &str) -> Team {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "name": format!("{} {}", location_name, team_name),
+            "abbreviation": abbreviation,
+            "teamName": team_name,
+            "locationName": location_name,
+            "shortName": location_name,
+        }))
+        .unwrap()
+    }
+
+    fn teams() -> Vec<Team> {
+        vec![
+            team(54, "VGK", "Vegas", "Golden Knights"),
+            team(19, "STL", "St. Louis", "Blues"),
+            team(3, "NYR", "New York", "Rangers"),
+            team(2, "NYI", "New York", "Islanders"),
+            team(145, "CWS", "Chicago", "White Sox"),
+        ]
+    }
+
+    fn find_abbreviation(query: &str) -> Result<String, Error> {
+        let teams = teams();
+        find(&teams, query).map(|team| team.abbreviation.clone().unwrap())
+    }
+
+    #[test]
+    fn matches_any_name_ignoring_case() {
+        for query in &[
+            "VGK",
+            "vgk",
+            "Vegas",
+            "golden knights",
+            "Vegas Golden Knights",
+        ] {
+            assert_eq!(find_abbreviation(query).unwrap(), "VGK");
+        }
+        assert_eq!(find_abbreviation("st louis").unwrap(), "STL");
+    }
+
+    #[test]
+    fn matches_aliases() {
+        assert_eq!(find_abbreviation("CHW").unwrap(), "CWS");
+        assert_eq!(find_abbreviation("lv").unwrap(), "VGK");
+    }
+
+    #[test]
+    fn suggests_close_names() {
+        let e = find_abbreviation("VKG").unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "Team VKG does not exist, did you mean: VGK (Vegas Golden Knights)?"
+        );
+
+        let e = find_abbreviation("knights").unwrap_err();
+        assert!(e.to_string().contains("VGK (Vegas Golden Knights)"));
+
+        let e = find_abbreviation("Toronto").unwrap_err();
+        assert_eq!(e.to_string(), "Team Toronto does not exist");
+    }
+
+    #[test]
+    fn ambiguous_names() {
+        let e = find_abbreviation("New York").unwrap_err();
+        assert!(e.to_string().contains("NYR (New York Rangers)"));
+        assert!(e.to_string().contains("NYI (New York Islanders)"));
+    }
+}
@@ -0,0 +1,354 @@
+use crate::{
+    log_error,
+    opt::{Command, FeedType, Opt, Sport, WatchFormat},
+    stream::LazyStream,
+    BANNER,
+};
+use async_std::{process, task};
+use chrono::{DateTime, Local, Utc};
+use failure::Error;
+use serde_json::json;
+use std::{collections::BTreeMap, time::Duration};
+
+/// Polling interval once no feed is waiting to go live
+const IDLE_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+pub fn run(opts: Opt) {
+    task::block_on(async {
+        if let Err(e) = process(&opts).await {
+            log_error(e.as_fail());
+            process::exit(1);
+        };
+    });
+}
+
+async fn process(opts: &Opt) -> Result<(), Error> {
+    let format = if let Command::Watch { format } = opts.command {
+        format
+    } else {
+        unreachable!()
+    };
+
+    if format == WatchFormat::Human {
+        println!("{}", BANNER);
+        println!("Watching {} feeds, press Ctrl+C to stop...\n", opts.sports);
+    }
+
+    let mut previous = Snapshot::new();
+
+    loop {
+        match snapshot(opts).await {
+            Ok(current) => {
+                for event in events(&previous, &current) {
+                    match format {
+                        WatchFormat::Human => println!("{}", event),
+                        WatchFormat::Ndjson => println!("{}", event.to_json()),
+                    }
+                }
+                previous = current;
+            }
+            // Keep the last snapshot so a failed poll isn't reported as every feed disappearing
+            Err(e) => log_error(&e.context("Failed to check streams, trying again later")),
+        }
+
+        task::sleep(poll_interval(&previous, Utc::now())).await;
+    }
+}
+
+type Snapshot = BTreeMap<(Sport, u64, FeedType), Feed>;
+
+#[derive(Debug, Clone, PartialEq)]
+struct Feed {
+    sport: Sport,
+    game_pk: u64,
+    feed_type: FeedType,
+    game_date: DateTime<Utc>,
+    home: String,
+    away: String,
+    /// Master link, once the feed is live
+    link: Option<String>,
+}
+
+impl Feed {
+    /// Team the feed belongs to, e.g. `VGK` for the home feed of a Golden
+    /// Knights home game, otherwise both teams
+    fn label(&self) -> String {
+        match self.feed_type {
+            FeedType::Home => self.home.clone(),
+            FeedType::Away => self.away.clone(),
+            _ => format!("{} @ {}", self.away, self.home),
+        }
+    }
+}
+
+/// Every feed of the selected date, with its master link if it's live
+async fn snapshot(opts: &Opt) -> Result<Snapshot, Error> {
+    // Feeds are listed in game content, which is cached for hours
+    let opts = Opt {
+        no_cache: true,
+        ..opts.clone()
+    };
+
+    let mut lazy_stream = LazyStream::new(&opts).await?;
+    lazy_stream.resolve_with_master_link(opts.cdn).await;
+
+    let mut snapshot = Snapshot::new();
+    for game in lazy_stream.games() {
+        let abbreviation = |name: &Option<String>, team_name: &str| {
+            name.clone().unwrap_or_else(|| team_name.to_owned())
+        };
+        let home = abbreviation(&game.home_team.abbreviation, &game.home_team.team_name);
+        let away = abbreviation(&game.away_team.abbreviation, &game.away_team.team_name);
+        let sport = game.sport();
+
+        for (feed_type, stream) in game.streams.unwrap_or_default() {
+            let feed = Feed {
+                sport,
+                game_pk: game.game_pk,
+                feed_type,
+                game_date: game.game_date,
+                home: home.clone(),
+                away: away.clone(),
+                // Only games of the first day are resolved above
+                link: stream.resolved_master_link().map(str::to_owned),
+            };
+            snapshot.insert((sport, game.game_pk, feed_type), feed);
+        }
+    }
+
+    Ok(snapshot)
+}
+
+#[derive(Debug, PartialEq)]
+enum EventKind {
+    Live,
+    /// Still listed, but the master link no longer resolves
+    Down,
+    Gone,
+}
+
+#[derive(Debug, PartialEq)]
+struct Event {
+    kind: EventKind,
+    feed: Feed,
+}
+
+impl Event {
+    fn to_json(&self) -> String {
+        let event = match self.kind {
+            EventKind::Live => "live",
+            EventKind::Down => "down",
+            EventKind::Gone => "gone",
+        };
+
+        json!({
+            "event": event,
+            "time": Utc::now().to_rfc3339(),
+            "sport": self.feed.sport.to_string().to_lowercase(),
+            "game_pk": self.feed.game_pk,
+            "game_date": self.feed.game_date.to_rfc3339(),
+            "feed": self.feed.feed_type.to_string(),
+            "home": self.feed.home,
+            "away": self.feed.away,
+            "link": self.feed.link,
+        })
+        .to_string()
+    }
+}
+
+impl std::fmt::Display for Event {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let status = match self.kind {
+            EventKind::Live => "is live",
+            EventKind::Down => "is down",
+            EventKind::Gone => "is gone",
+        };
+
+        write!(
+            f,
+            "[{}] {} {} {} ({} @ {}, {})",
+            Local::now().format("%H:%M:%S"),
+            self.feed.label(),
+            self.feed.feed_type,
+            status,
+            self.feed.away,
+            self.feed.home,
+            self.feed
+                .game_date
+                .with_timezone(&Local)
+                .format("%-I:%M %p"),
+        )
+    }
+}
+
+/// Feeds whose master link resolved or stopped resolving, and feeds no
+/// longer listed
+fn events(previous: &Snapshot, current: &Snapshot) -> Vec<Event> {
+    let changed = current.iter().filter_map(|(key, feed)| {
+        let was_live = previous
+            .get(key)
+            .and_then(|feed| feed.link.as_ref())
+            .is_some();
+
+        let kind = match (was_live, feed.link.is_some()) {
+            (false, true) => EventKind::Live,
+            (true, false) => EventKind::Down,
+            _ => return None,
+        };
+
+        Some(Event {
+            kind,
+            feed: feed.clone(),
+        })
+    });
+
+    let gone = previous
+        .iter()
+        .filter(|(key, _)| !current.contains_key(key))
+        .map(|(_, feed)| Event {
+            kind: EventKind::Gone,
+            feed: feed.clone(),
+        });
+
+    changed.chain(gone).collect()
+}
+
+/// Poll quickly around the start of games with feeds that aren't live yet,
+/// and rarely otherwise
+fn poll_interval(snapshot: &Snapshot, now: DateTime<Utc>) -> Duration {
+    snapshot
+        .values()
+        .filter(|feed| feed.link.is_none())
+        .map(|feed| {
+            let until_start = feed.game_date - now;
+
+            if until_start > chrono::Duration::hours(2) {
+                Duration::from_secs(15 * 60)
+            } else if until_start > chrono::Duration::minutes(30) {
+                Duration::from_secs(5 * 60)
+            } else if until_start > chrono::Duration::hours(-1) {
+                Duration::from_secs(30)
+            } else {
+                Duration::from_secs(2 * 60)
+            }
+        })
+        .min()
+        .unwrap_or(IDLE_INTERVAL)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn start() -> DateTime<Utc> {
+        "2021-04-01T23:00:00Z".parse().unwrap()
+    }
+
+    fn feed(
+        game_pk: u64,
+        feed_type: FeedType,
+        link: Option<&str>,
+    ) -> ((Sport, u64, FeedType), Feed) {
+        let feed = Feed {
+            sport: Sport::Nhl,
+            game_pk,
+            feed_type,
+            game_date: start(),
+            home: "BOS".to_owned(),
+            away: "VGK".to_owned(),
+            link: link.map(str::to_owned),
+        };
+        ((Sport::Nhl, game_pk, feed_type), feed)
+    }
+
+    #[test]
+    fn live_when_link_first_resolves() {
+        let previous: Snapshot = vec![
+            feed(1, FeedType::Home, None),
+            feed(1, FeedType::Away, Some("https://a")),
+        ]
+        .into_iter()
+        .collect();
+        let current: Snapshot = vec![
+            feed(1, FeedType::Home, Some("https://h")),
+            feed(1, FeedType::Away, Some("https://a2")),
+            feed(2, FeedType::National, Some("https://n")),
+        ]
+        .into_iter()
+        .collect();
+
+        let events = events(&previous, &current);
+        let live: Vec<_> = events
+            .iter()
+            .map(|event| (&event.kind, event.feed.game_pk, event.feed.feed_type))
+            .collect();
+
+        assert_eq!(
+            live,
+            vec![
+                (&EventKind::Live, 1, FeedType::Home),
+                (&EventKind::Live, 2, FeedType::National),
+            ]
+        );
+        assert_eq!(events[0].feed.label(), "BOS");
+        assert_eq!(events[1].feed.label(), "VGK @ BOS");
+    }
+
+    #[test]
+    fn gone_when_feed_disappears() {
+        let previous: Snapshot = vec![
+            feed(1, FeedType::Home, Some("https://h")),
+            feed(1, FeedType::French, None),
+        ]
+        .into_iter()
+        .collect();
+        let current: Snapshot = vec![feed(1, FeedType::Home, Some("https://h"))]
+            .into_iter()
+            .collect();
+
+        let events = events(&previous, &current);
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].kind, EventKind::Gone);
+        assert_eq!(events[0].feed.feed_type, FeedType::French);
+        assert!(super::events(&current, &current).is_empty());
+    }
+
+    #[test]
+    fn down_when_link_stops_resolving() {
+        let snapshot = |link: Option<&str>| -> Snapshot {
+            vec![feed(1, FeedType::Home, link)].into_iter().collect()
+        };
+        let kinds = |previous: &Snapshot, current: &Snapshot| -> Vec<EventKind> {
+            events(previous, current)
+                .into_iter()
+                .map(|event| event.kind)
+                .collect()
+        };
+
+        let live = snapshot(Some("https://h"));
+        let down = snapshot(None);
+
+        assert_eq!(kinds(&down, &live), vec![EventKind::Live]);
+        assert_eq!(kinds(&live, &down), vec![EventKind::Down]);
+        assert_eq!(kinds(&down, &down), vec![]);
+        assert_eq!(kinds(&down, &live), vec![EventKind::Live]);
+    }
+
+    #[test]
+    fn polls_faster_near_start() {
+        let snapshot: Snapshot = vec![feed(1, FeedType::Home, None)].into_iter().collect();
+        let at = |minutes| start() - chrono::Duration::minutes(minutes);
+
+        assert_eq!(poll_interval(&snapshot, at(180)).as_secs(), 15 * 60);
+        assert_eq!(poll_interval(&snapshot, at(60)).as_secs(), 5 * 60);
+        assert_eq!(poll_interval(&snapshot, at(10)).as_secs(), 30);
+        assert_eq!(poll_interval(&snapshot, at(-30)).as_secs(), 30);
+        assert_eq!(poll_interval(&snapshot, at(-90)).as_secs(), 2 * 60);
+
+        let live: Snapshot = vec![feed(1, FeedType::Home, Some("https://h"))]
+            .into_iter()
+            .collect();
+        assert_eq!(poll_interval(&live, at(10)), IDLE_INTERVAL);
+    }
+}
@@ -0,0 +1,825 @@
+//! Runs the binary against a local server standing in for both the stats API
+//! (serving `tests/fixtures/api`, see `lazystream dump`) and the stream host.
+
+use std::{
+    fs,
+    net::SocketAddr,
+    path::PathBuf,
+    process::{Command, Output},
+    thread,
+};
+use tiny_http::Server;
+
+/// Closes each connection like `serve` does
+#[path = "../src/serve/response.rs"]
+mod response;
+
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
+
+/// Stream id of the HOME feed, the only one the host has a link for
+const LIVE_ID: &str = "80123";
+
+fn start_server() -> SocketAddr {
+    let server = Server::http("127.0.0.1:0").unwrap();
+    let addr = server.server_addr();
+
+    thread::spawn(move || {
+        for request in server.incoming_requests() {
+            let (status, body) = respond(request.url(), addr);
+
+            let _ = response::respond(request, status, &[], &body);
+        }
+    });
+
+    addr
+}
+
+fn respond(url: &str, addr: SocketAddr) -> (u16, Vec<u8>) {
+    let (path, query) = match url.find('?') {
+        Some(idx) => (&url[..idx], &url[idx + 1..]),
+        None => (url, ""),
+    };
+
+    if path == "/getM3U8.php" {
+        if query.contains(&format!("id={}&", LIVE_ID)) {
+            let link = format!("http://{}/master/{}.m3u8", addr, LIVE_ID);
+            (200, link.into_bytes())
+        } else {
+            (200, b"Not available".to_vec())
+        }
+    } else if path == format!("/master/{}.m3u8", LIVE_ID) {
+        let master = fs::read(format!("{}/hls/nhl_master.m3u8", FIXTURES)).unwrap();
+        (200, master)
+    } else if path.starts_with("/master/") && path.ends_with(".m3u8") {
+        let media = "#EXTM3U\n#EXT-X-TARGETDURATION:5\n#EXTINF:5.0,\nsegment.ts\n";
+        (200, media.as_bytes().to_vec())
+    } else if path.starts_with("/master/") && path.ends_with(".ts") {
+        (200, vec![0x47; 188])
+    } else {
+        // Schedules of other days than the fixture day are next to it, e.g.
+        // `schedule-2021-04-02.json`
+        let dated = query
+            .strip_prefix("date=")
+            .map(|date| path.replace(".json", &format!("-{}.json", date)));
+        let data = dated
+            .and_then(|path| fs::read(format!("{}/api{}", FIXTURES, path)).ok())
+            .or_else(|| fs::read(format!("{}/api{}", FIXTURES, path)).ok());
+
+        match data {
+            Some(data) => (200, data),
+            None => (404, b"Not found".to_vec()),
+        }
+    }
+}
+
+/// Run lazystream for the fixture day, in UTC so times are stable, taking the
+/// plain http links of the stand-in host, without picking up the config file of
+/// whoever runs the tests
+fn lazystream(addr: SocketAddr, args: &[&str]) -> Output {
+    lazystream_with_hosts(addr, &format!("http://{}", addr), args)
+}
+
+/// Run lazystream like `lazystream`, with streams from `hosts` instead
+fn lazystream_with_hosts(addr: SocketAddr, hosts: &str, args: &[&str]) -> Output {
+    let server = format!("http://{}", addr);
+
+    // An empty config file, unless the test gives its own
+    let empty_config = empty_config();
+    let config = if args.contains(&"--config") {
+        vec![]
+    } else {
+        vec!["--config", empty_config.to_str().unwrap()]
+    };
+
+    Command::new(env!("CARGO_BIN_EXE_lazystream"))
+        .args(args)
+        .args(["--date", "20210401", "--no-cache"])
+        .args(["--api-mirror", &server, "--host", hosts])
+        // The stub host hands out plain http links
+        .args(["--link-scheme", "http"])
+        .env("TZ", "UTC")
+        .args(config)
+        .output()
+        .unwrap()
+}
+
+fn empty_config() -> PathBuf {
+    let path = std::env::temp_dir().join("lazystream-empty-config.toml");
+    fs::write(&path, "").unwrap();
+    path
+}
+
+fn out_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("lazystream-{}-{}", name, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn generate_playlist() {
+    let addr = start_server();
+    let dir = out_dir("playlist");
+
+    let output = lazystream(
+        addr,
+        &["generate", "playlist", dir.join("games").to_str().unwrap()],
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    // The AWAY feed isn't live, so it doesn't get a link
+    assert_eq!(
+        fs::read_to_string(dir.join("games.m3u")).unwrap(),
+        format!(
+            "#EXTM3U\n\
+             #EXTINF:-1 CUID=\"1000\" tvg-id=\"1000\" tvg-name=\"Lazyman 1\",11:00 PM Golden Knights @ Bruins HOME\n\
+             http://{0}/master/80123.m3u8\n\
+             #EXTINF:-1 CUID=\"1001\" tvg-id=\"1001\" tvg-name=\"Lazyman 2\",11:00 PM Golden Knights @ Bruins AWAY\n\
+             .\n",
+            addr
+        )
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn generate_xmltv() {
+    let addr = start_server();
+    let dir = out_dir("xmltv");
+
+    let output = lazystream(
+        addr,
+        &[
+            "generate",
+            "xmltv",
+            dir.join("guide").to_str().unwrap(),
+            "--trim",
+            "--start-channel",
+            "500",
+            "--quality",
+            "540p",
+        ],
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    assert_eq!(
+        fs::read_to_string(dir.join("guide.m3u")).unwrap(),
+        format!(
+            "#EXTM3U\n\
+             #EXTINF:-1 CUID=\"500\" tvg-id=\"500\" tvg-name=\"Lazyman 1\",Lazyman 1\n\
+             http://{0}/master/3500K/3500_complete-trimmed.m3u8\n\
+             #EXTINF:-1 CUID=\"501\" tvg-id=\"501\" tvg-name=\"Lazyman 2\",Lazyman 2\n\
+             .\n",
+            addr
+        )
+    );
+
+    assert_eq!(
+        fs::read_to_string(dir.join("guide.xml")).unwrap(),
+        include_str!("fixtures/cli/guide.xml").replace("{VERSION}", env!("CARGO_PKG_VERSION"))
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn playlist_pointing_at_server() {
+    let addr = start_server();
+    let dir = out_dir("server");
+
+    let output = lazystream(
+        addr,
+        &[
+            "generate",
+            "playlist",
+            dir.join("games").to_str().unwrap(),
+            "--exclude-feeds",
+            "AWAY",
+            "--server",
+            "http://127.0.0.1:8080",
+        ],
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    assert_eq!(
+        fs::read_to_string(dir.join("games.m3u")).unwrap(),
+        "#EXTM3U\n\
+         #EXTINF:-1 CUID=\"1000\" tvg-id=\"1000\" tvg-name=\"Lazyman 1\",11:00 PM Golden Knights @ Bruins HOME\n\
+         http://127.0.0.1:8080/stream/nhl/2020020700/HOME?date=2021-04-01\n"
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn stream_not_available_yet() {
+    let addr = start_server();
+    let dir = out_dir("stream-not-available-yet");
+
+    let output = lazystream(
+        addr,
+        &[
+            "record",
+            "team",
+            "VGK",
+            dir.to_str().unwrap(),
+            "--feed-type",
+            "AWAY",
+            "--backend",
+            "native",
+            "--disable-retry",
+        ],
+    );
+
+    assert!(!output.status.success());
+    assert!(
+        stderr(&output).contains("Stream not available yet"),
+        "{}",
+        stderr(&output)
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn unreachable_host_is_reported() {
+    let addr = start_server();
+    let dir = out_dir("unreachable-host-is-reported");
+    let args = [
+        "record",
+        "team",
+        "VGK",
+        dir.to_str().unwrap(),
+        "--feed-type",
+        "AWAY",
+        "--backend",
+        "native",
+        "--disable-retry",
+    ];
+
+    let output = lazystream_with_hosts(addr, "http://127.0.0.1:1", &args);
+    assert!(!output.status.success());
+    assert!(
+        stderr(&output).contains("Could not reach stream host http://127.0.0.1:1"),
+        "{}",
+        stderr(&output)
+    );
+
+    // Falls back to the stub host, which has no stream yet
+    let hosts = format!("http://127.0.0.1:1,http://{}", addr);
+    let output = lazystream_with_hosts(addr, &hosts, &args);
+    assert!(!output.status.success());
+    assert!(
+        stderr(&output).contains("Stream not available yet"),
+        "{}",
+        stderr(&output)
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn gives_up_after_deadline() {
+    let addr = start_server();
+    let dir = out_dir("gives-up-after-deadline");
+
+    let output = lazystream(
+        addr,
+        &[
+            "record",
+            "team",
+            "VGK",
+            dir.to_str().unwrap(),
+            "--feed-type",
+            "AWAY",
+            "--backend",
+            "native",
+            "--give-up-after",
+            "60",
+        ],
+    );
+
+    assert!(!output.status.success());
+    assert!(
+        stderr(&output).contains("Stream still not available 60 minutes after the game started"),
+        "{}",
+        stderr(&output)
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn teams_playing_each_other_are_followed_once() {
+    let addr = start_server();
+    let dir = out_dir("teams-playing-each-other-are-followed-once");
+
+    let output = lazystream(
+        addr,
+        &[
+            "record",
+            "team",
+            "vgk,Bruins",
+            dir.to_str().unwrap(),
+            "--backend",
+            "native",
+            "--feed-type",
+            "AWAY",
+            "--disable-retry",
+        ],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(stdout.contains("Found matching team for vgk: Vegas Golden Knights"));
+    assert!(stdout.contains("Found matching team for Bruins: Boston Bruins"));
+    assert_eq!(stdout.matches("Using stream feed AWAY").count(), 1);
+    assert!(!output.status.success());
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn records_both_games_of_doubleheader() {
+    let addr = start_server();
+    let dir = out_dir("records-both-games-of-doubleheader");
+
+    let output = lazystream(
+        addr,
+        &[
+            "record",
+            "team",
+            "NYY",
+            dir.to_str().unwrap(),
+            "--sport",
+            "mlb",
+            "--backend",
+            "native",
+            "--disable-retry",
+        ],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = stderr(&output);
+
+    assert!(stdout.contains("Game 1 found for today"), "{}", stdout);
+    assert!(stdout.contains("Game 2 found for today"), "{}", stdout);
+
+    // Neither stream is live, game 2 is only tried once game 1 is done with
+    assert!(!output.status.success());
+    let game_1 = stderr
+        .find("New York Yankees @ Boston Red Sox (Game 1) AWAY")
+        .unwrap();
+    let game_2 = stderr
+        .find("New York Yankees @ Boston Red Sox (Game 2) AWAY")
+        .unwrap();
+    assert!(game_1 < game_2, "{}", stderr);
+    assert!(stderr.contains("2 of 2 recordings failed"), "{}", stderr);
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn picks_game_of_doubleheader() {
+    let addr = start_server();
+    let dir = out_dir("picks-game-of-doubleheader");
+
+    let output = lazystream(
+        addr,
+        &[
+            "record",
+            "team",
+            "NYY",
+            dir.to_str().unwrap(),
+            "--sport",
+            "mlb",
+            "--game-number",
+            "2",
+            "--backend",
+            "native",
+            "--disable-retry",
+        ],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(stdout.contains("Game 2 found for today"), "{}", stdout);
+    assert!(!stdout.contains("Game 1 found for today"), "{}", stdout);
+    assert!(
+        stderr(&output).contains("Stream not available yet"),
+        "{}",
+        stderr(&output)
+    );
+
+    let output = lazystream(
+        addr,
+        &[
+            "record",
+            "team",
+            "NYY",
+            dir.to_str().unwrap(),
+            "--sport",
+            "mlb",
+            "--game-number",
+            "3",
+            "--backend",
+            "native",
+        ],
+    );
+    assert!(
+        stderr(&output).contains("There is no game 3 today for New York Yankees"),
+        "{}",
+        stderr(&output)
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn postponed_game_is_not_waited_for() {
+    let addr = start_server();
+    let dir = out_dir("postponed-game-is-not-waited-for");
+
+    let output = lazystream(
+        addr,
+        &[
+            "record",
+            "team",
+            "CHC",
+            dir.to_str().unwrap(),
+            "--sport",
+            "mlb",
+            "--backend",
+            "native",
+        ],
+    );
+
+    assert!(!output.status.success());
+    assert!(
+        stderr(&output).contains("Chicago Cubs @ St. Louis Cardinals is postponed"),
+        "{}",
+        stderr(&output)
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn generate_excludes_postponed_games() {
+    let addr = start_server();
+    let dir = out_dir("postponed");
+    let file = dir.join("games");
+
+    let output = lazystream(
+        addr,
+        &[
+            "generate",
+            "playlist",
+            file.to_str().unwrap(),
+            "--sport",
+            "mlb",
+        ],
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    let m3u = fs::read_to_string(dir.join("games.m3u")).unwrap();
+    assert!(m3u.contains("Cubs @ Cardinals"), "{}", m3u);
+
+    let output = lazystream(
+        addr,
+        &[
+            "generate",
+            "playlist",
+            file.to_str().unwrap(),
+            "--sport",
+            "mlb",
+            "--exclude-postponed",
+        ],
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    let m3u = fs::read_to_string(dir.join("games.m3u")).unwrap();
+    assert!(!m3u.contains("Cubs @ Cardinals"), "{}", m3u);
+    assert!(m3u.contains("Yankees @ Red Sox"), "{}", m3u);
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn scores_are_hidden_by_default() {
+    let addr = start_server();
+
+    let output = lazystream(addr, &["scores", "--sport", "mlb"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(stdout.contains(
+        "5:05 PM - New York Yankees @ Boston Red Sox (Game 1) - Live\n\
+         9:10 PM - New York Yankees @ Boston Red Sox (Game 2)\n\
+         11:15 PM - Chicago Cubs @ St. Louis Cardinals - Postponed\n"
+    ));
+    assert!(stdout.contains("Scores are hidden"), "{}", stdout);
+
+    let output = lazystream(addr, &["scores", "--sport", "mlb", "--spoilers"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(
+        stdout.contains("New York Yankees @ Boston Red Sox (Game 1) - Live 3-1, Top 5th\n"),
+        "{}",
+        stdout
+    );
+    assert!(!stdout.contains("Scores are hidden"), "{}", stdout);
+}
+
+#[test]
+fn xmltv_never_has_spoilers() {
+    let addr = start_server();
+    let dir = out_dir("spoilers");
+
+    let output = lazystream(
+        addr,
+        &[
+            "generate",
+            "xmltv",
+            dir.join("guide").to_str().unwrap(),
+            "--sport",
+            "mlb",
+            "--spoilers",
+        ],
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    let xmltv = fs::read_to_string(dir.join("guide.xml")).unwrap();
+    assert!(xmltv.contains("Yankees"), "{}", xmltv);
+    assert!(!xmltv.contains("3-1"), "{}", xmltv);
+    assert!(!xmltv.contains("Top 5th"), "{}", xmltv);
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn config_supplies_defaults() {
+    let addr = start_server();
+    let dir = out_dir("config");
+    let config = dir.join("config.toml");
+    let file = dir.join("games");
+    fs::write(
+        &config,
+        "sport = \"mlb\"\nquality = \"720p\"\n\n[generate]\nexclude-postponed = true\n",
+    )
+    .unwrap();
+
+    let output = lazystream(
+        addr,
+        &[
+            "generate",
+            "playlist",
+            file.to_str().unwrap(),
+            "--config",
+            config.to_str().unwrap(),
+        ],
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    let m3u = fs::read_to_string(dir.join("games.m3u")).unwrap();
+    assert!(m3u.contains("Yankees @ Red Sox"), "{}", m3u);
+    assert!(!m3u.contains("Cubs @ Cardinals"), "{}", m3u);
+
+    let output = lazystream(
+        addr,
+        &[
+            "generate",
+            "playlist",
+            file.to_str().unwrap(),
+            "--config",
+            config.to_str().unwrap(),
+            "--no-exclude-postponed",
+        ],
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    let m3u = fs::read_to_string(dir.join("games.m3u")).unwrap();
+    assert!(m3u.contains("Cubs @ Cardinals"), "{}", m3u);
+
+    let output = lazystream(
+        addr,
+        &[
+            "config",
+            "show",
+            "--config",
+            config.to_str().unwrap(),
+            "--quality",
+            "1080p",
+        ],
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("sport = \"mlb\""), "{}", stdout);
+    assert!(stdout.contains("quality = \"1080p\""), "{}", stdout);
+
+    let output = lazystream(
+        addr,
+        &[
+            "config",
+            "show",
+            "--config",
+            config.to_str().unwrap(),
+            "--",
+            "generate",
+            "playlist",
+            file.to_str().unwrap(),
+            "--no-exclude-postponed",
+        ],
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("sport = \"mlb\""), "{}", stdout);
+    assert!(stdout.contains("exclude-postponed = false"), "{}", stdout);
+
+    let output = lazystream(addr, &["config", "show", "--config", "/nonexistent.toml"]);
+    assert!(!output.status.success());
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn profile_is_merged_over_config() {
+    let addr = start_server();
+    let dir = out_dir("profile");
+    let config = dir.join("config.toml");
+    fs::write(
+        &config,
+        "cdn = \"l3c\"\nquality = \"720p\"\n\n[profile.basement]\nquality = \"best\"\n",
+    )
+    .unwrap();
+    let config = config.to_str().unwrap();
+
+    let output = lazystream(
+        addr,
+        &[
+            "config",
+            "show",
+            "--config",
+            config,
+            "--profile",
+            "basement",
+        ],
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("with profile basement"), "{}", stdout);
+    assert!(stdout.contains("cdn = \"l3c\""), "{}", stdout);
+    assert!(stdout.contains("quality = \"best\""), "{}", stdout);
+
+    let output = lazystream(
+        addr,
+        &["config", "show", "--config", config, "--profile", "attic"],
+    );
+    assert!(!output.status.success());
+    assert!(
+        stderr(&output).contains("use one of: basement"),
+        "{}",
+        stderr(&output)
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn host_check() {
+    let addr = start_server();
+
+    let output = lazystream(addr, &["host", "check"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}{}", stdout, stderr(&output));
+    assert!(
+        stdout.contains("NHL akc   OK, Vegas Golden Knights @ Boston Bruins HOME"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("All 4 host checks passed"), "{}", stdout);
+
+    // Game 1 of the doubleheader is live, but the host has no link for it
+    let output = lazystream(addr, &["host", "check", "--sport", "mlb"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!output.status.success());
+    assert!(stdout.contains("MLB l3c   Not available"), "{}", stdout);
+    assert!(
+        stderr(&output).contains("2 of 4 host checks failed"),
+        "{}",
+        stderr(&output)
+    );
+
+    let output = lazystream_with_hosts(addr, "http://127.0.0.1:1", &["host", "check"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!output.status.success());
+    assert!(stdout.contains("HTTP      Unreachable"), "{}", stdout);
+
+    // Without games no stream is requested, which doesn't show the host works,
+    // but isn't a failure either
+    let dir = out_dir("host-check");
+    let schedule = dir.join("schedule.json");
+    fs::write(&schedule, r#"{ "date": "2021-04-01", "games": [] }"#).unwrap();
+
+    let output = lazystream(
+        addr,
+        &[
+            "host",
+            "check",
+            "--schedule-file",
+            schedule.to_str().unwrap(),
+        ],
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(
+        stdout.contains("NHL: No games today to check streams with"),
+        "{}",
+        stdout
+    );
+    assert!(
+        stdout.contains("2 of 3 host checks passed, 1 couldn't be verified"),
+        "{}",
+        stdout
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn auto_cdn() {
+    let addr = start_server();
+    let dir = out_dir("auto-cdn");
+
+    let output = lazystream(
+        addr,
+        &[
+            "generate",
+            "playlist",
+            dir.join("games").to_str().unwrap(),
+            "--cdn",
+            "auto",
+        ],
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Using CDN "), "{}", stdout);
+    assert!(stdout.contains("akc took"), "{}", stdout);
+    assert!(stdout.contains("l3c took"), "{}", stdout);
+
+    let m3u = fs::read_to_string(dir.join("games.m3u")).unwrap();
+    assert!(
+        m3u.contains(&format!("http://{}/master/80123.m3u8", addr)),
+        "{}",
+        m3u
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn multi_day_guide() {
+    let addr = start_server();
+    let dir = out_dir("multi-day");
+
+    let output = lazystream(
+        addr,
+        &[
+            "generate",
+            "xmltv",
+            dir.join("guide").to_str().unwrap(),
+            "--trim",
+            "--days",
+            "2",
+        ],
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    // Channels play the first day, with as many as the busiest day needs
+    let m3u = fs::read_to_string(dir.join("guide.m3u")).unwrap();
+    let numbers: Vec<_> = m3u
+        .lines()
+        .filter_map(|line| line.split("CUID=\"").nth(1))
+        .map(|rest| rest.split('"').next().unwrap())
+        .collect();
+    assert_eq!(numbers, ["1000", "1001"], "{}", m3u);
+
+    let xml = fs::read_to_string(dir.join("guide.xml")).unwrap();
+    let attributes = |prefix: &str| -> Vec<String> {
+        xml.split(prefix)
+            .skip(1)
+            .map(|rest| rest.split('"').next().unwrap().to_owned())
+            .collect()
+    };
+    assert_eq!(attributes("<channel id=\""), ["1000", "1001", "1002"]);
+
+    // Both days are in the guide, on the same channels
+    assert_eq!(
+        attributes("<programme channel=\""),
+        ["1000", "1001", "1000", "1001", "1002"]
+    );
+    assert!(
+        xml.contains("Bruins @ Golden Knights (NATIONAL)"),
+        "{}",
+        xml
+    );
+
+    fs::remove_dir_all(dir).unwrap();
+}